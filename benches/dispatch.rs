@@ -0,0 +1,48 @@
+use criterion::{criterion_group, criterion_main, Criterion};
+use rust_game_boy_emulator::gameboy::{Dispatch, Gameboy};
+
+/// A tight loop in WRAM mixing loads, ALU, CB-prefixed and jump instructions.
+const PROGRAM: [u8; 18] = [
+    0x21, 0x00, 0xC1, // LD HL,$C100
+    0x06, 0x10, // LD B,$10
+    0x2A, // loop: LD A,(HL+)
+    0x80, // ADD A,B
+    0xA9, // XOR C
+    0x4F, // LD C,A
+    0xCB, 0x37, // SWAP A
+    0xCB, 0x7F, // BIT 7,A
+    0x05, // DEC B
+    0x20, 0xF5, // JR NZ,loop
+    0x18, 0xEE, // JR $C000
+];
+
+const INSTRUCTIONS: usize = 10_000;
+
+fn gameboy<'a>(dispatch: Dispatch) -> Gameboy<'a> {
+    let mut gameboy = Gameboy::default();
+    gameboy.dispatch = dispatch;
+    for (offset, byte) in PROGRAM.iter().enumerate() {
+        gameboy.write_byte(0xC000 + offset as u16, *byte);
+    }
+    gameboy.cpu.registers.pc = 0xC000;
+    gameboy.cpu.registers.sp = 0xD000;
+    gameboy
+}
+
+fn bench_dispatch(c: &mut Criterion) {
+    let mut group = c.benchmark_group("dispatch");
+    for (name, dispatch) in [("table", Dispatch::Table), ("match", Dispatch::Match)] {
+        let mut gameboy = gameboy(dispatch);
+        group.bench_function(name, |b| {
+            b.iter(|| {
+                for _ in 0..INSTRUCTIONS {
+                    gameboy.run_next_instruction();
+                }
+            })
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_dispatch);
+criterion_main!(benches);
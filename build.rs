@@ -0,0 +1,68 @@
+//! Turns `src/Opcodes.json` into static tables indexed by opcode so nothing has
+//! to be parsed at runtime. See `src/opcode_info.rs` for the types.
+
+use std::fmt::Write;
+use std::path::Path;
+
+fn operand(operand: &serde_json::Value) -> String {
+    format!(
+        "OperandInformation {{ name: {:?}, immediate: {}, bytes: {}, increment: {}, decrement: {} }}",
+        operand["name"].as_str().unwrap(),
+        operand["immediate"].as_bool().unwrap(),
+        operand["bytes"].as_u64().unwrap_or(0),
+        operand["increment"].as_bool().unwrap_or(false),
+        operand["decrement"].as_bool().unwrap_or(false),
+    )
+}
+
+fn table(name: &str, opcodes: &serde_json::Value) -> String {
+    let mut out = format!("pub static {}: [Opcode; 256] = [\n", name);
+    for byte in 0..=0xFF {
+        let opcode = &opcodes[format!("0x{:02X}", byte)];
+        let cycles = opcode["cycles"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|cycles| cycles.as_u64().unwrap().to_string())
+            .collect::<Vec<_>>()
+            .join(", ");
+        let operands = opcode["operands"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(operand)
+            .collect::<Vec<_>>()
+            .join(", ");
+        let flags = &opcode["flags"];
+        writeln!(
+            out,
+            "    Opcode {{ mnemonic: {:?}, bytes: {}, cycles: &[{}], operands: &[{}], immediate: {}, \
+             flags: FlagInformation {{ z: {:?}, n: {:?}, h: {:?}, c: {:?} }} }},",
+            opcode["mnemonic"].as_str().unwrap(),
+            opcode["bytes"].as_u64().unwrap(),
+            cycles,
+            operands,
+            opcode["immediate"].as_bool().unwrap(),
+            flags["Z"].as_str().unwrap(),
+            flags["N"].as_str().unwrap(),
+            flags["H"].as_str().unwrap(),
+            flags["C"].as_str().unwrap(),
+        )
+        .unwrap();
+    }
+    out.push_str("];\n");
+    out
+}
+
+fn main() {
+    println!("cargo:rerun-if-changed=src/Opcodes.json");
+
+    let json: serde_json::Value =
+        serde_json::from_slice(&std::fs::read("src/Opcodes.json").unwrap()).unwrap();
+
+    let mut out = table("UNPREFIXED", &json["unprefixed"]);
+    out.push_str(&table("CBPREFIXED", &json["cbprefixed"]));
+
+    let path = Path::new(&std::env::var("OUT_DIR").unwrap()).join("opcodes.rs");
+    std::fs::write(path, out).unwrap();
+}
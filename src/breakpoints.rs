@@ -0,0 +1,403 @@
+//! Execute breakpoints, watchpoints and interrupt breakpoints, checked by the
+//! CPU as it runs. A hit pauses emulation, so this works without a frontend.
+
+use std::ops::RangeInclusive;
+
+use crate::cpu::Registers;
+use crate::debugger::{parse_number, Register};
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum Comparison {
+    Equal,
+    NotEqual,
+    Less,
+    LessOrEqual,
+    Greater,
+    GreaterOrEqual,
+}
+
+impl Comparison {
+    /// Longest operators first so `<=` isn't taken for `<`.
+    const OPERATORS: [(&'static str, Comparison); 6] = [
+        ("==", Comparison::Equal),
+        ("!=", Comparison::NotEqual),
+        ("<=", Comparison::LessOrEqual),
+        (">=", Comparison::GreaterOrEqual),
+        ("<", Comparison::Less),
+        (">", Comparison::Greater),
+    ];
+
+    fn compare(self, left: u16, right: u16) -> bool {
+        match self {
+            Comparison::Equal => left == right,
+            Comparison::NotEqual => left != right,
+            Comparison::Less => left < right,
+            Comparison::LessOrEqual => left <= right,
+            Comparison::Greater => left > right,
+            Comparison::GreaterOrEqual => left >= right,
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone)]
+enum Operand {
+    Register(Register),
+    Value(u16),
+}
+
+impl Operand {
+    fn parse(text: &str) -> Result<Operand, String> {
+        match Register::parse(text) {
+            Some(register) => Ok(Operand::Register(register)),
+            None => parse_number(text).map(Operand::Value),
+        }
+    }
+
+    fn value(self, registers: &Registers) -> u16 {
+        match self {
+            Operand::Register(register) => register.get(registers),
+            Operand::Value(value) => value,
+        }
+    }
+}
+
+/// A boolean expression over registers, like `A == 0x3F && HL > 0xC000`.
+/// `&&` binds tighter than `||`; there are no parentheses.
+#[derive(Debug, Clone)]
+pub struct Condition {
+    text: String,
+    any_of: Vec<Vec<(Operand, Comparison, Operand)>>,
+}
+
+impl Condition {
+    pub fn parse(text: &str) -> Result<Condition, String> {
+        let any_of = text
+            .split("||")
+            .map(|all| all.split("&&").map(Condition::parse_comparison).collect())
+            .collect::<Result<_, _>>()?;
+        Ok(Condition {
+            text: text.trim().to_string(),
+            any_of,
+        })
+    }
+
+    fn parse_comparison(text: &str) -> Result<(Operand, Comparison, Operand), String> {
+        let (operator, comparison) = Comparison::OPERATORS
+            .into_iter()
+            .find(|(operator, _)| text.contains(operator))
+            .ok_or_else(|| format!("Missing comparison in '{}'", text.trim()))?;
+        let (left, right) = text.split_once(operator).unwrap();
+        Ok((
+            Operand::parse(left.trim())?,
+            comparison,
+            Operand::parse(right.trim())?,
+        ))
+    }
+
+    pub fn evaluate(&self, registers: &Registers) -> bool {
+        self.any_of.iter().any(|all| {
+            all.iter().all(|(left, comparison, right)| {
+                comparison.compare(left.value(registers), right.value(registers))
+            })
+        })
+    }
+}
+
+impl std::fmt::Display for Condition {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.text)
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Access {
+    Read,
+    Write,
+    ReadWrite,
+}
+
+#[derive(Debug, Clone)]
+pub enum BreakpointKind {
+    /// Stop before the instruction at `address` executes. `bank` restricts it to
+    /// one ROM bank when set.
+    Execute { address: u16, bank: Option<u8> },
+    /// Stop after an instruction accesses an address in `range`.
+    Watch {
+        range: RangeInclusive<u16>,
+        access: Access,
+    },
+    /// Stop after dispatching one of the interrupts in `mask`, laid out like IF.
+    Interrupt { mask: u8 },
+}
+
+#[derive(Debug, Clone)]
+pub struct Breakpoint {
+    pub kind: BreakpointKind,
+    pub condition: Option<Condition>,
+}
+
+impl Breakpoint {
+    pub fn new(kind: BreakpointKind) -> Breakpoint {
+        Breakpoint {
+            kind,
+            condition: None,
+        }
+    }
+
+    pub fn with_condition(mut self, condition: Condition) -> Breakpoint {
+        self.condition = Some(condition);
+        self
+    }
+
+    fn condition_holds(&self, registers: &Registers) -> bool {
+        self.condition
+            .as_ref()
+            .is_none_or(|condition| condition.evaluate(registers))
+    }
+}
+
+impl std::fmt::Display for Breakpoint {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.kind {
+            BreakpointKind::Execute {
+                address,
+                bank: Some(bank),
+            } => write!(f, "execute {:02X}:{:04X}", bank, address)?,
+            BreakpointKind::Execute {
+                address,
+                bank: None,
+            } => write!(f, "execute {:04X}", address)?,
+            BreakpointKind::Watch { range, access } => {
+                write!(f, "{:?} {:04X}-{:04X}", access, range.start(), range.end())?
+            }
+            BreakpointKind::Interrupt { mask } => write!(f, "interrupt {:05b}", mask)?,
+        }
+        if let Some(condition) = &self.condition {
+            write!(f, " if {}", condition)?;
+        }
+        Ok(())
+    }
+}
+
+/// Why emulation stopped.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Hit {
+    Execute { id: usize, address: u16 },
+    Read { id: usize, address: u16, value: u8 },
+    Write { id: usize, address: u16, value: u8 },
+    Interrupt { id: usize, vector: u16 },
+}
+
+impl std::fmt::Display for Hit {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Hit::Execute { id, address } => write!(f, "breakpoint {} at {:04X}", id, address),
+            Hit::Read { id, address, value } => write!(
+                f,
+                "watchpoint {}: read {:02X} from {:04X}",
+                id, value, address
+            ),
+            Hit::Write { id, address, value } => write!(
+                f,
+                "watchpoint {}: wrote {:02X} to {:04X}",
+                id, value, address
+            ),
+            Hit::Interrupt { id, vector } => {
+                write!(f, "breakpoint {}: interrupt to {:04X}", id, vector)
+            }
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct Breakpoints {
+    breakpoints: Vec<(usize, Breakpoint)>,
+    next_id: usize,
+    /// The address of the last execute breakpoint hit, so that resuming runs the
+    /// instruction there instead of stopping on it again.
+    resume_from: Option<u16>,
+    /// A watch or interrupt hit waiting for the current instruction to finish.
+    pending: Option<Hit>,
+    last_hit: Option<Hit>,
+}
+
+impl Breakpoints {
+    /// Adds a breakpoint and returns its id.
+    pub fn add(&mut self, breakpoint: Breakpoint) -> usize {
+        self.next_id += 1;
+        self.breakpoints.push((self.next_id, breakpoint));
+        self.next_id
+    }
+
+    /// Removes a breakpoint, returning whether it existed.
+    pub fn remove(&mut self, id: usize) -> bool {
+        let count = self.breakpoints.len();
+        self.breakpoints.retain(|(other, _)| *other != id);
+        self.breakpoints.len() != count
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &(usize, Breakpoint)> {
+        self.breakpoints.iter()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.breakpoints.is_empty()
+    }
+
+    /// The breakpoint that most recently stopped emulation.
+    pub fn last_hit(&self) -> Option<&Hit> {
+        self.last_hit.as_ref()
+    }
+
+    pub(crate) fn set_last_hit(&mut self, hit: Option<Hit>) {
+        self.last_hit = hit;
+    }
+
+    /// Checked before the instruction at `address` is fetched.
+    pub(crate) fn check_execute(
+        &mut self,
+        registers: &Registers,
+        address: u16,
+        bank: u8,
+    ) -> Option<Hit> {
+        if self.resume_from.take() == Some(address) {
+            return None;
+        }
+        let id = self.find(registers, |kind| match kind {
+            BreakpointKind::Execute {
+                address: other,
+                bank: other_bank,
+            } => *other == address && other_bank.is_none_or(|other| other == bank),
+            _ => false,
+        })?;
+        self.resume_from = Some(address);
+        Some(Hit::Execute { id, address })
+    }
+
+    pub(crate) fn check_access(
+        &mut self,
+        registers: &Registers,
+        address: u16,
+        value: u8,
+        write: bool,
+    ) {
+        if self.pending.is_some() {
+            return;
+        }
+        let id = self.find(registers, |kind| match kind {
+            BreakpointKind::Watch { range, access } => {
+                range.contains(&address)
+                    && match access {
+                        Access::Read => !write,
+                        Access::Write => write,
+                        Access::ReadWrite => true,
+                    }
+            }
+            _ => false,
+        });
+        self.pending = id.map(|id| match write {
+            true => Hit::Write { id, address, value },
+            false => Hit::Read { id, address, value },
+        });
+    }
+
+    pub(crate) fn check_interrupt(&mut self, registers: &Registers, interrupt: u8, vector: u16) {
+        let id = self.find(registers, |kind| match kind {
+            BreakpointKind::Interrupt { mask } => mask & interrupt != 0,
+            _ => false,
+        });
+        if let Some(id) = id {
+            self.pending.get_or_insert(Hit::Interrupt { id, vector });
+        }
+    }
+
+    pub(crate) fn take_pending(&mut self) -> Option<Hit> {
+        self.pending.take()
+    }
+
+    fn find(
+        &self,
+        registers: &Registers,
+        matches: impl Fn(&BreakpointKind) -> bool,
+    ) -> Option<usize> {
+        self.breakpoints
+            .iter()
+            .find(|(_, breakpoint)| {
+                matches(&breakpoint.kind) && breakpoint.condition_holds(registers)
+            })
+            .map(|(id, _)| *id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_condition() {
+        let condition = Condition::parse("A == 0x3F && HL > 0xC000").unwrap();
+        let mut registers = Registers {
+            a: 0x3F,
+            h: 0xC1,
+            ..Default::default()
+        };
+        assert!(condition.evaluate(&registers));
+        registers.h = 0x80;
+        assert!(!condition.evaluate(&registers));
+
+        let condition = Condition::parse("b != 0 || cf == 1").unwrap();
+        assert!(!condition.evaluate(&registers));
+        registers.f.carry = true;
+        assert!(condition.evaluate(&registers));
+
+        assert!(Condition::parse("A = 3").is_err());
+        assert!(Condition::parse("XY == 3").is_err());
+    }
+
+    #[test]
+    fn test_execute_resumes_past_hit() {
+        let mut breakpoints = Breakpoints::default();
+        let registers = Registers::default();
+        let id = breakpoints.add(Breakpoint::new(BreakpointKind::Execute {
+            address: 0x4000,
+            bank: Some(2),
+        }));
+
+        assert_eq!(breakpoints.check_execute(&registers, 0x4000, 1), None);
+        assert_eq!(
+            breakpoints.check_execute(&registers, 0x4000, 2),
+            Some(Hit::Execute {
+                id,
+                address: 0x4000
+            })
+        );
+        assert_eq!(breakpoints.check_execute(&registers, 0x4000, 2), None);
+        assert!(breakpoints.check_execute(&registers, 0x4000, 2).is_some());
+    }
+
+    #[test]
+    fn test_watch_access_kind() {
+        let mut breakpoints = Breakpoints::default();
+        let registers = Registers::default();
+        let id = breakpoints.add(Breakpoint::new(BreakpointKind::Watch {
+            range: 0xC000..=0xC0FF,
+            access: Access::Write,
+        }));
+
+        breakpoints.check_access(&registers, 0xC010, 1, false);
+        assert_eq!(breakpoints.take_pending(), None);
+        breakpoints.check_access(&registers, 0xC100, 1, true);
+        assert_eq!(breakpoints.take_pending(), None);
+        breakpoints.check_access(&registers, 0xC010, 1, true);
+        assert_eq!(
+            breakpoints.take_pending(),
+            Some(Hit::Write {
+                id,
+                address: 0xC010,
+                value: 1
+            })
+        );
+        assert!(breakpoints.remove(id));
+        assert!(breakpoints.is_empty());
+    }
+}
@@ -0,0 +1,249 @@
+//! A shadow of the call stack, kept from CALL, RST and interrupt dispatch
+//! pushing return addresses and RET and RETI popping them, so a backtrace can
+//! be shown whenever the game stops.
+
+use std::collections::VecDeque;
+use std::fmt::Write;
+
+use crate::profiler::Location;
+use crate::symbols::Symbols;
+
+/// Frames deeper than this are assumed abandoned and the outermost is dropped.
+const MAX_DEPTH: usize = 256;
+/// How many of the latest mismatches are kept.
+const MAX_MISMATCHES: usize = 16;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum FrameKind {
+    Call,
+    Rst,
+    Interrupt,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Frame {
+    pub kind: FrameKind,
+    /// The CALL or RST, or the instruction an interrupt came before.
+    pub site: Location,
+    pub target: Location,
+    pub return_address: u16,
+    /// Where the return address was pushed.
+    pub sp: u16,
+}
+
+/// Stack manipulation that broke the pairing of calls and returns.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Mismatch {
+    /// A return to an address no call pushed, e.g. pushing an address and
+    /// returning to jump there.
+    Unpaired { at: Location, target: u16 },
+    /// A return that skipped frames because their return addresses were
+    /// popped or overwritten.
+    Skipped {
+        at: Location,
+        target: u16,
+        frames: usize,
+    },
+    /// Frames dropped because SP moved past their return addresses without
+    /// returning, e.g. by loading SP.
+    Discarded { at: Location, frames: usize },
+}
+
+impl std::fmt::Display for Mismatch {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Mismatch::Unpaired { at, target } => write!(
+                f,
+                "return at {:04X} to {:04X} matches no call",
+                at.address, target
+            ),
+            Mismatch::Skipped { at, target, frames } => write!(
+                f,
+                "return at {:04X} to {:04X} skipped {} frame(s)",
+                at.address, target, frames
+            ),
+            Mismatch::Discarded { at, frames } => write!(
+                f,
+                "{} frame(s) abandoned by the stack moving before {:04X}",
+                frames, at.address
+            ),
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct CallStack {
+    frames: Vec<Frame>,
+    mismatches: VecDeque<Mismatch>,
+}
+
+impl CallStack {
+    /// Outermost first.
+    pub fn frames(&self) -> &[Frame] {
+        &self.frames
+    }
+
+    /// The latest mismatches, oldest first.
+    pub fn mismatches(&self) -> impl Iterator<Item = &Mismatch> {
+        self.mismatches.iter()
+    }
+
+    fn record(&mut self, mismatch: Mismatch) {
+        log::debug!("Call stack mismatch: {}", mismatch);
+        if self.mismatches.len() == MAX_MISMATCHES {
+            self.mismatches.pop_front();
+        }
+        self.mismatches.push_back(mismatch);
+    }
+
+    /// Drops frames whose return addresses are above SP, as those have been
+    /// popped or overwritten since.
+    fn discard_above(&mut self, at: Location, sp: u16) {
+        let live = self
+            .frames
+            .iter()
+            .take_while(|frame| frame.sp >= sp)
+            .count();
+        if live < self.frames.len() {
+            let frames = self.frames.len() - live;
+            self.frames.truncate(live);
+            self.record(Mismatch::Discarded { at, frames });
+        }
+    }
+
+    pub(crate) fn push(&mut self, frame: Frame) {
+        // the new return address overwrote anything pushed at or below it
+        self.discard_above(frame.site, frame.sp.wrapping_add(1));
+        if self.frames.len() == MAX_DEPTH {
+            self.frames.remove(0);
+        }
+        self.frames.push(frame);
+    }
+
+    /// Handles a return at `at` that popped `target` from `sp`.
+    pub(crate) fn pop(&mut self, at: Location, sp: u16, target: u16) {
+        self.discard_above(at, sp);
+        let matching = self
+            .frames
+            .iter()
+            .rposition(|frame| frame.sp == sp && frame.return_address == target)
+            .or_else(|| {
+                self.frames
+                    .iter()
+                    .rposition(|frame| frame.return_address == target)
+            });
+        match matching {
+            Some(index) => {
+                let skipped = self.frames.len() - 1 - index;
+                if skipped > 0 {
+                    self.record(Mismatch::Skipped {
+                        at,
+                        target,
+                        frames: skipped,
+                    });
+                }
+                self.frames.truncate(index);
+            }
+            None => self.record(Mismatch::Unpaired { at, target }),
+        }
+    }
+
+    /// The frames innermost first, starting at `pc`, e.g.
+    /// `#1  C003  Main+$3  (call Sub)`, followed by recent mismatches.
+    pub fn backtrace(&self, pc: Location, symbols: &Symbols) -> String {
+        let describe = |location: Location| symbols.describe(location.bank, location.address);
+        let mut out = format!("#0  {:04X}  {}\n", pc.address, describe(pc));
+        for (depth, frame) in self.frames.iter().rev().enumerate() {
+            let kind = match frame.kind {
+                FrameKind::Call => "call",
+                FrameKind::Rst => "rst",
+                FrameKind::Interrupt => "interrupt",
+            };
+            writeln!(
+                out,
+                "#{:<2} {:04X}  {}  ({} {})",
+                depth + 1,
+                frame.site.address,
+                describe(frame.site),
+                kind,
+                describe(frame.target)
+            )
+            .unwrap();
+        }
+        if !self.mismatches.is_empty() {
+            writeln!(out, "Recent call stack mismatches:").unwrap();
+            for mismatch in &self.mismatches {
+                writeln!(out, "  {}", mismatch).unwrap();
+            }
+        }
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gameboy::gameboy_with_program;
+
+    #[test]
+    fn test_backtrace() {
+        // CALL $C010; ...; $C010: RST $38 lands in zeroed memory
+        let mut program = [0; 0x11];
+        program[..3].copy_from_slice(&[0xCD, 0x10, 0xC0]);
+        program[0x10] = 0xFF;
+        let mut gameboy = gameboy_with_program(&program);
+        gameboy.bus.boot_rom_enabled = false;
+        gameboy.bus.cartridge_rom = vec![0; 0x8000].leak();
+        gameboy.symbols = Symbols::parse("00:c000 Main\n00:c010 Sub\n");
+        gameboy.step().unwrap();
+        gameboy.step().unwrap();
+        gameboy.step().unwrap();
+
+        assert_eq!(
+            gameboy.backtrace(),
+            "#0  0039  $0039\n\
+             #1  C010  Sub  (rst $0038)\n\
+             #2  C000  Main  (call Sub)\n"
+        );
+    }
+
+    #[test]
+    fn test_returns() {
+        // CALL $C010; RET; ...; $C010: RET
+        let mut program = [0; 0x11];
+        program[..4].copy_from_slice(&[0xCD, 0x10, 0xC0, 0xC9]);
+        program[0x10] = 0xC9;
+        let mut gameboy = gameboy_with_program(&program);
+        gameboy.step().unwrap();
+        gameboy.step().unwrap();
+        assert!(gameboy.call_stack.frames().is_empty());
+        assert_eq!(gameboy.call_stack.mismatches().count(), 0);
+
+        // the outer RET pops whatever is above SP, which no call pushed
+        gameboy.step().unwrap();
+        assert_eq!(
+            gameboy.call_stack.mismatches().last().unwrap().to_string(),
+            "return at C003 to 0000 matches no call"
+        );
+    }
+
+    #[test]
+    fn test_popped_return_address() {
+        // CALL $C010; ...; $C010: CALL $C020; $C020: POP HL; RET
+        let mut program = [0; 0x22];
+        program[..3].copy_from_slice(&[0xCD, 0x10, 0xC0]);
+        program[0x10..0x13].copy_from_slice(&[0xCD, 0x20, 0xC0]);
+        program[0x20..].copy_from_slice(&[0xE1, 0xC9]);
+        let mut gameboy = gameboy_with_program(&program);
+        for _ in 0..4 {
+            gameboy.step().unwrap();
+        }
+
+        assert_eq!(gameboy.cpu.registers.pc, 0xC003);
+        assert!(gameboy.call_stack.frames().is_empty());
+        assert_eq!(
+            gameboy.call_stack.mismatches().last().unwrap().to_string(),
+            "1 frame(s) abandoned by the stack moving before C021"
+        );
+    }
+}
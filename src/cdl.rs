@@ -0,0 +1,104 @@
+//! A Code/Data Log: one byte of flags per ROM byte recording how the game
+//! accessed it, saved as a `.cdl` file next to the ROM so coverage adds up
+//! across sessions and the disassembler can tell code from data.
+
+use std::path::Path;
+
+/// The first byte of an executed instruction.
+pub const OPCODE: u8 = 0x01;
+/// Any later byte of an executed instruction, including the second byte of
+/// a 0xCB-prefixed one.
+pub const OPERAND: u8 = 0x02;
+/// Read by an instruction as data.
+pub const DATA: u8 = 0x04;
+/// Copied to OAM by DMA.
+pub const DMA: u8 = 0x08;
+
+/// The ROM size the cartridge header declares at 0x148, which is how much of
+/// the 2 MiB cartridge buffer is actually ROM.
+pub fn rom_size(cartridge: &[u8]) -> usize {
+    match cartridge.get(0x148) {
+        Some(&code) if code <= 8 => (0x8000 << code).min(cartridge.len()),
+        _ => 0x8000.min(cartridge.len()),
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CodeDataLog {
+    flags: Vec<u8>,
+}
+
+impl CodeDataLog {
+    pub fn new(rom_size: usize) -> CodeDataLog {
+        CodeDataLog {
+            flags: vec![0; rom_size],
+        }
+    }
+
+    /// Continues the log in `path` if there is one, so flags accumulate.
+    pub fn load_or_new(path: impl AsRef<Path>, rom_size: usize) -> std::io::Result<CodeDataLog> {
+        match std::fs::read(path) {
+            Ok(mut flags) => {
+                flags.resize(rom_size, 0);
+                Ok(CodeDataLog { flags })
+            }
+            Err(error) if error.kind() == std::io::ErrorKind::NotFound => {
+                Ok(CodeDataLog::new(rom_size))
+            }
+            Err(error) => Err(error),
+        }
+    }
+
+    /// Loads the `.cdl` file next to `rom`, if there is one.
+    pub fn for_rom(rom: impl AsRef<Path>) -> Option<CodeDataLog> {
+        let flags = std::fs::read(rom.as_ref().with_extension("cdl")).ok()?;
+        Some(CodeDataLog { flags })
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> std::io::Result<()> {
+        std::fs::write(path, &self.flags)
+    }
+
+    /// Adds `flag` to the byte at `offset` in the ROM file.
+    pub fn mark(&mut self, offset: usize, flag: u8) {
+        if let Some(flags) = self.flags.get_mut(offset) {
+            *flags |= flag;
+        }
+    }
+
+    /// The flags of the byte at `offset`, 0 if it was never accessed.
+    pub fn flags(&self, offset: usize) -> u8 {
+        self.flags.get(offset).copied().unwrap_or(0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rom_size() {
+        let mut cartridge = vec![0; 0x200000];
+        assert_eq!(rom_size(&cartridge), 0x8000);
+        cartridge[0x148] = 0x03;
+        assert_eq!(rom_size(&cartridge), 0x40000);
+        cartridge[0x148] = 0x52;
+        assert_eq!(rom_size(&cartridge), 0x8000);
+    }
+
+    #[test]
+    fn test_load_merges_with_saved_log() {
+        let path = std::env::temp_dir().join(format!("cdl-test-{}.cdl", std::process::id()));
+        let mut log = CodeDataLog::load_or_new(&path, 4).unwrap();
+        log.mark(1, OPCODE);
+        log.mark(1, DATA);
+        log.mark(10, OPCODE);
+        log.save(&path).unwrap();
+
+        let mut log = CodeDataLog::load_or_new(&path, 4).unwrap();
+        log.mark(2, DMA);
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(log.flags, [0, OPCODE | DATA, DMA, 0]);
+        assert_eq!(log.flags(10), 0);
+    }
+}
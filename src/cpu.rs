@@ -170,4 +170,6 @@ impl std::convert::From<u8> for FlagsRegister {
 pub struct CPU {
     pub registers: Registers,
     pub halted: bool,
+    /// Set after decoding an illegal opcode; the CPU stops fetching until reset.
+    pub locked: bool,
 }
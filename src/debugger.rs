@@ -0,0 +1,735 @@
+//! Interactive command line debugger, entered whenever emulation is paused.
+
+use std::io::{BufRead, Write};
+
+use crate::breakpoints::{Access, Breakpoint, BreakpointKind, Condition};
+use crate::cpu::{Register16bTarget, RegisterTarget, Registers};
+use crate::gameboy::Gameboy;
+use crate::io_viewer::{self, Register as IoRegister};
+use crate::oam_viewer;
+use crate::symbols::Symbols;
+
+/// How long `next` and `finish` may run before giving up: one second of emulated time.
+const RUN_LIMIT: u64 = 70224 * 60;
+
+const HELP: &str = "\
+step [N]          execute N instructions (default 1)
+next              step over CALL and RST
+finish            run until the current routine returns
+continue          resume emulation
+regs              show registers and flags
+bt                show the call stack
+set REG VALUE     set a register (a, f, b, .., af, bc, de, hl, sp, pc) or flag (zf, nf, hf, cf)
+x ADDR [LEN]      dump LEN bytes of memory (default 0x40)
+poke ADDR VALUE   write a byte to memory
+dis [ADDR] [N]    disassemble N instructions around PC or from ADDR
+oam               list sprites with decoded attributes
+io [NAME]         decode the I/O registers, or just NAME (e.g. lcdc)
+break [BANK:]ADDR [if COND]     stop before executing ADDR
+watch START[-END] [if COND]     stop after a write to the range (rwatch: reads, awatch: both)
+ibreak [vblank|stat|timer|serial|joypad] [if COND]
+                  stop after an interrupt is dispatched (any if none given)
+info              list breakpoints
+delete ID         remove a breakpoint
+quit              exit the emulator
+Addresses may also be labels from the ROM's .sym file, optionally with an offset
+like 'Main.loop+$4'.
+Conditions compare registers and flags with numbers, e.g. 'a == 3f && hl > c000'.
+Addresses and values are hex, optionally prefixed with $ or 0x. An empty line repeats the last command.";
+
+/// Something the debugger can read and write by name.
+#[derive(Debug, Copy, Clone)]
+pub enum Register {
+    U8(RegisterTarget),
+    U16(Register16bTarget),
+    /// A flag, identified by its bit in F.
+    Flag(u8),
+}
+
+impl Register {
+    pub fn parse(name: &str) -> Option<Register> {
+        let register = match name.to_ascii_lowercase().as_str() {
+            "a" => Register::U8(RegisterTarget::A),
+            "f" => Register::U8(RegisterTarget::F),
+            "b" => Register::U8(RegisterTarget::B),
+            "c" => Register::U8(RegisterTarget::C),
+            "d" => Register::U8(RegisterTarget::D),
+            "e" => Register::U8(RegisterTarget::E),
+            "h" => Register::U8(RegisterTarget::H),
+            "l" => Register::U8(RegisterTarget::L),
+            "af" => Register::U16(Register16bTarget::AF),
+            "bc" => Register::U16(Register16bTarget::BC),
+            "de" => Register::U16(Register16bTarget::DE),
+            "hl" => Register::U16(Register16bTarget::HL),
+            "sp" => Register::U16(Register16bTarget::SP),
+            "pc" => Register::U16(Register16bTarget::PC),
+            "zf" => Register::Flag(7),
+            "nf" => Register::Flag(6),
+            "hf" => Register::Flag(5),
+            "cf" => Register::Flag(4),
+            _ => return None,
+        };
+        Some(register)
+    }
+
+    pub fn get(self, registers: &Registers) -> u16 {
+        match self {
+            Register::U8(target) => registers.get_u8(target) as u16,
+            Register::U16(target) => registers.get_u16(target),
+            Register::Flag(bit) => (registers.get_u8(RegisterTarget::F) >> bit) as u16 & 1,
+        }
+    }
+
+    pub fn set(self, gameboy: &mut Gameboy, value: u16) {
+        let registers = &mut gameboy.cpu.registers;
+        match self {
+            Register::U8(target) => registers.set_u8(target, value as u8),
+            Register::U16(target) => registers.set_u16(target, value),
+            Register::Flag(bit) => {
+                let flags = registers.get_u8(RegisterTarget::F) & !(1 << bit);
+                registers.set_u8(RegisterTarget::F, flags | ((value != 0) as u8) << bit);
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum Command {
+    Step(u32),
+    Next,
+    Finish,
+    Continue,
+    Registers,
+    Backtrace,
+    Set(Register, u16),
+    Examine { address: u16, length: u16 },
+    Poke { address: u16, value: u8 },
+    Disassemble { address: Option<u16>, count: u16 },
+    Oam,
+    Io(Option<&'static IoRegister>),
+    Break(Breakpoint),
+    Info,
+    Delete(usize),
+    Help,
+    Quit,
+}
+
+/// What the emulator should do once a command has been handled.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Resume {
+    /// Keep reading commands.
+    Prompt,
+    Continue,
+    Quit,
+}
+
+/// Parses a hex number, with or without a `$` or `0x` prefix.
+pub fn parse_number(text: &str) -> Result<u16, String> {
+    let digits = text
+        .strip_prefix('$')
+        .or_else(|| text.strip_prefix("0x"))
+        .unwrap_or(text);
+    u16::from_str_radix(digits, 16).map_err(|_| format!("Invalid number: {}", text))
+}
+
+/// Parses `[BANK:]ADDR` or a label from `symbols` with an optional `+OFFSET`,
+/// returning the bank if one was given.
+pub fn parse_location(text: &str, symbols: &Symbols) -> Result<(Option<u8>, u16), String> {
+    let (name, offset) = match text.split_once('+') {
+        Some((name, offset)) => (name, parse_number(offset)?),
+        None => (text, 0),
+    };
+    if let Some((bank, address)) = symbols.lookup(name) {
+        let bank = (address < 0x8000).then_some(bank);
+        return Ok((bank, address.wrapping_add(offset)));
+    }
+    match text.split_once(':') {
+        Some((bank, address)) => Ok((Some(parse_number(bank)? as u8), parse_number(address)?)),
+        None => Ok((None, parse_number(text)?)),
+    }
+}
+
+impl Command {
+    /// Parses a command line, resolving labels in addresses through `symbols`.
+    pub fn parse(line: &str, symbols: &Symbols) -> Result<Command, String> {
+        let (line, condition) = match line.split_once(" if ") {
+            Some((line, condition)) => (line, Some(Condition::parse(condition)?)),
+            None => (line, None),
+        };
+        let words: Vec<&str> = line.split_whitespace().collect();
+        let argument = |index: usize| -> Result<u16, String> {
+            words
+                .get(index)
+                .ok_or_else(|| "Missing argument, see 'help'".to_string())
+                .and_then(|word| parse_number(word))
+        };
+        let optional = |index: usize| words.get(index).map(|word| parse_number(word)).transpose();
+        let address = |word: &str| parse_location(word, symbols).map(|(_, address)| address);
+        let optional_address =
+            |index: usize| words.get(index).map(|word| address(word)).transpose();
+
+        let command = match words.first().copied().unwrap_or_default() {
+            "s" | "step" => Command::Step(optional(1)?.unwrap_or(1) as u32),
+            "n" | "next" => Command::Next,
+            "fin" | "finish" => Command::Finish,
+            "c" | "continue" => Command::Continue,
+            "r" | "regs" => Command::Registers,
+            "bt" | "backtrace" => Command::Backtrace,
+            "set" => {
+                let name = words.get(1).ok_or("Missing register")?;
+                let register =
+                    Register::parse(name).ok_or_else(|| format!("Unknown register: {}", name))?;
+                Command::Set(register, argument(2)?)
+            }
+            "x" => Command::Examine {
+                address: address(words.get(1).ok_or("Missing address")?)?,
+                length: optional(2)?.unwrap_or(0x40),
+            },
+            "poke" => Command::Poke {
+                address: address(words.get(1).ok_or("Missing address")?)?,
+                value: argument(2)? as u8,
+            },
+            "oam" => Command::Oam,
+            "io" => Command::Io(
+                words
+                    .get(1)
+                    .map(|name| {
+                        io_viewer::find(name).ok_or_else(|| format!("Unknown register: {}", name))
+                    })
+                    .transpose()?,
+            ),
+            "dis" => Command::Disassemble {
+                address: optional_address(1)?,
+                count: optional(2)?.unwrap_or(10),
+            },
+            "b" | "break" => {
+                let location = words.get(1).ok_or("Missing address")?;
+                let (bank, address) = parse_location(location, symbols)?;
+                Command::Break(Breakpoint::new(BreakpointKind::Execute { address, bank }))
+            }
+            watch @ ("watch" | "rwatch" | "awatch") => {
+                let range = words.get(1).ok_or("Missing address")?;
+                let range = match range.split_once('-') {
+                    Some((start, end)) => address(start)?..=address(end)?,
+                    None => address(range)?..=address(range)?,
+                };
+                let access = match watch {
+                    "rwatch" => Access::Read,
+                    "awatch" => Access::ReadWrite,
+                    _ => Access::Write,
+                };
+                Command::Break(Breakpoint::new(BreakpointKind::Watch { range, access }))
+            }
+            "ibreak" => {
+                let mask = match words.get(1).copied() {
+                    None => 0x1F,
+                    Some("vblank") => 0x01,
+                    Some("stat") => 0x02,
+                    Some("timer") => 0x04,
+                    Some("serial") => 0x08,
+                    Some("joypad") => 0x10,
+                    Some(other) => return Err(format!("Unknown interrupt: {}", other)),
+                };
+                Command::Break(Breakpoint::new(BreakpointKind::Interrupt { mask }))
+            }
+            "i" | "info" => Command::Info,
+            "d" | "delete" => {
+                let id = words.get(1).ok_or("Missing breakpoint id")?;
+                Command::Delete(id.parse().map_err(|_| format!("Invalid id: {}", id))?)
+            }
+            "h" | "help" => Command::Help,
+            "q" | "quit" => Command::Quit,
+            other => return Err(format!("Unknown command: {}, see 'help'", other)),
+        };
+        match (command, condition) {
+            (Command::Break(breakpoint), Some(condition)) => {
+                Ok(Command::Break(breakpoint.with_condition(condition)))
+            }
+            (_, Some(_)) => Err("Only breakpoints take a condition".to_string()),
+            (command, None) => Ok(command),
+        }
+    }
+}
+
+/// Formats the instruction at `address` as its raw bytes and mnemonic, returning
+/// the text and the instruction's length.
+pub fn disassemble(gameboy: &Gameboy, address: u16) -> (String, u16) {
+    let instruction = gameboy.decode(address);
+    (
+        format!("{:04X}: {:<8}  {}", address, instruction.hex(), instruction),
+        instruction.len(),
+    )
+}
+
+/// Finds an address a few instructions before `pc` from which decoding lands
+/// exactly on `pc`, so listings can show what led up to it.
+fn sync_before(gameboy: &Gameboy, pc: u16) -> u16 {
+    for distance in (1..=9).rev() {
+        let start = pc.wrapping_sub(distance);
+        let mut address = start;
+        while address.wrapping_sub(start) < distance {
+            address = address.wrapping_add(disassemble(gameboy, address).1);
+        }
+        if address == pc {
+            return start;
+        }
+    }
+    pc
+}
+
+fn is_call(opcode: u8) -> bool {
+    matches!(opcode, 0xCD | 0xC4 | 0xCC | 0xD4 | 0xDC) || opcode & 0xC7 == 0xC7
+}
+
+fn is_return(opcode: u8) -> bool {
+    matches!(opcode, 0xC9 | 0xD9 | 0xC0 | 0xC8 | 0xD0 | 0xD8)
+}
+
+#[derive(Debug, Default)]
+pub struct Debugger {
+    last_command: Option<Command>,
+}
+
+impl Debugger {
+    /// Reads and runs commands from stdin until emulation should resume.
+    pub fn repl(&mut self, gameboy: &mut Gameboy) -> Resume {
+        let stdin = std::io::stdin();
+        let mut stdout = std::io::stdout();
+        self.show_location(gameboy, &mut stdout);
+
+        loop {
+            print!("(gb) ");
+            stdout.flush().unwrap();
+
+            let mut line = String::new();
+            if stdin.lock().read_line(&mut line).unwrap_or(0) == 0 {
+                return Resume::Quit;
+            }
+            match self.run_line(gameboy, &line, &mut stdout) {
+                Resume::Prompt => {}
+                resume => return resume,
+            }
+        }
+    }
+
+    /// Parses and runs a single line of input.
+    pub fn run_line(&mut self, gameboy: &mut Gameboy, line: &str, out: &mut impl Write) -> Resume {
+        let command = if line.trim().is_empty() {
+            match self.last_command.clone() {
+                Some(command) => command,
+                None => return Resume::Prompt,
+            }
+        } else {
+            match Command::parse(line, &gameboy.symbols) {
+                Ok(command) => command,
+                Err(error) => {
+                    writeln!(out, "{}", error).unwrap();
+                    return Resume::Prompt;
+                }
+            }
+        };
+        self.last_command = Some(command.clone());
+        self.execute(gameboy, command, out)
+    }
+
+    pub fn execute(
+        &mut self,
+        gameboy: &mut Gameboy,
+        command: Command,
+        out: &mut impl Write,
+    ) -> Resume {
+        match command {
+            Command::Step(count) => {
+                for _ in 0..count {
+                    if !self.step(gameboy, out) {
+                        break;
+                    }
+                    if let Some(hit) = gameboy.breakpoints.last_hit() {
+                        writeln!(out, "Stopped at {}", hit).unwrap();
+                        break;
+                    }
+                }
+                self.show_location(gameboy, out);
+            }
+            Command::Next => {
+                let pc = gameboy.cpu.registers.pc;
+                let opcode = gameboy.read_byte(pc);
+                if is_call(opcode) {
+                    let sp = gameboy.cpu.registers.sp;
+                    let return_address = pc.wrapping_add(disassemble(gameboy, pc).1);
+                    self.run_until(gameboy, out, |gameboy, _| {
+                        gameboy.cpu.registers.pc == return_address && gameboy.cpu.registers.sp == sp
+                    });
+                } else {
+                    self.step(gameboy, out);
+                }
+                self.show_location(gameboy, out);
+            }
+            Command::Finish => {
+                let sp = gameboy.cpu.registers.sp;
+                self.run_until(gameboy, out, |gameboy, opcode| {
+                    is_return(opcode) && gameboy.cpu.registers.sp > sp
+                });
+                self.show_location(gameboy, out);
+            }
+            Command::Continue => {
+                gameboy.paused = false;
+                return Resume::Continue;
+            }
+            Command::Registers => self.show_registers(gameboy, out),
+            Command::Backtrace => write!(out, "{}", gameboy.backtrace()).unwrap(),
+            Command::Set(register, value) => {
+                register.set(gameboy, value);
+                self.show_registers(gameboy, out);
+            }
+            Command::Examine { address, length } => {
+                for row in (0..length).step_by(16) {
+                    let start = address.wrapping_add(row);
+                    let bytes = (0..16.min(length - row))
+                        .map(|offset| {
+                            format!("{:02X}", gameboy.read_byte(start.wrapping_add(offset)))
+                        })
+                        .collect::<Vec<_>>()
+                        .join(" ");
+                    writeln!(out, "{:04X}: {}", start, bytes).unwrap();
+                }
+            }
+            Command::Poke { address, value } => gameboy.write_byte(address, value),
+            Command::Disassemble { address, count } => {
+                let pc = gameboy.cpu.registers.pc;
+                let mut address = address.unwrap_or_else(|| sync_before(gameboy, pc));
+                let label = gameboy.symbols.labeler(gameboy.rom_bank(0x4000));
+                for _ in 0..count {
+                    if let Some(name) = label(address) {
+                        writeln!(out, "{}:", name).unwrap();
+                    }
+                    let (text, length) = disassemble(gameboy, address);
+                    let marker = if address == pc { "=>" } else { "  " };
+                    writeln!(out, "{} {}", marker, text).unwrap();
+                    address = address.wrapping_add(length);
+                }
+            }
+            Command::Oam => write!(out, "{}", oam_viewer::listing(&gameboy.bus)).unwrap(),
+            Command::Io(None) => write!(out, "{}", io_viewer::listing(gameboy)).unwrap(),
+            Command::Io(Some(register)) => {
+                writeln!(out, "{}", io_viewer::line(gameboy, register)).unwrap()
+            }
+            Command::Help => writeln!(out, "{}", HELP).unwrap(),
+            Command::Break(breakpoint) => {
+                let description = breakpoint.to_string();
+                let id = gameboy.breakpoints.add(breakpoint);
+                writeln!(out, "Breakpoint {}: {}", id, description).unwrap();
+            }
+            Command::Info => {
+                for (id, breakpoint) in gameboy.breakpoints.iter() {
+                    writeln!(out, "{:>3}  {}", id, breakpoint).unwrap();
+                }
+            }
+            Command::Delete(id) => {
+                if !gameboy.breakpoints.remove(id) {
+                    writeln!(out, "No breakpoint {}", id).unwrap();
+                }
+            }
+            Command::Quit => return Resume::Quit,
+        }
+        Resume::Prompt
+    }
+
+    /// Executes one instruction, returning false if emulation cannot go on.
+    fn step(&mut self, gameboy: &mut Gameboy, out: &mut impl Write) -> bool {
+        match gameboy.step() {
+            Ok(_) => true,
+            Err(error) => {
+                writeln!(out, "{}", error).unwrap();
+                false
+            }
+        }
+    }
+
+    /// Steps until `done` returns true for the state after an instruction and
+    /// the opcode that was just executed, or the run limit is hit.
+    fn run_until(
+        &mut self,
+        gameboy: &mut Gameboy,
+        out: &mut impl Write,
+        done: impl Fn(&Gameboy, u8) -> bool,
+    ) {
+        let start = gameboy.scheduler.now();
+        gameboy.paused = false;
+        while gameboy.scheduler.now() - start < RUN_LIMIT {
+            let opcode = gameboy.read_byte(gameboy.cpu.registers.pc);
+            if !self.step(gameboy, out) || gameboy.paused || done(gameboy, opcode) {
+                return;
+            }
+        }
+        writeln!(out, "Stopped after {} t-states", RUN_LIMIT).unwrap();
+    }
+
+    fn show_registers(&self, gameboy: &Gameboy, out: &mut impl Write) {
+        let registers = &gameboy.cpu.registers;
+        let flags = registers.f;
+        writeln!(
+            out,
+            "AF={:04X} BC={:04X} DE={:04X} HL={:04X} SP={:04X} PC={:04X} {}{}{}{}",
+            registers.get_u16(Register16bTarget::AF),
+            registers.get_u16(Register16bTarget::BC),
+            registers.get_u16(Register16bTarget::DE),
+            registers.get_u16(Register16bTarget::HL),
+            registers.sp,
+            registers.pc,
+            if flags.zero { 'Z' } else { '-' },
+            if flags.subtract { 'N' } else { '-' },
+            if flags.half_carry { 'H' } else { '-' },
+            if flags.carry { 'C' } else { '-' },
+        )
+        .unwrap();
+    }
+
+    fn show_location(&self, gameboy: &Gameboy, out: &mut impl Write) {
+        self.show_registers(gameboy, out);
+        if !gameboy.symbols.is_empty() {
+            writeln!(
+                out,
+                "{}:",
+                gameboy.describe_address(gameboy.cpu.registers.pc)
+            )
+            .unwrap();
+        }
+        writeln!(
+            out,
+            "=> {}",
+            disassemble(gameboy, gameboy.cpu.registers.pc).0
+        )
+        .unwrap();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gameboy::gameboy_with_program;
+
+    fn run(debugger: &mut Debugger, gameboy: &mut Gameboy, line: &str) -> String {
+        let mut out = Vec::new();
+        debugger.run_line(gameboy, line, &mut out);
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn test_parse() {
+        let symbols = Symbols::default();
+        assert!(matches!(
+            Command::parse("step 3", &symbols),
+            Ok(Command::Step(3))
+        ));
+        // counts are hex like every other number
+        assert!(matches!(
+            Command::parse("step 10", &symbols),
+            Ok(Command::Step(0x10))
+        ));
+        assert!(matches!(
+            Command::parse("step", &symbols),
+            Ok(Command::Step(1))
+        ));
+        assert!(matches!(
+            Command::parse("x $C000 10", &symbols),
+            Ok(Command::Examine {
+                address: 0xC000,
+                length: 0x10
+            })
+        ));
+        assert!(matches!(
+            Command::parse("set hl 0xC123", &symbols),
+            Ok(Command::Set(Register::U16(Register16bTarget::HL), 0xC123))
+        ));
+        assert!(Command::parse("set xy 1", &symbols).is_err());
+        assert!(Command::parse("jump", &symbols).is_err());
+    }
+
+    #[test]
+    fn test_breakpoint_commands() {
+        let mut gameboy = gameboy_with_program(&[]);
+        let mut debugger = Debugger::default();
+        run(
+            &mut debugger,
+            &mut gameboy,
+            "break 01:4000 if a == 3f && hl > c000",
+        );
+        run(&mut debugger, &mut gameboy, "rwatch ff00-ff7f");
+        run(&mut debugger, &mut gameboy, "ibreak vblank");
+        run(&mut debugger, &mut gameboy, "delete 2");
+        let output = run(&mut debugger, &mut gameboy, "info");
+
+        assert_eq!(
+            output,
+            "  1  execute 01:4000 if a == 3f && hl > c000\n  3  interrupt 00001\n"
+        );
+        assert!(Command::parse("regs if a == 1", &Symbols::default()).is_err());
+    }
+
+    #[test]
+    fn test_set_registers_and_flags() {
+        let mut gameboy = gameboy_with_program(&[]);
+        let mut debugger = Debugger::default();
+        run(&mut debugger, &mut gameboy, "set a 3f");
+        run(&mut debugger, &mut gameboy, "set de 1234");
+        let output = run(&mut debugger, &mut gameboy, "set cf 1");
+
+        assert_eq!(gameboy.cpu.registers.a, 0x3F);
+        assert_eq!(gameboy.cpu.registers.d, 0x12);
+        assert_eq!(gameboy.cpu.registers.e, 0x34);
+        assert!(gameboy.cpu.registers.f.carry);
+        assert!(output.starts_with("AF=3F10 BC=0000 DE=1234"));
+    }
+
+    #[test]
+    fn test_memory() {
+        let mut gameboy = gameboy_with_program(&[]);
+        let mut debugger = Debugger::default();
+        run(&mut debugger, &mut gameboy, "poke C100 AB");
+        let output = run(&mut debugger, &mut gameboy, "x C0FF 3");
+
+        assert_eq!(gameboy.read_byte(0xC100), 0xAB);
+        assert_eq!(output, "C0FF: 00 AB 00\n");
+    }
+
+    #[test]
+    fn test_step_and_repeat() {
+        // LD A,$05; INC A; INC A; INC A
+        let mut gameboy = gameboy_with_program(&[0x3E, 0x05, 0x3C, 0x3C, 0x3C]);
+        let mut debugger = Debugger::default();
+        run(&mut debugger, &mut gameboy, "step 2");
+        assert_eq!(gameboy.cpu.registers.a, 0x06);
+
+        let output = run(&mut debugger, &mut gameboy, "");
+        assert_eq!(gameboy.cpu.registers.a, 0x08);
+        assert!(output.contains("PC=C005"));
+    }
+
+    #[test]
+    fn test_step_stops_at_breakpoints() {
+        // INC A, six times
+        let mut gameboy = gameboy_with_program(&[0x3C; 6]);
+        let mut debugger = Debugger::default();
+        run(&mut debugger, &mut gameboy, "break c002");
+        let output = run(&mut debugger, &mut gameboy, "step 10");
+        assert!(output.contains("Stopped at"));
+        assert_eq!(gameboy.cpu.registers.pc, 0xC002);
+        assert_eq!(gameboy.cpu.registers.a, 2);
+
+        run(&mut debugger, &mut gameboy, "step 3");
+        assert_eq!(gameboy.cpu.registers.pc, 0xC005);
+        assert_eq!(gameboy.cpu.registers.a, 5);
+    }
+
+    #[test]
+    fn test_next_and_finish() {
+        // CALL $C010; LD B,B; ...; $C010: INC A; INC A; RET
+        let mut program = [0; 0x13];
+        program[..4].copy_from_slice(&[0xCD, 0x10, 0xC0, 0x40]);
+        program[0x10..].copy_from_slice(&[0x3C, 0x3C, 0xC9]);
+        let mut gameboy = gameboy_with_program(&program);
+        let mut debugger = Debugger::default();
+
+        run(&mut debugger, &mut gameboy, "next");
+        assert_eq!(gameboy.cpu.registers.pc, 0xC003);
+        assert_eq!(gameboy.cpu.registers.a, 2);
+
+        gameboy.cpu.registers.pc = 0xC000;
+        run(&mut debugger, &mut gameboy, "step 2");
+        assert_eq!(gameboy.cpu.registers.pc, 0xC011);
+        run(&mut debugger, &mut gameboy, "finish");
+        assert_eq!(gameboy.cpu.registers.pc, 0xC003);
+        assert_eq!(gameboy.cpu.registers.sp, 0xD000);
+    }
+
+    #[test]
+    fn test_disassemble_around_pc() {
+        // NOP; LD HL,$C100; LD (HL+),A; CB SWAP A
+        let mut gameboy = gameboy_with_program(&[0x00, 0x21, 0x00, 0xC1, 0x22, 0xCB, 0x37]);
+        gameboy.cpu.registers.pc = 0xC004;
+        let mut debugger = Debugger::default();
+        let output = run(&mut debugger, &mut gameboy, "dis C001 3");
+
+        assert_eq!(
+            output,
+            "   C001: 21 00 C1  ld hl, $C100\n\
+             => C004: 22        ld [hl+], a\n   \
+             C005: CB 37     swap a\n"
+        );
+    }
+
+    #[test]
+    fn test_backtrace() {
+        // CALL $C010; ...; $C010: NOP
+        let mut program = [0; 0x11];
+        program[..3].copy_from_slice(&[0xCD, 0x10, 0xC0]);
+        let mut gameboy = gameboy_with_program(&program);
+        let mut debugger = Debugger::default();
+        run(&mut debugger, &mut gameboy, "step");
+        let output = run(&mut debugger, &mut gameboy, "bt");
+
+        assert_eq!(output, "#0  C010  $C010\n#1  C000  $C000  (call $C010)\n");
+    }
+
+    #[test]
+    fn test_oam() {
+        let mut gameboy = gameboy_with_program(&[]);
+        gameboy.bus.memory[0xFE04..0xFE08].copy_from_slice(&[0x20, 0x10, 0x05, 0x20]);
+        let mut debugger = Debugger::default();
+        let output = run(&mut debugger, &mut gameboy, "oam");
+
+        assert_eq!(output.lines().count(), 41);
+        assert!(output
+            .lines()
+            .nth(2)
+            .unwrap()
+            .starts_with(" 1   16  32  05    OBP0  X-"));
+    }
+
+    #[test]
+    fn test_io() {
+        let mut gameboy = gameboy_with_program(&[]);
+        gameboy.write_byte(0xFF40, 0x91);
+        let mut debugger = Debugger::default();
+        let output = run(&mut debugger, &mut gameboy, "io lcdc");
+
+        assert!(output.starts_with("LCDC FF40 91  LCD on, window map 9800"));
+        assert!(run(&mut debugger, &mut gameboy, "io nr99").contains("Unknown register: nr99"));
+    }
+
+    #[test]
+    fn test_labels() {
+        // CALL Routine; ...; Routine: INC A; RET
+        let mut program = [0; 0x12];
+        program[..3].copy_from_slice(&[0xCD, 0x10, 0xC0]);
+        program[0x10..].copy_from_slice(&[0x3C, 0xC9]);
+        let mut gameboy = gameboy_with_program(&program);
+        gameboy.symbols = Symbols::parse("00:c000 Main\n00:c010 Routine\n");
+        let mut debugger = Debugger::default();
+
+        let output = run(&mut debugger, &mut gameboy, "dis Main 1");
+        assert_eq!(output, "Main:\n=> C000: CD 10 C0  call Routine\n");
+        run(&mut debugger, &mut gameboy, "break Routine+1");
+        run(&mut debugger, &mut gameboy, "c");
+        while !gameboy.paused {
+            gameboy.step().unwrap();
+        }
+        assert_eq!(gameboy.cpu.registers.pc, 0xC011);
+        gameboy.cpu.registers.pc = 0xC010;
+        run(&mut debugger, &mut gameboy, "delete 1");
+        let output = run(&mut debugger, &mut gameboy, "step");
+        assert!(output.contains("\nRoutine+$1:\n=> C011: C9"), "{}", output);
+    }
+
+    #[test]
+    fn test_continue_unpauses() {
+        let mut gameboy = gameboy_with_program(&[]);
+        gameboy.paused = true;
+        let mut debugger = Debugger::default();
+        let resume = debugger.run_line(&mut gameboy, "c", &mut Vec::new());
+
+        assert_eq!(resume, Resume::Continue);
+        assert!(!gameboy.paused);
+    }
+}
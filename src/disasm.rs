@@ -0,0 +1,462 @@
+//! Decodes machine code into RGBDS syntax, e.g. `ld a, [hl+]` or `jr nz, $C010`.
+
+use std::fmt::Write;
+use std::ops::RangeInclusive;
+
+use crate::cdl::{self, CodeDataLog};
+use crate::opcode_info::{self, Opcode};
+use crate::symbols::Symbols;
+
+const BANK_SIZE: usize = 0x4000;
+/// How many bytes of data go on one `db` line.
+const DATA_PER_LINE: usize = 8;
+
+/// One decoded instruction.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Instruction {
+    pub address: u16,
+    pub bytes: Vec<u8>,
+    pub text: String,
+    /// Where a jump, call or RST transfers control to, when it's known
+    /// without running the code.
+    pub target: Option<u16>,
+}
+
+impl Instruction {
+    pub fn len(&self) -> u16 {
+        self.bytes.len() as u16
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.bytes.is_empty()
+    }
+
+    /// The raw bytes as space separated hex.
+    pub fn hex(&self) -> String {
+        self.bytes
+            .iter()
+            .map(|byte| format!("{:02X}", byte))
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+}
+
+impl std::fmt::Display for Instruction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.text)
+    }
+}
+
+fn signed(value: u8) -> String {
+    let value = value as i8;
+    if value < 0 {
+        format!("-${:02X}", value.unsigned_abs())
+    } else {
+        format!("${:02X}", value)
+    }
+}
+
+/// Decodes the instruction at `address`, reading memory through `read`.
+pub fn decode(read: impl Fn(u16) -> u8, address: u16) -> Instruction {
+    decode_labeled(read, address, |_| None)
+}
+
+/// Like [`decode`], but jump targets and memory addresses are named by `label`
+/// when it knows them.
+pub fn decode_labeled(
+    read: impl Fn(u16) -> u8,
+    address: u16,
+    label: impl Fn(u16) -> Option<String>,
+) -> Instruction {
+    let name = |value: u16| label(value).unwrap_or_else(|| format!("${:04X}", value));
+    let first = read(address);
+    let (opcode, prefixed): (&Opcode, bool) = if first == 0xCB {
+        (opcode_info::cbprefixed(read(address.wrapping_add(1))), true)
+    } else {
+        (opcode_info::unprefixed(first), false)
+    };
+    let bytes: Vec<u8> = (0..opcode.bytes.max(1) as u16)
+        .map(|offset| read(address.wrapping_add(offset)))
+        .collect();
+
+    if opcode.mnemonic.starts_with("ILLEGAL") {
+        return Instruction {
+            address,
+            text: format!("db ${:02X}", first),
+            bytes,
+            target: None,
+        };
+    }
+
+    let mut mnemonic = opcode.mnemonic.to_ascii_lowercase();
+    let mut operands = Vec::new();
+    let mut target = None;
+    // immediates follow the opcode in the order the operands are listed
+    let mut next = if prefixed { 2 } else { 1 };
+    let mut immediate = |size: u8| {
+        let value = match size {
+            1 => bytes[next] as u16,
+            _ => u16::from_le_bytes([bytes[next], bytes[next + 1]]),
+        };
+        next += size as usize;
+        value
+    };
+
+    let mut iter = opcode.operands.iter().peekable();
+    while let Some(operand) = iter.next() {
+        let text = match operand.name {
+            "n8" => format!("${:02X}", immediate(1)),
+            "n16" => format!("${:04X}", immediate(2)),
+            "a16" => {
+                let value = immediate(2);
+                if matches!(opcode.mnemonic, "JP" | "CALL") {
+                    target = Some(value);
+                }
+                name(value)
+            }
+            "a8" => name(0xFF00 | immediate(1)),
+            "e8" if opcode.mnemonic == "JR" => {
+                let offset = immediate(1) as u8 as i8;
+                let destination = address.wrapping_add(2).wrapping_add(offset as u16);
+                target = Some(destination);
+                name(destination)
+            }
+            "e8" => signed(immediate(1) as u8),
+            "SP" if operand.increment => {
+                // LD HL, SP+e8
+                iter.next();
+                let offset = signed(immediate(1) as u8);
+                match offset.strip_prefix('-') {
+                    Some(offset) => format!("sp-{}", offset),
+                    None => format!("sp+{}", offset),
+                }
+            }
+            "C" if !operand.immediate => {
+                mnemonic = "ldh".to_string();
+                "c".to_string()
+            }
+            vector if opcode.mnemonic == "RST" => {
+                target = u16::from_str_radix(&vector[1..], 16).ok();
+                vector.to_string()
+            }
+            name => name.to_ascii_lowercase(),
+        };
+        let text = match (operand.increment, operand.decrement) {
+            (true, _) if !operand.immediate => format!("{}+", text),
+            (_, true) => format!("{}-", text),
+            _ => text,
+        };
+        operands.push(if operand.immediate {
+            text
+        } else {
+            format!("[{}]", text)
+        });
+    }
+    if opcode.mnemonic == "STOP" {
+        // RGBDS emits the padding byte itself
+        operands.clear();
+    }
+
+    let text = if operands.is_empty() {
+        mnemonic
+    } else {
+        format!("{} {}", mnemonic, operands.join(", "))
+    };
+    Instruction {
+        address,
+        bytes,
+        text,
+        target,
+    }
+}
+
+/// Decodes `bytes` one instruction after another, as if loaded at `base`. An
+/// instruction cut off by the end of the slice is emitted as `db`.
+pub fn decode_all(bytes: &[u8], base: u16) -> Vec<Instruction> {
+    decode_all_labeled(bytes, base, |_| None)
+}
+
+fn decode_all_labeled(
+    bytes: &[u8],
+    base: u16,
+    label: impl Fn(u16) -> Option<String>,
+) -> Vec<Instruction> {
+    let mut instructions = Vec::new();
+    let mut offset = 0;
+    while offset < bytes.len() {
+        let address = base.wrapping_add(offset as u16);
+        let instruction = decode_labeled(
+            |at| {
+                let index = at.wrapping_sub(base) as usize;
+                bytes.get(index).copied().unwrap_or(0)
+            },
+            address,
+            &label,
+        );
+        if offset + instruction.bytes.len() > bytes.len() {
+            for byte in &bytes[offset..] {
+                instructions.push(Instruction {
+                    address: base.wrapping_add(offset as u16),
+                    bytes: vec![*byte],
+                    text: format!("db ${:02X}", byte),
+                    target: None,
+                });
+                offset += 1;
+            }
+            break;
+        }
+        offset += instruction.bytes.len();
+        instructions.push(instruction);
+    }
+    instructions
+}
+
+/// Like `decode_all_labeled`, but only decodes instructions where `flags`
+/// (one CDL entry per byte) say one was executed, emitting everything else as
+/// `db` lines that stop at labels.
+fn decode_logged(
+    bytes: &[u8],
+    base: u16,
+    label: impl Fn(u16) -> Option<String>,
+    flags: &[u8],
+) -> Vec<Instruction> {
+    let is_code = |offset: usize| {
+        flags
+            .get(offset)
+            .is_some_and(|flags| flags & cdl::OPCODE != 0)
+    };
+    let mut instructions = Vec::new();
+    let mut offset = 0;
+    while offset < bytes.len() {
+        let address = base.wrapping_add(offset as u16);
+        if is_code(offset) {
+            let read = |at: u16| bytes.get(at.wrapping_sub(base) as usize).copied();
+            let instruction = decode_labeled(|at| read(at).unwrap_or(0), address, &label);
+            // one cut off by the end of the bank falls through to data
+            if offset + instruction.bytes.len() <= bytes.len() {
+                offset += instruction.bytes.len();
+                instructions.push(instruction);
+                continue;
+            }
+        }
+        let mut end = offset + 1;
+        while end < bytes.len()
+            && end - offset < DATA_PER_LINE
+            && !is_code(end)
+            && label(base.wrapping_add(end as u16)).is_none()
+        {
+            end += 1;
+        }
+        let data = &bytes[offset..end];
+        let values: Vec<String> = data.iter().map(|byte| format!("${:02X}", byte)).collect();
+        instructions.push(Instruction {
+            address,
+            bytes: data.to_vec(),
+            text: format!("db {}", values.join(", ")),
+            target: None,
+        });
+        offset = end;
+    }
+    instructions
+}
+
+/// Disassembles whole ROM banks into RGBDS source, one section per bank, with
+/// labels from `symbols` where they're known. With a code/data log, only
+/// bytes it saw executed as opcodes are decoded and the rest become `db`.
+pub fn disassemble_rom(
+    rom: &[u8],
+    banks: RangeInclusive<usize>,
+    symbols: &Symbols,
+    cdl: Option<&CodeDataLog>,
+) -> String {
+    let mut out = String::new();
+    for bank in banks {
+        let Some(data) = rom.get(bank * BANK_SIZE..((bank + 1) * BANK_SIZE).min(rom.len())) else {
+            break;
+        };
+        let base = if bank == 0 { 0x0000 } else { 0x4000 };
+        if bank == 0 {
+            writeln!(out, "SECTION \"ROM Bank $000\", ROM0[$0000]").unwrap();
+        } else {
+            writeln!(
+                out,
+                "SECTION \"ROM Bank ${:03X}\", ROMX[$4000], BANK[${:X}]",
+                bank, bank
+            )
+            .unwrap();
+        }
+        let label = symbols.labeler(bank.max(1) as u8);
+        let instructions = match cdl {
+            Some(cdl) => {
+                let flags: Vec<u8> = (0..data.len())
+                    .map(|offset| cdl.flags(bank * BANK_SIZE + offset))
+                    .collect();
+                decode_logged(data, base, &label, &flags)
+            }
+            None => decode_all_labeled(data, base, &label),
+        };
+        for instruction in instructions {
+            if let Some(name) = label(instruction.address) {
+                writeln!(out, "{}:", name).unwrap();
+            }
+            writeln!(
+                out,
+                "    {:<24} ; ${:04X}: {}",
+                instruction.text,
+                instruction.address,
+                instruction.hex()
+            )
+            .unwrap();
+        }
+        writeln!(out).unwrap();
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn text(bytes: &[u8], base: u16) -> Vec<String> {
+        decode_all(bytes, base)
+            .into_iter()
+            .map(|instruction| instruction.text)
+            .collect()
+    }
+
+    #[test]
+    fn test_operands() {
+        assert_eq!(
+            text(
+                &[
+                    0x00, // nop
+                    0x22, // ld [hl+], a
+                    0x3A, // ld a, [hl-]
+                    0x36, 0x3F, // ld [hl], $3F
+                    0x01, 0x34, 0x12, // ld bc, $1234
+                    0xE0, 0x40, // ldh [$FF40], a
+                    0xF2, // ldh a, [c]
+                    0xFA, 0x00, 0xC0, // ld a, [$C000]
+                    0x08, 0x00, 0xD0, // ld [$D000], sp
+                    0xF8, 0xFE, // ld hl, sp-$02
+                    0xE8, 0x05, // add sp, $05
+                    0xCB, 0x7E, // bit 7, [hl]
+                    0xCB, 0x37, // swap a
+                    0x10, 0x00, // stop
+                    0xD3, // db $D3
+                ],
+                0x0150
+            ),
+            [
+                "nop",
+                "ld [hl+], a",
+                "ld a, [hl-]",
+                "ld [hl], $3F",
+                "ld bc, $1234",
+                "ldh [$FF40], a",
+                "ldh a, [c]",
+                "ld a, [$C000]",
+                "ld [$D000], sp",
+                "ld hl, sp-$02",
+                "add sp, $05",
+                "bit 7, [hl]",
+                "swap a",
+                "stop",
+                "db $D3",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_jump_targets() {
+        let instructions = decode_all(
+            &[
+                0x20, 0xFE, // jr nz, $0150
+                0xC3, 0x00, 0x40, // jp $4000
+                0xCC, 0x34, 0x12, // call z, $1234
+                0xFF, // rst $38
+                0xE9, // jp hl
+            ],
+            0x0150,
+        );
+        let decoded: Vec<_> = instructions
+            .iter()
+            .map(|instruction| (instruction.text.as_str(), instruction.target))
+            .collect();
+        assert_eq!(
+            decoded,
+            [
+                ("jr nz, $0150", Some(0x0150)),
+                ("jp $4000", Some(0x4000)),
+                ("call z, $1234", Some(0x1234)),
+                ("rst $38", Some(0x38)),
+                ("jp hl", None),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_truncated_instruction() {
+        assert_eq!(text(&[0x3C, 0xC3, 0x00], 0), ["inc a", "db $C3", "db $00"]);
+    }
+
+    #[test]
+    fn test_disassemble_rom() {
+        let mut rom = vec![0; 2 * BANK_SIZE];
+        rom[BANK_SIZE] = 0x3C;
+        let source = disassemble_rom(&rom, 1..=1, &Symbols::default(), None);
+        let mut lines = source.lines();
+        assert_eq!(
+            lines.next(),
+            Some("SECTION \"ROM Bank $001\", ROMX[$4000], BANK[$1]")
+        );
+        assert_eq!(
+            lines.next(),
+            Some("    inc a                    ; $4000: 3C")
+        );
+        assert_eq!(lines.filter(|line| line.contains("nop")).count(), 0x3FFF);
+    }
+
+    #[test]
+    fn test_labels() {
+        let mut rom = vec![0; 2 * BANK_SIZE];
+        // jr to itself, then call into bank 1
+        rom[0x150..0x155].copy_from_slice(&[0x18, 0xFE, 0xCD, 0x00, 0x40]);
+        rom[BANK_SIZE..BANK_SIZE + 2].copy_from_slice(&[0xF0, 0x44]);
+        let symbols = Symbols::parse("00:0150 Main\n01:4000 Routine\n00:ff44 rLY\n");
+        let source = disassemble_rom(&rom, 0..=1, &symbols, None);
+        assert!(source.contains("Main:\n    jr Main "));
+        assert!(source.contains("    call Routine "));
+        assert!(source.contains("Routine:\n    ldh a, [rLY] "));
+    }
+
+    #[test]
+    fn test_code_data_log() {
+        let mut rom = vec![0; BANK_SIZE];
+        // LD A,[$0010]; SWAP A; then 11 bytes of data, the last after a label
+        rom[..5].copy_from_slice(&[0xFA, 0x10, 0x00, 0xCB, 0x37]);
+        let mut log = CodeDataLog::new(BANK_SIZE);
+        for offset in [0x00, 0x03, 0x10] {
+            log.mark(offset, cdl::OPCODE);
+        }
+        for offset in [0x01, 0x02, 0x04] {
+            log.mark(offset, cdl::OPERAND);
+        }
+        let symbols = Symbols::parse("00:000f Table\n");
+        let source = disassemble_rom(&rom, 0..=0, &symbols, Some(&log));
+        let lines: Vec<&str> = source.lines().skip(1).take(6).collect();
+
+        assert_eq!(
+            lines,
+            [
+                "    ld a, [$0010]            ; $0000: FA 10 00",
+                "    swap a                   ; $0003: CB 37",
+                "    db $00, $00, $00, $00, $00, $00, $00, $00 ; $0005: 00 00 00 00 00 00 00 00",
+                "    db $00, $00              ; $000D: 00 00",
+                "Table:",
+                "    db $00                   ; $000F: 00",
+            ]
+        );
+        assert!(source.contains("    nop                      ; $0010: 00\n    db $00,"));
+    }
+}
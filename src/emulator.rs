@@ -1,7 +1,91 @@
+use crate::cdl::{self, CodeDataLog};
 use crate::gameboy;
+use crate::profiler::Profiler;
+use crate::rewind;
+use crate::symbols::Symbols;
+use crate::trace::{self, Tracer};
+use std::path::{Path, PathBuf};
 
-pub fn run(cartridge: &[u8; 0x200000]) {
+#[derive(Debug, Default)]
+pub struct Options {
+    /// Start in the debugger instead of running straight away.
+    pub debug: bool,
+    /// Serve the GDB remote protocol on this local port.
+    pub gdb_port: Option<u16>,
+    /// Labels loaded from the `.sym` file next to the ROM.
+    pub symbols: Symbols,
+    /// Write a gameboy-doctor trace of every instruction to this file.
+    pub trace: Option<PathBuf>,
+    /// Start at 0x0100 with the post-boot state instead of running the boot ROM.
+    pub skip_boot_rom: bool,
+    /// Make LY always read 0x90, as gameboy-doctor logs expect.
+    pub force_ly: bool,
+    /// Write a report of where t-states went to this file on exit.
+    pub profile: Option<PathBuf>,
+    /// Write the profile as folded stacks for flamegraph tools to this file on exit.
+    pub profile_folded: Option<PathBuf>,
+    /// Log how ROM bytes are accessed into this `.cdl` file, adding to what
+    /// it already holds.
+    pub cdl: Option<PathBuf>,
+    /// How often snapshots are taken for rewinding, and how many are kept.
+    pub rewind: rewind::Settings,
+    /// Run at this multiple of normal speed instead, from 0.25 to 8.
+    pub speed: Option<f64>,
+    /// Start with fast-forward on.
+    pub fast_forward: bool,
+}
+
+pub fn run(cartridge: &[u8; 0x200000], mut options: Options) {
     let mut gameboy = gameboy::Gameboy::default();
     gameboy::initialize(&mut gameboy);
-    gameboy.run(cartridge);
+    gameboy.symbols = std::mem::take(&mut options.symbols);
+    if options.skip_boot_rom {
+        gameboy::skip_boot_rom(&mut gameboy);
+    }
+    if options.force_ly {
+        gameboy.force_ly = Some(trace::DOCTOR_LY);
+    }
+    if let Some(path) = &options.trace {
+        match Tracer::create(path) {
+            Ok(tracer) => gameboy.trace = Some(tracer),
+            Err(error) => {
+                eprintln!("Error creating {}: {}", path.display(), error);
+                std::process::exit(1);
+            }
+        }
+    }
+    if options.profile.is_some() || options.profile_folded.is_some() {
+        gameboy.profiler = Some(Profiler::default());
+    }
+    if let Some(path) = &options.cdl {
+        match CodeDataLog::load_or_new(path, cdl::rom_size(cartridge)) {
+            Ok(log) => gameboy.cdl = Some(log),
+            Err(error) => {
+                eprintln!("Error reading {}: {}", path.display(), error);
+                std::process::exit(1);
+            }
+        }
+    }
+    gameboy.paused = options.debug;
+    gameboy.run(cartridge, &options);
+
+    if let Some(profiler) = &gameboy.profiler {
+        if let Some(path) = &options.profile {
+            write_output(path, &profiler.report(&gameboy.symbols));
+        }
+        if let Some(path) = &options.profile_folded {
+            write_output(path, &profiler.folded(&gameboy.symbols));
+        }
+    }
+    if let (Some(log), Some(path)) = (&gameboy.cdl, &options.cdl) {
+        if let Err(error) = log.save(path) {
+            eprintln!("Error writing {}: {}", path.display(), error);
+        }
+    }
+}
+
+fn write_output(path: &Path, contents: &str) {
+    if let Err(error) = std::fs::write(path, contents) {
+        eprintln!("Error writing {}: {}", path.display(), error);
+    }
 }
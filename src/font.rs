@@ -0,0 +1,107 @@
+//! A 3x5 pixel font for labelling debug views, so they don't need SDL_ttf.
+
+use sdl2::pixels::Color;
+
+use crate::ppu::pack;
+
+/// Horizontal advance per character, including a column of spacing.
+pub const CHAR_WIDTH: usize = 4;
+/// Vertical advance per line, including two rows of spacing.
+pub const LINE_HEIGHT: usize = 7;
+
+/// Rows of a glyph top to bottom, with the leftmost pixel in bit 2.
+/// Lowercase letters use their uppercase glyph; anything else is blank.
+fn glyph(c: char) -> [u8; 5] {
+    match c.to_ascii_uppercase() {
+        '0' => [0b111, 0b101, 0b101, 0b101, 0b111],
+        '1' => [0b010, 0b110, 0b010, 0b010, 0b111],
+        '2' => [0b111, 0b001, 0b111, 0b100, 0b111],
+        '3' => [0b111, 0b001, 0b111, 0b001, 0b111],
+        '4' => [0b101, 0b101, 0b111, 0b001, 0b001],
+        '5' => [0b111, 0b100, 0b111, 0b001, 0b111],
+        '6' => [0b111, 0b100, 0b111, 0b101, 0b111],
+        '7' => [0b111, 0b001, 0b001, 0b001, 0b001],
+        '8' => [0b111, 0b101, 0b111, 0b101, 0b111],
+        '9' => [0b111, 0b101, 0b111, 0b001, 0b111],
+        'A' => [0b010, 0b101, 0b111, 0b101, 0b101],
+        'B' => [0b110, 0b101, 0b110, 0b101, 0b110],
+        'C' => [0b011, 0b100, 0b100, 0b100, 0b011],
+        'D' => [0b110, 0b101, 0b101, 0b101, 0b110],
+        'E' => [0b111, 0b100, 0b110, 0b100, 0b111],
+        'F' => [0b111, 0b100, 0b110, 0b100, 0b100],
+        'G' => [0b011, 0b100, 0b101, 0b101, 0b011],
+        'H' => [0b101, 0b101, 0b111, 0b101, 0b101],
+        'I' => [0b111, 0b010, 0b010, 0b010, 0b111],
+        'J' => [0b001, 0b001, 0b001, 0b101, 0b010],
+        'K' => [0b101, 0b101, 0b110, 0b101, 0b101],
+        'L' => [0b100, 0b100, 0b100, 0b100, 0b111],
+        'M' => [0b101, 0b111, 0b111, 0b101, 0b101],
+        'N' => [0b110, 0b101, 0b101, 0b101, 0b101],
+        'O' => [0b010, 0b101, 0b101, 0b101, 0b010],
+        'P' => [0b110, 0b101, 0b110, 0b100, 0b100],
+        'Q' => [0b010, 0b101, 0b101, 0b110, 0b011],
+        'R' => [0b110, 0b101, 0b110, 0b101, 0b101],
+        'S' => [0b011, 0b100, 0b010, 0b001, 0b110],
+        'T' => [0b111, 0b010, 0b010, 0b010, 0b010],
+        'U' => [0b101, 0b101, 0b101, 0b101, 0b111],
+        'V' => [0b101, 0b101, 0b101, 0b101, 0b010],
+        'W' => [0b101, 0b101, 0b111, 0b111, 0b101],
+        'X' => [0b101, 0b101, 0b010, 0b101, 0b101],
+        'Y' => [0b101, 0b101, 0b010, 0b010, 0b010],
+        'Z' => [0b111, 0b001, 0b010, 0b100, 0b111],
+        ':' => [0b000, 0b010, 0b000, 0b010, 0b000],
+        '.' => [0b000, 0b000, 0b000, 0b000, 0b010],
+        ',' => [0b000, 0b000, 0b000, 0b010, 0b100],
+        '-' => [0b000, 0b000, 0b111, 0b000, 0b000],
+        '+' => [0b000, 0b010, 0b111, 0b010, 0b000],
+        '=' => [0b000, 0b111, 0b000, 0b111, 0b000],
+        '/' => [0b001, 0b001, 0b010, 0b100, 0b100],
+        '(' => [0b001, 0b010, 0b010, 0b010, 0b001],
+        ')' => [0b100, 0b010, 0b010, 0b010, 0b100],
+        '$' => [0b011, 0b110, 0b010, 0b011, 0b110],
+        _ => [0; 5],
+    }
+}
+
+/// Draws `text` on one line into an image `width` pixels wide, with its top
+/// left corner at `x`, `y`. Only the glyphs' pixels are touched.
+pub fn draw_text(
+    pixels: &mut [u32],
+    width: usize,
+    (x, y): (usize, usize),
+    text: &str,
+    color: Color,
+) {
+    for (index, c) in text.chars().enumerate() {
+        let left = x + index * CHAR_WIDTH;
+        for (row, bits) in glyph(c).into_iter().enumerate() {
+            for column in 0..3 {
+                if bits & (0b100 >> column) != 0 {
+                    pixels[(y + row) * width + left + column] = pack(color);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_draw_text() {
+        let width = 3 * CHAR_WIDTH;
+        let mut pixels = vec![0; width * LINE_HEIGHT];
+        draw_text(&mut pixels, width, (0, 0), "a 1", Color::WHITE);
+        let white = pack(Color::WHITE);
+
+        // the top of the A is only its middle pixel
+        assert_eq!(&pixels[..3], &[0, white, 0]);
+        assert!(pixels[CHAR_WIDTH..2 * CHAR_WIDTH]
+            .iter()
+            .all(|&pixel| pixel == 0));
+        // the bottom of the 1 is a full row
+        let bottom = 4 * width + 2 * CHAR_WIDTH;
+        assert_eq!(&pixels[bottom..bottom + 3], &[white; 3]);
+    }
+}
@@ -1,12 +1,28 @@
+use crate::breakpoints::{Breakpoints, Hit};
+use crate::call_stack::{CallStack, Frame, FrameKind};
+use crate::cdl::{self, CodeDataLog};
 use crate::cpu::{Register16bTarget, CPU};
+use crate::debugger::{Debugger, Resume};
+use crate::disasm;
+use crate::emulator::Options;
+use crate::gdb::GdbServer;
+use crate::hooks::{AccessKind, MemoryAccess, MemoryObserver};
 use crate::instructions;
 use crate::joypad;
 use crate::memory::special_addresses::{self, *};
 use crate::memory::{self, MemoryBus};
-use crate::opcode_info::{OpcodeInfo, OperandInformation};
-use crate::ppu::PPU;
+use crate::ppu::{self, PPU};
+use crate::profiler::{Location, Profiler};
+use crate::rewind::Rewind;
+use crate::scheduler::{EventKind, Scheduler};
+use crate::screen::Screen;
+use crate::speed::Speed;
+use crate::symbols::Symbols;
+use crate::trace::{self, Tracer};
+use crate::views::{View, Views};
 use std::collections::HashMap;
 use std::fmt::Debug;
+use std::panic::AssertUnwindSafe;
 use std::time::{Duration, Instant};
 
 macro_rules! flag_set_at {
@@ -78,34 +94,178 @@ impl Debug for Interrupt {
     }
 }
 
+/// What to do when the CPU decodes one of the unused opcodes.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub enum IllegalOpcodePolicy {
+    /// Hang the CPU until reset, like real hardware does.
+    #[default]
+    LockUp,
+    /// Pause execution with the program counter at the offending opcode.
+    Break,
+    /// Make `Gameboy::step` return a `StepError`.
+    Error,
+}
+
+/// How the rest of the system is kept in step with the CPU.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub enum Timing {
+    /// Timers are advanced once the whole instruction has completed.
+    #[default]
+    Instruction,
+    /// Every memory access made by an instruction advances the timers by one
+    /// M-cycle (4 t-states) at the moment it happens.
+    MCycle,
+}
+
+/// How decoded opcodes are turned into calls to their implementation.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub enum Dispatch {
+    /// Look up a boxed closure per opcode with `instructions::from_byte`.
+    #[default]
+    Table,
+    /// Jump straight to the implementation with `instructions::execute`.
+    Match,
+}
+
+/// Notable things that happened during emulation, for the frontend to show.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Event {
+    IllegalOpcode {
+        address: u16,
+        opcode: u8,
+    },
+    Breakpoint(Hit),
+    /// `LD B,B` ran at `address` with `break_on_ld_b_b` set.
+    SoftwareBreakpoint {
+        address: u16,
+    },
+}
+
+impl std::fmt::Display for Event {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Event::IllegalOpcode { address, opcode } => {
+                write!(f, "illegal opcode {:02X} at {:04X}", opcode, address)
+            }
+            Event::Breakpoint(hit) => write!(f, "{}", hit),
+            Event::SoftwareBreakpoint { address } => {
+                write!(f, "software breakpoint at {:04X}", address)
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum StepError {
+    IllegalOpcode { address: u16, opcode: u8 },
+}
+
+impl std::fmt::Display for StepError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            StepError::IllegalOpcode { address, opcode } => {
+                write!(f, "illegal opcode {:02X} at {:04X}", opcode, address)
+            }
+        }
+    }
+}
+
+impl std::error::Error for StepError {}
+
 pub struct Gameboy<'a> {
     pub cpu: CPU,
     pub bus: MemoryBus<'a>,
-    pub opcode_info: OpcodeInfo,
     pub interrupts_enabled: bool,
-    pub scanline_counter: u64,
-    pub divider_counter: u8,
-    pub timer_counter: u64,
+    pub ppu: PPU,
+    pub scheduler: Scheduler,
+    /// T-state at which DIV was last reset; TIMA and the frame sequencer are
+    /// clocked from the same counter.
+    pub divider_reset_at: u64,
+    /// Step of the APU frame sequencer, which clocks length, envelope and sweep.
+    pub frame_sequencer_step: u8,
     pub joypad: joypad::Joypad,
+    pub illegal_opcode_policy: IllegalOpcodePolicy,
+    pub timing: Timing,
+    pub dispatch: Dispatch,
+    /// T-states already advanced by the current instruction in `Timing::MCycle` mode.
+    pub(crate) instruction_ticks: u8,
+    /// Execution is suspended until something (the user, a debugger) clears it.
+    pub paused: bool,
+    pub(crate) events: Vec<Event>,
+    pub(crate) step_error: Option<StepError>,
+    pub breakpoints: Breakpoints,
+    /// Labels from the ROM's `.sym` file, used wherever addresses are shown.
+    pub symbols: Symbols,
+    /// Receives a gameboy-doctor line before every instruction when set.
+    pub trace: Option<Tracer>,
+    /// What reads of LY return instead of the current line, when set.
+    pub force_ly: Option<u8>,
+    /// Collects bytes sent over the link cable when set; otherwise they're
+    /// printed to stdout.
+    pub serial_output: Option<Vec<u8>>,
+    /// Pause after executing `LD B,B`, which test ROMs like Mooneye's use as a
+    /// breakpoint.
+    pub break_on_ld_b_b: bool,
+    /// Counts where t-states are spent when set.
+    pub profiler: Option<Profiler>,
+    /// Records how each ROM byte is accessed when set.
+    pub cdl: Option<CodeDataLog>,
+    /// Routines entered through calls and interrupts that haven't returned.
+    pub call_stack: CallStack,
+    /// Told about every memory access the CPU makes when set.
+    pub observer: Option<Box<dyn MemoryObserver>>,
 }
 
+const DIVIDER_PERIOD: u64 = 256;
+const FRAME_SEQUENCER_PERIOD: u64 = 8192;
+/// 8 bits at 8192 Hz using the internal clock.
+const SERIAL_TRANSFER_TICKS: u64 = 4096;
+const OAM_SCAN_TICKS: u64 = 80;
+const DRAWING_TICKS: u64 = 172;
+const LINE_TICKS: u64 = 456;
+
+/// Pauses emulation and opens the debugger prompt on the terminal.
+const DEBUGGER_KEY: sdl2::keyboard::Keycode = sdl2::keyboard::Keycode::Backquote;
+/// How often the screen is redrawn, whatever the emulation speed.
+const FRAME_TIME: Duration = Duration::from_nanos(1_000_000_000 / 60);
+/// Runs the game backwards while held.
+const REWIND_KEY: sdl2::keyboard::Keycode = sdl2::keyboard::Keycode::R;
+
 impl<'a> Default for Gameboy<'a> {
     fn default() -> Self {
-        // load opcode info
-        let json = include_bytes!("Opcodes.json");
-        let opcode_info: OpcodeInfo = serde_json::from_slice(json).unwrap();
-
         let cpu = CPU::default();
 
+        let mut scheduler = Scheduler::default();
+        scheduler.schedule(0, EventKind::OamScan);
+        scheduler.schedule(DIVIDER_PERIOD, EventKind::DividerTick);
+        scheduler.schedule(FRAME_SEQUENCER_PERIOD, EventKind::FrameSequencer);
+
         Self {
             cpu,
-            opcode_info,
             bus: MemoryBus::default(),
             interrupts_enabled: false,
-            scanline_counter: 0,
-            divider_counter: 0,
-            timer_counter: 0,
+            ppu: PPU::default(),
+            scheduler,
+            divider_reset_at: 0,
+            frame_sequencer_step: 0,
             joypad: joypad::Joypad::new(),
+            illegal_opcode_policy: IllegalOpcodePolicy::default(),
+            timing: Timing::default(),
+            dispatch: Dispatch::default(),
+            instruction_ticks: 0,
+            paused: false,
+            events: Vec::new(),
+            step_error: None,
+            breakpoints: Breakpoints::default(),
+            symbols: Symbols::default(),
+            trace: None,
+            force_ly: None,
+            serial_output: None,
+            break_on_ld_b_b: false,
+            profiler: None,
+            cdl: None,
+            call_stack: CallStack::default(),
+            observer: None,
         }
     }
 }
@@ -187,15 +347,43 @@ pub fn initialize(gameboy: &mut Gameboy) {
         });
 }
 
+/// Starts at the cartridge entry point with the state the DMG boot ROM leaves
+/// behind, as gameboy-doctor logs expect.
+pub fn skip_boot_rom(gameboy: &mut Gameboy) {
+    gameboy.bus.boot_rom_enabled = false;
+    gameboy.cpu.registers.f = 0xB0.into();
+    gameboy.cpu.registers.sp = 0xFFFE;
+    gameboy.cpu.registers.pc = 0x0100;
+}
+
 impl<'a> Gameboy<'a> {
-    pub fn run(&mut self, cartridge: &'a [u8; 0x200000]) {
+    pub fn run(&mut self, cartridge: &'a [u8; 0x200000], options: &Options) {
         self.bus.cartridge_rom = cartridge;
 
+        // before SDL starts, so a taken port doesn't leave a window behind
+        let mut gdb = options.gdb_port.map(|port| match GdbServer::listen(port) {
+            Ok(server) => {
+                println!("Waiting for GDB on port {}", server.port());
+                server
+            }
+            Err(error) => {
+                eprintln!("Cannot listen on port {}: {}", port, error);
+                std::process::exit(1);
+            }
+        });
+
         let sdl_context = sdl2::init().unwrap();
 
-        let mut ppu = PPU::new(&sdl_context);
+        let mut screen = Screen::new(&sdl_context);
 
         let mut event_pump = sdl_context.event_pump().unwrap();
+        let mut debugger = Debugger::default();
+        let mut views = Views::default();
+        let mut rewind = Rewind::new(options.rewind);
+        let mut rewinding = false;
+        let mut speed = Speed::new(options.speed.unwrap_or(1.0));
+        speed.fast_forward = options.fast_forward;
+        let mut status = None;
 
         'running: loop {
             let before = Instant::now();
@@ -204,6 +392,43 @@ impl<'a> Gameboy<'a> {
 
                 match event {
                     sdl2::event::Event::Quit { .. } => break 'running,
+                    ref event if views.handle(event, self) => {}
+                    sdl2::event::Event::KeyDown {
+                        keycode: Some(DEBUGGER_KEY),
+                        ..
+                    } => self.paused = true,
+                    sdl2::event::Event::KeyDown {
+                        keycode: Some(REWIND_KEY),
+                        ..
+                    } => rewinding = true,
+                    sdl2::event::Event::KeyUp {
+                        keycode: Some(REWIND_KEY),
+                        ..
+                    } => rewinding = false,
+                    sdl2::event::Event::KeyDown {
+                        keycode: Some(key),
+                        repeat: false,
+                        ..
+                    } if speed.key_down(key) => {}
+                    sdl2::event::Event::KeyUp {
+                        keycode: Some(key), ..
+                    } if speed.key_up(key) => {}
+                    sdl2::event::Event::KeyDown {
+                        keycode: Some(key),
+                        repeat: false,
+                        ..
+                    } if View::for_key(key).is_some() => {
+                        views.toggle(&sdl_context, View::for_key(key).unwrap())
+                    }
+                    sdl2::event::Event::Window {
+                        window_id,
+                        win_event: sdl2::event::WindowEvent::Close,
+                        ..
+                    } if views.close(window_id) => {}
+                    sdl2::event::Event::Window {
+                        win_event: sdl2::event::WindowEvent::Close,
+                        ..
+                    } => break 'running,
                     sdl2::event::Event::KeyUp { keycode, .. } => {
                         if let Some(button) = KEYMAP.get(&keycode.unwrap()) {
                             self.update_joypad_state(button, false);
@@ -218,48 +443,430 @@ impl<'a> Gameboy<'a> {
                 }
             }
 
+            if let Some(gdb) = &mut gdb {
+                gdb.poll(self);
+            }
+            let gdb_attached = gdb.as_ref().is_some_and(GdbServer::attached);
+            if self.paused && !gdb_attached && debugger.repl(self) == Resume::Quit {
+                break 'running;
+            }
+
             let start_frame = Instant::now();
-            self.run_frame(&mut ppu);
+            if !self.paused && rewinding && rewind.enabled() {
+                rewind.rewind(self);
+            } else if !self.paused {
+                // when unthrottled, run frames until it's time to show one
+                let frames = speed.frames_due();
+                let mut run = 0;
+                while !self.paused
+                    && frames.map_or(start_frame.elapsed() < FRAME_TIME, |frames| run < frames)
+                {
+                    match std::panic::catch_unwind(AssertUnwindSafe(|| self.run_frame())) {
+                        Ok(Ok(())) => rewind.frame(self),
+                        Ok(Err(error)) => {
+                            eprintln!("Emulation stopped: {}\n{}", error, self.backtrace());
+                            break 'running;
+                        }
+                        Err(panic) => {
+                            eprintln!("{}", self.backtrace());
+                            std::panic::resume_unwind(panic);
+                        }
+                    }
+                    run += 1;
+                }
+            }
+            if speed.status() != status {
+                status = speed.status();
+                match &status {
+                    Some(status) => {
+                        screen.set_title(&format!("Rust Gameboy Emulator - {}", status))
+                    }
+                    None => screen.set_title("Rust Gameboy Emulator"),
+                }
+            }
+            for event in self.take_events() {
+                match &event {
+                    Event::Breakpoint(hit) => print!("{}\n{}", hit, self.backtrace()),
+                    Event::SoftwareBreakpoint { .. } | Event::IllegalOpcode { .. } => {
+                        print!("{}\n{}", event, self.backtrace())
+                    }
+                }
+                log::warn!("{}", event);
+                screen.set_title(&format!("Rust Gameboy Emulator - {}", event));
+            }
             let frame_duration = start_frame.elapsed();
-            let frame_time = Duration::from_secs_f64(1.0 / 60.0);
-            if frame_duration < frame_time {
-                std::thread::sleep(frame_time - frame_duration);
+            if frame_duration < FRAME_TIME {
+                std::thread::sleep(FRAME_TIME - frame_duration);
             }
             let fps = 1.0 / before.elapsed().as_secs_f64();
             log::info!("FPS: {:.2?}", fps);
-            ppu.draw();
+            screen.draw(&self.ppu);
+            views.draw(self);
+        }
+    }
+
+    /// Runs until the PPU has produced a complete frame.
+    pub fn run_frame(&mut self) -> Result<(), StepError> {
+        while !self.ppu.frame_ready && !self.paused {
+            log::debug!("{:?}", self.cpu.registers);
+            self.step()?;
         }
+        self.ppu.frame_ready = false;
+        Ok(())
     }
 
-    fn run_frame(&mut self, ppu: &mut PPU) {
-        const MAX_TICKS: u64 = 69905 * 4; // 69905 cycles per frame, 4 ticks per cycle
-                                          // frame
-        {
-            let mut frame_ticks: u64 = 0;
-            while frame_ticks < MAX_TICKS {
-                log::debug!("{:?}", self.cpu.registers);
-                let ticks = if self.cpu.halted {
-                    4
+    /// Executes the next instruction, or idles for one cycle when the CPU is
+    /// halted or locked up, then dispatches any pending interrupt. Returns the
+    /// number of t-states that elapsed.
+    pub fn step(&mut self) -> Result<u8, StepError> {
+        self.instruction_ticks = 0;
+        self.breakpoints.set_last_hit(None);
+        let halted = self.cpu.halted || self.cpu.locked;
+        let pc = self.cpu.registers.pc;
+        // CALL and RET count against the routine they're in
+        if let Some(profiler) = &mut self.profiler {
+            profiler.enter(self.call_stack.frames());
+        }
+        let ticks = if halted {
+            4
+        } else {
+            if !self.breakpoints.is_empty() {
+                let pc = self.cpu.registers.pc;
+                let bank = self.rom_bank(pc);
+                if let Some(hit) = self
+                    .breakpoints
+                    .check_execute(&self.cpu.registers, pc, bank)
+                {
+                    self.hit_breakpoint(hit);
+                    return Ok(0);
+                }
+            }
+            if self.trace.is_some() {
+                self.trace_instruction();
+            }
+            self.run_next_instruction()
+        };
+        match self.timing {
+            Timing::Instruction => self.advance(ticks as u64),
+            Timing::MCycle => {
+                // whatever wasn't spent on bus accesses are internal cycles at the end
+                while self.instruction_ticks < ticks {
+                    self.internal_cycle();
+                }
+            }
+        }
+
+        if self.profiler.is_some() {
+            self.profile_instruction(pc, halted, ticks);
+        }
+
+        let interrupt_ticks = self.handle_interrupts();
+        if self.timing == Timing::Instruction {
+            self.advance(interrupt_ticks as u64);
+        }
+        if interrupt_ticks > 0 {
+            if let Some(profiler) = &mut self.profiler {
+                profiler.enter(self.call_stack.frames());
+                profiler.interrupt(interrupt_ticks);
+            }
+        }
+
+        if let Some(hit) = self.breakpoints.take_pending() {
+            self.hit_breakpoint(hit);
+        }
+
+        match self.step_error.take() {
+            Some(error) => Err(error),
+            None => Ok(ticks + interrupt_ticks),
+        }
+    }
+
+    fn hit_breakpoint(&mut self, hit: Hit) {
+        log::info!("Stopped at {}", hit);
+        self.paused = true;
+        self.breakpoints.set_last_hit(Some(hit.clone()));
+        self.events.push(Event::Breakpoint(hit));
+    }
+
+    /// Counts the instruction that just ran from `pc` with `sp`, following
+    /// calls into routines and returns out of them.
+    fn profile_instruction(&mut self, pc: u16, halted: bool, ticks: u8) {
+        let location = self.code_location(pc);
+        let Some(profiler) = &mut self.profiler else {
+            return;
+        };
+        if halted {
+            profiler.halt(ticks);
+        } else {
+            profiler.instruction(location, ticks);
+        }
+    }
+
+    /// Records entering the routine at PC through a push of `return_address`
+    /// by the instruction at `site`.
+    pub(crate) fn enter_routine(&mut self, kind: FrameKind, site: u16, return_address: u16) {
+        let frame = Frame {
+            kind,
+            site: self.code_location(site),
+            target: self.code_location(self.cpu.registers.pc),
+            return_address,
+            sp: self.cpu.registers.sp,
+        };
+        self.call_stack.push(frame);
+    }
+
+    /// Records the return at `at` that popped `target` from `sp`.
+    pub(crate) fn leave_routine(&mut self, at: u16, sp: u16, target: u16) {
+        let at = self.code_location(at);
+        self.call_stack.pop(at, sp, target);
+    }
+
+    /// The shadow call stack from PC outwards, with symbols.
+    pub fn backtrace(&self) -> String {
+        self.call_stack
+            .backtrace(self.code_location(self.cpu.registers.pc), &self.symbols)
+    }
+
+    /// `address` with the ROM bank mapped there, or bank 0 outside ROM.
+    fn code_location(&self, address: u16) -> Location {
+        let bank = if address < 0x8000 {
+            self.rom_bank(address)
+        } else {
+            0
+        };
+        Location { bank, address }
+    }
+
+    /// Where `address` currently maps to in the ROM file, if it's in ROM.
+    pub fn rom_offset(&self, address: u16) -> Option<usize> {
+        if self.bus.boot_rom_enabled && address < self.bus.boot_rom.len() as u16 {
+            return None;
+        }
+        match address {
+            0x0000..=0x3FFF => Some(address as usize),
+            0x4000..=0x7FFF => {
+                Some(self.rom_bank(address) as usize * 0x4000 + (address as usize - 0x4000))
+            }
+            _ => None,
+        }
+    }
+
+    fn log_rom_access(&mut self, address: u16, flag: u8) {
+        let offset = self.rom_offset(address);
+        if let (Some(cdl), Some(offset)) = (&mut self.cdl, offset) {
+            cdl.mark(offset, flag);
+        }
+    }
+
+    fn trace_instruction(&mut self) {
+        let line = trace::line(self);
+        if let Some(Err(error)) = self.trace.as_mut().map(|trace| trace.record(&line)) {
+            log::error!("Stopping trace: {}", error);
+            self.trace = None;
+        }
+    }
+
+    /// The ROM bank mapped at `address`. Without MBC support the switchable
+    /// area always holds bank 1.
+    pub fn rom_bank(&self, address: u16) -> u8 {
+        match address {
+            0x0000..=0x3FFF => 0,
+            _ => 1,
+        }
+    }
+
+    /// Names `address` after the closest label at or before it in the bank
+    /// currently mapped there, or as hex without one.
+    pub fn describe_address(&self, address: u16) -> String {
+        self.symbols.describe(self.rom_bank(address), address)
+    }
+
+    /// Decodes the instruction at `address` with labels for its operands.
+    pub fn decode(&self, address: u16) -> disasm::Instruction {
+        disasm::decode_labeled(
+            |address| self.read_byte(address),
+            address,
+            self.symbols.labeler(self.rom_bank(0x4000)),
+        )
+    }
+
+    /// Moves the clock forward, handling every event that came due.
+    fn advance(&mut self, ticks: u64) {
+        self.scheduler.advance(ticks);
+        while let Some((at, kind)) = self.scheduler.pop_due() {
+            self.handle_event(at, kind);
+        }
+    }
+
+    fn handle_event(&mut self, at: u64, kind: EventKind) {
+        match kind {
+            EventKind::OamScan => {
+                self.set_ppu_mode(ppu::Mode::OamScan);
+                self.scheduler
+                    .schedule(at + OAM_SCAN_TICKS, EventKind::Drawing);
+            }
+            EventKind::Drawing => {
+                self.set_ppu_mode(ppu::Mode::Drawing);
+                self.scheduler
+                    .schedule(at + DRAWING_TICKS, EventKind::HBlank);
+            }
+            EventKind::HBlank => {
+                let line = self.bus.memory[LY];
+                self.ppu.update(&self.bus, line);
+                self.set_ppu_mode(ppu::Mode::HBlank);
+                self.scheduler.schedule(
+                    at + LINE_TICKS - OAM_SCAN_TICKS - DRAWING_TICKS,
+                    EventKind::LineEnd,
+                );
+            }
+            EventKind::LineEnd => {
+                let line = match self.bus.memory[LY] {
+                    153.. => 0,
+                    line => line + 1,
+                };
+                self.bus.memory[LY] = line;
+                self.compare_ly();
+                if (line as usize) < ppu::SCREEN_HEIGHT {
+                    self.handle_event(at, EventKind::OamScan);
                 } else {
-                    self.run_next_instruction()
+                    if line as usize == ppu::SCREEN_HEIGHT {
+                        self.set_ppu_mode(ppu::Mode::VBlank);
+                        self.request_interrupt(Interrupt::VBlank);
+                        self.ppu.frame_ready = true;
+                    }
+                    self.scheduler.schedule(at + LINE_TICKS, EventKind::LineEnd);
+                }
+            }
+            EventKind::DividerTick => {
+                self.bus.memory[DIV] = self.bus.memory[DIV].wrapping_add(1);
+                self.scheduler
+                    .schedule(at + DIVIDER_PERIOD, EventKind::DividerTick);
+            }
+            EventKind::TimerTick => {
+                let tac = self.bus.memory[TAC];
+                let tima = match self.bus.memory[TIMA].checked_add(1) {
+                    Some(tima) => tima,
+                    None => {
+                        self.request_interrupt(Interrupt::Timer);
+                        self.bus.memory[TMA]
+                    }
                 };
+                self.bus.memory[TIMA] = tima;
+                self.scheduler
+                    .schedule(at + Self::timer_period(tac), EventKind::TimerTick);
+            }
+            EventKind::SerialTransfer => {
+                let byte = self.bus.memory[SB];
+                match &mut self.serial_output {
+                    Some(output) => output.push(byte),
+                    None => print!("{}", byte as char),
+                }
+                // nothing is connected, so all ones are shifted in
+                self.bus.memory[SB] = 0xFF;
+                self.bus.memory[SC] &= 0x7F;
+                self.request_interrupt(Interrupt::Serial);
+            }
+            EventKind::FrameSequencer => {
+                self.frame_sequencer_step = (self.frame_sequencer_step + 1) % 8;
+                self.scheduler
+                    .schedule(at + FRAME_SEQUENCER_PERIOD, EventKind::FrameSequencer);
+            }
+        }
+    }
+
+    fn set_ppu_mode(&mut self, mode: ppu::Mode) {
+        let stat = self.bus.memory[STAT];
+        self.bus.memory[STAT] = (stat & !0x03) | mode as u8;
+        let interrupt_source = match mode {
+            ppu::Mode::HBlank => 3,
+            ppu::Mode::VBlank => 4,
+            ppu::Mode::OamScan => 5,
+            ppu::Mode::Drawing => return,
+        };
+        if flag_set_at!(stat, interrupt_source) {
+            self.request_interrupt(Interrupt::LCDStat);
+        }
+    }
 
-                self.handle_interrupts();
-                self.serial_comm();
-                self.update_timers(ticks);
-                self.update_graphics(ticks, ppu);
-                frame_ticks += ticks as u64;
+    fn compare_ly(&mut self) {
+        let stat = self.bus.memory[STAT];
+        if self.bus.memory[LY] == self.bus.memory[LYC] {
+            self.bus.memory[STAT] = stat | 0x04;
+            if flag_set_at!(stat, 6) {
+                self.request_interrupt(Interrupt::LCDStat);
             }
+        } else {
+            self.bus.memory[STAT] = stat & !0x04;
+        }
+    }
+
+    /// T-states between TIMA increments for the frequency selected in TAC.
+    fn timer_period(tac: u8) -> u64 {
+        match tac & 0x3 {
+            0 => 1024,
+            1 => 16,
+            2 => 64,
+            _ => 256,
+        }
+    }
+
+    /// Schedules `kind` on the next multiple of `period` of the divider counter.
+    fn schedule_on_divider(&mut self, period: u64, kind: EventKind) {
+        let counter = self.scheduler.now() - self.divider_reset_at;
+        self.scheduler.cancel(kind);
+        self.scheduler.schedule_in(period - counter % period, kind);
+    }
+
+    fn reschedule_timer(&mut self) {
+        let tac = self.bus.memory[TAC];
+        if flag_set_at!(tac, 2) {
+            self.schedule_on_divider(Self::timer_period(tac), EventKind::TimerTick);
+        } else {
+            self.scheduler.cancel(EventKind::TimerTick);
         }
     }
 
     pub fn run_next_instruction(&mut self) -> u8 {
-        self.get_next_instruction()(self)
+        let address = self.cpu.registers.pc;
+        let (prefixed, opcode) = self.fetch_opcode();
+        let ticks = match (self.dispatch, prefixed) {
+            (Dispatch::Table, false) => instructions::from_byte(opcode)(self),
+            (Dispatch::Table, true) => instructions::from_prefixed_byte(opcode)(self),
+            (Dispatch::Match, false) => instructions::execute(self, opcode),
+            (Dispatch::Match, true) => instructions::execute_prefixed(self, opcode),
+        };
+        if self.break_on_ld_b_b && !prefixed && opcode == 0x40 {
+            log::info!("LD B,B at {:04X}", address);
+            self.paused = true;
+            self.events.push(Event::SoftwareBreakpoint { address });
+        }
+        ticks
     }
 
-    fn handle_interrupts(&mut self) {
-        // TODO: add 20 clocks to the cycle if an interrupt is triggered
-        // 24 if HALTed
+    /// Drains the events raised since the last call.
+    pub fn take_events(&mut self) -> Vec<Event> {
+        std::mem::take(&mut self.events)
+    }
+
+    pub fn illegal_opcode(&mut self, opcode: u8) {
+        let address = self.cpu.registers.pc;
+        log::error!("Illegal opcode {:02X} at {:04X}", opcode, address);
+        self.events.push(Event::IllegalOpcode { address, opcode });
+        match self.illegal_opcode_policy {
+            IllegalOpcodePolicy::LockUp => self.cpu.locked = true,
+            IllegalOpcodePolicy::Break => self.paused = true,
+            IllegalOpcodePolicy::Error => {
+                self.step_error = Some(StepError::IllegalOpcode { address, opcode })
+            }
+        }
+    }
+
+    /// Dispatches a pending interrupt if possible and returns the t-states it took.
+    fn handle_interrupts(&mut self) -> u8 {
+        // TODO: 24 clocks if HALTed
+        if self.cpu.locked {
+            return 0;
+        }
         let interrupt_enable = self.bus.memory[IE];
         let interrupt_flags = self.bus.memory[IF];
         let interrupt_value = interrupt_enable & interrupt_flags;
@@ -273,64 +880,54 @@ impl<'a> Gameboy<'a> {
                 // disable interrupts
                 self.interrupts_enabled = false;
                 let (interrupt, interrupt_handler) = Interrupt::interrupt_address(interrupt_value);
+                let interrupt = u8::from(interrupt);
                 // disable interrupt
-                self.bus.memory[IF] = interrupt_flags & !u8::from(interrupt);
+                self.bus.memory[IF] = interrupt_flags & !interrupt;
                 // push current program counter to stack
                 let pc = self.cpu.registers.get_u16(Register16bTarget::PC);
+                self.internal_cycle();
+                self.internal_cycle();
                 self.stack_push(pc);
                 // jump to interrupt handler
                 self.cpu
                     .registers
                     .set_u16(Register16bTarget::PC, interrupt_handler);
+                self.enter_routine(FrameKind::Interrupt, pc, pc);
+                self.internal_cycle();
+                if !self.breakpoints.is_empty() {
+                    self.breakpoints.check_interrupt(
+                        &self.cpu.registers,
+                        interrupt,
+                        interrupt_handler,
+                    );
+                }
+                const TICKS: u8 = 20;
+                return TICKS;
             }
         }
+        0
     }
 
-    fn serial_comm(&mut self) {
-        // read from serial port if requested
-        if self.bus.memory[SC] == 0x81 {
-            let byte = self.bus.memory[SB];
-            print!("{}", byte as char);
-            self.bus.memory[SB] = 0x0;
-        }
-    }
-
-    fn get_next_instruction(&mut self) -> &'static instructions::Instruction {
+    /// Reads the opcode at PC, following a 0xCB prefix. Returns whether it was
+    /// prefixed along with the opcode byte.
+    fn fetch_opcode(&mut self) -> (bool, u8) {
         let address = self.cpu.registers.get_u16(Register16bTarget::PC);
 
-        let mut instruction_byte = self.read_byte(address);
-
-        let opcode_info;
-        let instruction;
-
-        if instruction_byte == 0xCB {
-            instruction_byte = self.read_byte(address.wrapping_add(1));
+        if log::log_enabled!(log::Level::Debug) {
+            log::debug!(
+                "PC: 0x{:04X} ({}): {}",
+                address,
+                self.describe_address(address),
+                self.decode(address)
+            );
+        }
 
-            instruction = instructions::from_prefixed_byte(instruction_byte);
-            opcode_info = self
-                .opcode_info
-                .cbprefixed
-                .get(&format!("0x{:02X}", instruction_byte))
-                .unwrap();
-        } else {
-            instruction = instructions::from_byte(instruction_byte);
-            opcode_info = self
-                .opcode_info
-                .unprefixed
-                .get(&format!("0x{:02X}", instruction_byte))
-                .unwrap();
-        };
-        log::debug!(
-            "PC: 0x{:04X}: 0x{:02X} instruction: {} {:?}",
-            address,
-            instruction_byte,
-            opcode_info.mnemonic,
-            <Vec<OperandInformation> as Clone>::clone(&opcode_info.operands)
-                .into_iter()
-                .map(|operands| operands.name)
-                .collect::<Vec<String>>(),
-        );
-        instruction
+        let mut instruction_byte = self.read_cycle_as(address, cdl::OPCODE);
+        let prefixed = instruction_byte == 0xCB;
+        if prefixed {
+            instruction_byte = self.read_cycle_as(address.wrapping_add(1), cdl::OPERAND);
+        }
+        (prefixed, instruction_byte)
     }
 
     pub fn request_interrupt(&mut self, interrupt: Interrupt) {
@@ -344,81 +941,110 @@ impl<'a> Gameboy<'a> {
         self.cpu
             .registers
             .set_u16(Register16bTarget::PC, new_address);
-        self.read_byte(new_address)
+        self.read_cycle_as(new_address, cdl::OPERAND)
     }
 
-    fn update_graphics(&mut self, ticks: u8, ppu: &mut PPU) {
-        self.scanline_counter += ticks as u64;
-        if self.scanline_counter >= 456 * 4 {
-            self.scanline_counter = 0;
-            let mut current_scanline = self.bus.memory[LY];
-            if current_scanline == 144 {
-                self.request_interrupt(Interrupt::VBlank);
-            }
-            if current_scanline > 153 {
-                current_scanline = 0;
+    /// Reads a byte on behalf of the CPU, taking one M-cycle.
+    pub fn read_cycle(&mut self, address: u16) -> u8 {
+        self.read_cycle_as(address, cdl::DATA)
+    }
+
+    /// `read_cycle`, logging the access as `flag` when a CDL is recording.
+    fn read_cycle_as(&mut self, address: u16, flag: u8) -> u8 {
+        if self.cdl.is_some() {
+            self.log_rom_access(address, flag);
+        }
+        self.tick_mcycle();
+        let value = self.read_byte(address);
+        // fetching instructions isn't reading them as far as watchpoints go
+        if flag == cdl::DATA && !self.breakpoints.is_empty() {
+            self.breakpoints
+                .check_access(&self.cpu.registers, address, value, false);
+        }
+        if self.observer.is_some() {
+            let kind = if flag == cdl::OPCODE {
+                AccessKind::Execute
             } else {
-                current_scanline += 1;
-            }
-            self.bus.memory[0xFF44] = current_scanline;
-            ppu.update(self, current_scanline);
+                AccessKind::Read
+            };
+            self.observe(address, value, kind);
         }
+        value
     }
 
-    const CLOCK_SPEED: u64 = 4194304;
-    fn update_timers(&mut self, ticks: u8) {
-        let mut tima = self.bus.memory[TIMA]; // timer
-        let tma = self.bus.memory[TMA]; // timer modulo
-        let tac = self.bus.memory[TAC]; // timer controller
-        let mut div = self.bus.memory[DIV];
-
-        let frequency = match tac & 0x3 {
-            0 => 4096,
-            1 => 262144,
-            2 => 65536,
-            3 => 16382,
-            _ => panic!("invalid timer"),
-        };
-
-        let (new_divider_counter, overflow) = self.divider_counter.overflowing_add(ticks);
-        self.divider_counter = new_divider_counter;
-        if overflow {
-            div = div.wrapping_add(1);
+    /// Writes a byte on behalf of the CPU, taking one M-cycle.
+    pub fn write_cycle(&mut self, address: u16, value: u8) {
+        self.tick_mcycle();
+        self.write_byte(address, value);
+        if !self.breakpoints.is_empty() {
+            self.breakpoints
+                .check_access(&self.cpu.registers, address, value, true);
         }
+        if self.observer.is_some() {
+            self.observe(address, value, AccessKind::Write);
+        }
+    }
 
-        if flag_set_at!(tac, 2) {
-            self.timer_counter += ticks as u64;
-            if self.timer_counter >= Self::CLOCK_SPEED / frequency {
-                self.timer_counter = 0;
-                tima = tima.wrapping_add(1);
-                if tima == 0 {
-                    tima = tma;
-                    self.request_interrupt(Interrupt::Timer);
-                }
-            }
+    fn observe(&mut self, address: u16, value: u8, kind: AccessKind) {
+        let cycle = self.scheduler.now();
+        if let Some(observer) = &mut self.observer {
+            observer.observe(MemoryAccess {
+                address,
+                value,
+                kind,
+                cycle,
+            });
         }
+    }
+
+    /// An M-cycle where the CPU doesn't access the bus.
+    pub fn internal_cycle(&mut self) {
+        self.tick_mcycle();
+    }
 
-        self.bus.memory[memory::special_addresses::DIV] = div;
-        self.bus.memory[TIMA] = tima;
+    fn tick_mcycle(&mut self) {
+        if self.timing == Timing::MCycle {
+            self.advance(4);
+            self.instruction_ticks = self.instruction_ticks.wrapping_add(4);
+        }
     }
 
     pub fn read_byte(&self, address: u16) -> u8 {
+        if self.bus.flat {
+            return self.bus.memory[address as usize];
+        }
         if self.bus.boot_rom_enabled && address < self.bus.boot_rom.len() as u16 {
             return self.bus.boot_rom[address as usize];
         }
 
         match address as usize {
-            0x0000..=0x7FFF => self.bus.cartridge_rom[address as usize],
+            // reads past the end of a short ROM see an open bus
+            0x0000..=0x7FFF => self
+                .bus
+                .cartridge_rom
+                .get(address as usize)
+                .copied()
+                .unwrap_or(0xFF),
             special_addresses::P1 => self.get_joypad_state(),
+            special_addresses::LY => self.force_ly.unwrap_or(self.bus.memory[LY]),
             other => self.bus.memory[other as usize],
         }
     }
 
     pub fn write_byte(&mut self, address: u16, value: u8) {
+        if self.bus.flat {
+            self.bus.memory[address as usize] = value;
+            return;
+        }
         match address as usize {
             special_addresses::DMA => {
                 // DMA transfer
                 let start_address = (value as u16) << 8;
+                if self.cdl.is_some() {
+                    for i in 0..0xA0 {
+                        self.log_rom_access(start_address + i, cdl::DMA);
+                    }
+                }
                 for i in 0..0xA0 {
                     let byte = self.bus.memory[(start_address + i) as usize];
                     self.bus.memory[0xFE00 + i as usize] = byte;
@@ -426,6 +1052,29 @@ impl<'a> Gameboy<'a> {
             }
             special_addresses::DIV => {
                 self.bus.memory[special_addresses::DIV] = 0;
+                self.divider_reset_at = self.scheduler.now();
+                self.schedule_on_divider(DIVIDER_PERIOD, EventKind::DividerTick);
+                self.schedule_on_divider(FRAME_SEQUENCER_PERIOD, EventKind::FrameSequencer);
+                self.reschedule_timer();
+                return;
+            }
+            special_addresses::TAC => {
+                self.bus.memory[TAC] = value;
+                self.reschedule_timer();
+                return;
+            }
+            special_addresses::SC => {
+                if value & 0x81 == 0x81 {
+                    self.scheduler.cancel(EventKind::SerialTransfer);
+                    self.scheduler
+                        .schedule_in(SERIAL_TRANSFER_TICKS, EventKind::SerialTransfer);
+                }
+            }
+            special_addresses::STAT => {
+                // the mode and coincidence bits are read only
+                let stat = self.bus.memory[STAT];
+                self.bus.memory[STAT] = (value & 0x78) | (stat & 0x07);
+                return;
             }
             0xFF50 if self.bus.boot_rom_enabled => {
                 log::info!("Disabling boot ROM");
@@ -458,9 +1107,9 @@ impl<'a> Gameboy<'a> {
     pub fn stack_push(&mut self, pc: u16) {
         let [high, low] = pc.to_be_bytes();
         self.cpu.registers.sp = self.cpu.registers.sp.wrapping_sub(1);
-        self.write_byte(self.cpu.registers.sp, high);
+        self.write_cycle(self.cpu.registers.sp, high);
         self.cpu.registers.sp = self.cpu.registers.sp.wrapping_sub(1);
-        self.write_byte(self.cpu.registers.sp, low);
+        self.write_cycle(self.cpu.registers.sp, low);
     }
 
     fn get_joypad_state(&self) -> u8 {
@@ -479,9 +1128,21 @@ impl<'a> Gameboy<'a> {
     }
 }
 
+/// A Game Boy about to run `program` from the start of WRAM, with the stack
+/// further up.
+#[cfg(test)]
+pub(crate) fn gameboy_with_program<'a>(program: &[u8]) -> Gameboy<'a> {
+    let mut gameboy = Gameboy::default();
+    gameboy.bus.memory[0xC000..0xC000 + program.len()].copy_from_slice(program);
+    gameboy.cpu.registers.pc = 0xC000;
+    gameboy.cpu.registers.sp = 0xD000;
+    gameboy
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::breakpoints::{Access, Breakpoint, BreakpointKind, Condition};
     use crate::cpu::{FlagsRegister, RegisterTarget, Registers};
 
     #[test]
@@ -507,4 +1168,368 @@ mod tests {
         assert_eq!(gameboy.cpu.registers.get_u8(RegisterTarget::A), 7);
         assert_eq!(gameboy.cpu.registers.get_u16(Register16bTarget::PC), 0xC051);
     }
+
+    #[test]
+    fn test_mcycle_timing_reads_mid_instruction() {
+        for (timing, expected) in [(Timing::Instruction, 0), (Timing::MCycle, 1)] {
+            // LD A, (0xFF05): the read of TIMA happens on the fourth M-cycle
+            let mut gameboy = gameboy_with_program(&[0xFA, 0x05, 0xFF]);
+            gameboy.timing = timing;
+            // enabled, 16 t-states per increment
+            gameboy.write_byte(TAC as u16, 0x05);
+
+            assert_eq!(gameboy.step(), Ok(16));
+            assert_eq!(gameboy.cpu.registers.a, expected);
+            assert_eq!(gameboy.bus.memory[TIMA], 1);
+        }
+    }
+
+    #[test]
+    fn test_mcycle_timing_pads_internal_cycles() {
+        // JP 0xC000 has an internal cycle after reading its operand
+        let mut gameboy = gameboy_with_program(&[0xC3, 0x00, 0xC0]);
+        gameboy.timing = Timing::MCycle;
+        gameboy.write_byte(TAC as u16, 0x05);
+
+        assert_eq!(gameboy.step(), Ok(16));
+        assert_eq!(gameboy.instruction_ticks, 16);
+        assert_eq!(gameboy.bus.memory[TIMA], 1);
+    }
+
+    #[test]
+    fn test_mcycle_timing_reads_operands_of_untaken_branches() {
+        // JP NZ,$1234; JR NZ,$10; CALL NZ,$1234 with Z set
+        for program in [&[0xC2, 0x34, 0x12][..], &[0x20, 0x10], &[0xC4, 0x34, 0x12]] {
+            let mut gameboy = gameboy_with_program(program);
+            gameboy.timing = Timing::MCycle;
+            gameboy.cpu.registers.f.zero = true;
+            let seen = std::rc::Rc::new(std::cell::RefCell::new(Vec::new()));
+            let sink = seen.clone();
+            gameboy.observer = Some(Box::new(move |access: MemoryAccess| {
+                sink.borrow_mut().push((access.address, access.cycle))
+            }));
+
+            let ticks = gameboy.step().unwrap();
+            assert_eq!(ticks, 4 * program.len() as u8);
+            assert_eq!(gameboy.cpu.registers.pc, 0xC000 + program.len() as u16);
+            let start = seen.borrow()[0].1;
+            let expected: Vec<(u16, u64)> = (0..program.len())
+                .map(|offset| (0xC000 + offset as u16, start + 4 * offset as u64))
+                .collect();
+            assert_eq!(*seen.borrow(), expected);
+        }
+    }
+
+    #[test]
+    fn test_timer_overflow() {
+        let mut gameboy = Gameboy::default();
+        gameboy.bus.memory[TIMA] = 0xFF;
+        gameboy.bus.memory[TMA] = 0x42;
+        gameboy.write_byte(TAC as u16, 0x05);
+
+        gameboy.advance(15);
+        assert_eq!(gameboy.bus.memory[TIMA], 0xFF);
+        gameboy.advance(1);
+        assert_eq!(gameboy.bus.memory[TIMA], 0x42);
+        assert_eq!(gameboy.bus.memory[IF], u8::from(Interrupt::Timer));
+    }
+
+    #[test]
+    fn test_divider_reset_restarts_timer_phase() {
+        let mut gameboy = Gameboy::default();
+        gameboy.write_byte(TAC as u16, 0x04); // 1024 t-states per increment
+        gameboy.advance(1000);
+        gameboy.write_byte(DIV as u16, 0);
+        gameboy.advance(1000);
+        assert_eq!(gameboy.bus.memory[TIMA], 0);
+        assert_eq!(gameboy.bus.memory[DIV], 3);
+        gameboy.advance(24);
+        assert_eq!(gameboy.bus.memory[TIMA], 1);
+        assert_eq!(gameboy.bus.memory[DIV], 4);
+    }
+
+    #[test]
+    fn test_ppu_modes() {
+        let mut gameboy = Gameboy::default();
+        gameboy.bus.memory[STAT] = 0x08; // HBlank interrupt
+
+        gameboy.advance(0);
+        assert_eq!(gameboy.bus.memory[STAT] & 0x03, ppu::Mode::OamScan as u8);
+        gameboy.advance(80);
+        assert_eq!(gameboy.bus.memory[STAT] & 0x03, ppu::Mode::Drawing as u8);
+        gameboy.advance(172);
+        assert_eq!(gameboy.bus.memory[STAT] & 0x03, ppu::Mode::HBlank as u8);
+        assert_eq!(gameboy.bus.memory[IF], u8::from(Interrupt::LCDStat));
+        gameboy.advance(204);
+        assert_eq!(gameboy.bus.memory[LY], 1);
+        assert_eq!(gameboy.bus.memory[STAT] & 0x03, ppu::Mode::OamScan as u8);
+
+        gameboy.advance(143 * 456);
+        assert_eq!(gameboy.bus.memory[LY], 144);
+        assert_eq!(gameboy.bus.memory[STAT] & 0x03, ppu::Mode::VBlank as u8);
+        assert!(gameboy.ppu.frame_ready);
+        assert_ne!(gameboy.bus.memory[IF] & u8::from(Interrupt::VBlank), 0);
+
+        gameboy.advance(10 * 456);
+        assert_eq!(gameboy.bus.memory[LY], 0);
+        assert_eq!(gameboy.scheduler.now(), 70224);
+    }
+
+    #[test]
+    fn test_serial_transfer() {
+        let mut gameboy = Gameboy {
+            serial_output: Some(Vec::new()),
+            ..Default::default()
+        };
+        gameboy.write_byte(SB as u16, b'P');
+        gameboy.write_byte(SC as u16, 0x81);
+
+        gameboy.advance(4095);
+        assert_eq!(gameboy.bus.memory[SC], 0x81);
+        gameboy.advance(1);
+        assert_eq!(gameboy.bus.memory[SC], 0x01);
+        assert_eq!(gameboy.bus.memory[SB], 0xFF);
+        assert_eq!(gameboy.bus.memory[IF], u8::from(Interrupt::Serial));
+        assert_eq!(gameboy.serial_output, Some(b"P".to_vec()));
+    }
+
+    #[test]
+    fn test_flat_bus() {
+        let mut gameboy = Gameboy::default();
+        gameboy.bus.flat = true;
+        gameboy.write_byte(0x0100, 0x12);
+        gameboy.write_byte(STAT as u16, 0xFF);
+        assert_eq!(gameboy.read_byte(0x0100), 0x12);
+        assert_eq!(gameboy.read_byte(STAT as u16), 0xFF);
+        // the boot ROM isn't mapped over it either
+        assert_eq!(gameboy.read_byte(0x0000), 0x00);
+    }
+
+    #[test]
+    fn test_read_past_end_of_rom() {
+        let mut gameboy = Gameboy::default();
+        gameboy.bus.boot_rom_enabled = false;
+        gameboy.bus.cartridge_rom = &[0x12, 0x34];
+        assert_eq!(gameboy.read_byte(0x0001), 0x34);
+        assert_eq!(gameboy.read_byte(0x0002), 0xFF);
+        assert_eq!(gameboy.read_byte(0x7FFF), 0xFF);
+    }
+
+    #[test]
+    fn test_code_data_log() {
+        let mut gameboy = Gameboy::default();
+        let cartridge = vec![0; 0x8000].leak();
+        // LD A,[$4010]; SWAP A
+        cartridge[..5].copy_from_slice(&[0xFA, 0x10, 0x40, 0xCB, 0x37]);
+        gameboy.bus.cartridge_rom = cartridge;
+        gameboy.bus.boot_rom_enabled = false;
+        gameboy.cdl = Some(CodeDataLog::new(0x8000));
+        gameboy.step().unwrap();
+        gameboy.step().unwrap();
+        gameboy.write_byte(DMA as u16, 0x01);
+
+        let cdl = gameboy.cdl.unwrap();
+        let flags: Vec<u8> = (0..6).map(|offset| cdl.flags(offset)).collect();
+        assert_eq!(
+            flags,
+            [
+                cdl::OPCODE,
+                cdl::OPERAND,
+                cdl::OPERAND,
+                cdl::OPCODE,
+                cdl::OPERAND,
+                0
+            ]
+        );
+        assert_eq!(cdl.flags(0x4010), cdl::DATA);
+        assert_eq!(cdl.flags(0x0100), cdl::DMA);
+        assert_eq!(cdl.flags(0x01A0), 0);
+    }
+
+    #[test]
+    fn test_ld_b_b_breakpoint() {
+        // LD B,B; INC A
+        let mut gameboy = gameboy_with_program(&[0x40, 0x3C]);
+        gameboy.step().unwrap();
+        assert!(!gameboy.paused);
+
+        gameboy.cpu.registers.pc = 0xC000;
+        gameboy.break_on_ld_b_b = true;
+        gameboy.step().unwrap();
+        assert!(gameboy.paused);
+        assert_eq!(gameboy.cpu.registers.pc, 0xC001);
+        assert_eq!(
+            gameboy.take_events(),
+            vec![Event::SoftwareBreakpoint { address: 0xC000 }]
+        );
+    }
+
+    fn gameboy_with_illegal_opcode<'a>(policy: IllegalOpcodePolicy) -> Gameboy<'a> {
+        let mut gameboy = Gameboy {
+            illegal_opcode_policy: policy,
+            ..Default::default()
+        };
+        gameboy.cpu.registers.pc = 0xC000;
+        gameboy.bus.memory[0xC000] = 0xD3;
+        gameboy
+    }
+
+    #[test]
+    fn test_illegal_opcode_locks_up() {
+        let mut gameboy = gameboy_with_illegal_opcode(IllegalOpcodePolicy::LockUp);
+        assert_eq!(gameboy.step(), Ok(4));
+        assert!(gameboy.cpu.locked);
+
+        // interrupts can't wake a locked up CPU
+        gameboy.interrupts_enabled = true;
+        gameboy.bus.memory[IE] = 0x01;
+        gameboy.request_interrupt(Interrupt::VBlank);
+        gameboy.handle_interrupts();
+        assert_eq!(gameboy.step(), Ok(4));
+        assert_eq!(gameboy.cpu.registers.pc, 0xC000);
+        assert_eq!(
+            gameboy.take_events(),
+            vec![Event::IllegalOpcode {
+                address: 0xC000,
+                opcode: 0xD3
+            }]
+        );
+    }
+
+    #[test]
+    fn test_illegal_opcode_breaks() {
+        let mut gameboy = gameboy_with_illegal_opcode(IllegalOpcodePolicy::Break);
+        assert_eq!(gameboy.step(), Ok(4));
+        assert!(gameboy.paused);
+        assert!(!gameboy.cpu.locked);
+        assert_eq!(gameboy.cpu.registers.pc, 0xC000);
+    }
+
+    #[test]
+    fn test_illegal_opcode_errors() {
+        let mut gameboy = gameboy_with_illegal_opcode(IllegalOpcodePolicy::Error);
+        assert_eq!(
+            gameboy.step(),
+            Err(StepError::IllegalOpcode {
+                address: 0xC000,
+                opcode: 0xD3
+            })
+        );
+        assert!(!gameboy.cpu.locked);
+    }
+
+    fn run_until_paused(gameboy: &mut Gameboy) {
+        for _ in 0..100 {
+            if gameboy.paused {
+                return;
+            }
+            gameboy.step().unwrap();
+        }
+        panic!("never paused");
+    }
+
+    #[test]
+    fn test_execute_breakpoint() {
+        // INC A; INC A; INC A; JR -5
+        let mut gameboy = gameboy_with_program(&[0x3C, 0x3C, 0x3C, 0x18, 0xFB]);
+        gameboy.breakpoints.add(
+            Breakpoint::new(BreakpointKind::Execute {
+                address: 0xC002,
+                bank: None,
+            })
+            .with_condition(Condition::parse("a >= 4").unwrap()),
+        );
+
+        run_until_paused(&mut gameboy);
+        assert_eq!(gameboy.cpu.registers.pc, 0xC002);
+        assert_eq!(gameboy.cpu.registers.a, 5);
+        assert_eq!(
+            gameboy.take_events(),
+            vec![Event::Breakpoint(Hit::Execute {
+                id: 1,
+                address: 0xC002
+            })]
+        );
+
+        // resuming runs the instruction under the breakpoint
+        gameboy.paused = false;
+        gameboy.step().unwrap();
+        assert_eq!(gameboy.cpu.registers.a, 6);
+        run_until_paused(&mut gameboy);
+        assert_eq!(gameboy.cpu.registers.a, 8);
+    }
+
+    #[test]
+    fn test_watchpoint() {
+        // LD HL,$C100; LD A,(HL); LD (HL),A
+        let mut gameboy = gameboy_with_program(&[0x21, 0x00, 0xC1, 0x7E, 0x77]);
+        gameboy.bus.memory[0xC100] = 0x42;
+        gameboy
+            .breakpoints
+            .add(Breakpoint::new(BreakpointKind::Watch {
+                range: 0xC100..=0xC1FF,
+                access: Access::Write,
+            }));
+
+        run_until_paused(&mut gameboy);
+        assert_eq!(gameboy.cpu.registers.pc, 0xC005);
+        assert_eq!(
+            gameboy.take_events(),
+            vec![Event::Breakpoint(Hit::Write {
+                id: 1,
+                address: 0xC100,
+                value: 0x42
+            })]
+        );
+    }
+
+    #[test]
+    fn test_read_watchpoint_ignores_instruction_fetches() {
+        // NOP; JR -3; LD A,[$C000]
+        let mut gameboy = gameboy_with_program(&[0x00, 0x18, 0xFD, 0xFA, 0x00, 0xC0]);
+        gameboy
+            .breakpoints
+            .add(Breakpoint::new(BreakpointKind::Watch {
+                range: 0xC000..=0xC005,
+                access: Access::ReadWrite,
+            }));
+        for _ in 0..10 {
+            gameboy.step().unwrap();
+        }
+        assert!(!gameboy.paused);
+        assert!(gameboy.take_events().is_empty());
+
+        // reading the same bytes as data does trigger it
+        gameboy.cpu.registers.pc = 0xC003;
+        run_until_paused(&mut gameboy);
+        assert_eq!(
+            gameboy.take_events(),
+            vec![Event::Breakpoint(Hit::Read {
+                id: 1,
+                address: 0xC000,
+                value: 0x00
+            })]
+        );
+    }
+
+    #[test]
+    fn test_interrupt_breakpoint() {
+        let mut gameboy = gameboy_with_program(&[0x00]);
+        gameboy
+            .breakpoints
+            .add(Breakpoint::new(BreakpointKind::Interrupt { mask: 0x04 }));
+        gameboy.interrupts_enabled = true;
+        gameboy.bus.memory[IE] = 0x05;
+        gameboy.request_interrupt(Interrupt::Timer);
+
+        run_until_paused(&mut gameboy);
+        assert_eq!(gameboy.cpu.registers.pc, 0x0050);
+        assert_eq!(
+            gameboy.take_events(),
+            vec![Event::Breakpoint(Hit::Interrupt {
+                id: 1,
+                vector: 0x0050
+            })]
+        );
+    }
 }
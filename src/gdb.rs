@@ -0,0 +1,479 @@
+//! A GDB remote serial protocol server, so debuggers and IDEs can attach over TCP.
+//!
+//! The register file is exposed as six 16-bit registers: AF, BC, DE, HL, SP and
+//! PC, in that order and little-endian, as described by the target XML.
+
+use std::collections::HashMap;
+use std::io::{ErrorKind, Read, Write};
+use std::net::{TcpListener, TcpStream};
+
+use crate::breakpoints::{Access, Breakpoint, BreakpointKind, Hit};
+use crate::cpu::Register16bTarget;
+use crate::gameboy::Gameboy;
+
+const REGISTERS: [Register16bTarget; 6] = [
+    Register16bTarget::AF,
+    Register16bTarget::BC,
+    Register16bTarget::DE,
+    Register16bTarget::HL,
+    Register16bTarget::SP,
+    Register16bTarget::PC,
+];
+
+const TARGET_XML: &str = r#"<?xml version="1.0"?>
+<!DOCTYPE target SYSTEM "gdb-target.dtd">
+<target version="1.0">
+  <feature name="org.gnu.gdb.sm83.core">
+    <reg name="af" bitsize="16" type="int"/>
+    <reg name="bc" bitsize="16" type="int"/>
+    <reg name="de" bitsize="16" type="int"/>
+    <reg name="hl" bitsize="16" type="int"/>
+    <reg name="sp" bitsize="16" type="data_ptr"/>
+    <reg name="pc" bitsize="16" type="code_ptr"/>
+  </feature>
+</target>
+"#;
+
+const SIGINT: u8 = 2;
+const SIGTRAP: u8 = 5;
+
+fn checksum(data: &str) -> u8 {
+    data.bytes().fold(0, |sum, byte| sum.wrapping_add(byte))
+}
+
+fn hex_u16(text: &str) -> Option<u16> {
+    u16::from_str_radix(text, 16).ok()
+}
+
+fn decode_hex(text: &str) -> Option<Vec<u8>> {
+    (0..text.len())
+        .step_by(2)
+        .map(|index| u8::from_str_radix(text.get(index..index + 2)?, 16).ok())
+        .collect()
+}
+
+/// Splits `addr,length` as used by memory and breakpoint packets.
+fn address_and_length(text: &str) -> Option<(u16, u16)> {
+    let (address, length) = text.split_once(',')?;
+    Some((hex_u16(address)?, hex_u16(length)?))
+}
+
+/// Protocol state for one attached debugger, independent of the transport.
+#[derive(Debug, Default)]
+pub struct Session {
+    /// Breakpoints set by the debugger, keyed by the `type,addr,kind` it used.
+    breakpoints: HashMap<String, usize>,
+    /// Set while the debugger is waiting for a stop reply.
+    running: bool,
+}
+
+impl Session {
+    /// Handles one packet, returning the reply or `None` if the reply is a stop
+    /// reply that will be sent once the target stops.
+    pub fn handle_packet(&mut self, gameboy: &mut Gameboy, packet: &str) -> Option<String> {
+        let (command, arguments) = packet.split_at(packet.len().min(1));
+        let reply = match command {
+            "?" => self.stop_reply(gameboy, SIGTRAP),
+            "g" => REGISTERS
+                .iter()
+                .map(|register| {
+                    let [low, high] = gameboy.cpu.registers.get_u16(*register).to_le_bytes();
+                    format!("{:02x}{:02x}", low, high)
+                })
+                .collect(),
+            "G" => match decode_hex(arguments) {
+                Some(bytes) if bytes.len() >= REGISTERS.len() * 2 => {
+                    for (register, value) in REGISTERS.iter().zip(bytes.chunks(2)) {
+                        let value = u16::from_le_bytes([value[0], value[1]]);
+                        gameboy.cpu.registers.set_u16(*register, value);
+                    }
+                    "OK".to_string()
+                }
+                _ => "E01".to_string(),
+            },
+            "p" => match usize::from_str_radix(arguments, 16)
+                .ok()
+                .and_then(|index| REGISTERS.get(index))
+            {
+                Some(register) => {
+                    let [low, high] = gameboy.cpu.registers.get_u16(*register).to_le_bytes();
+                    format!("{:02x}{:02x}", low, high)
+                }
+                None => "E01".to_string(),
+            },
+            "P" => {
+                let register = arguments.split_once('=').and_then(|(index, value)| {
+                    let register = REGISTERS.get(usize::from_str_radix(index, 16).ok()?)?;
+                    Some((register, decode_hex(value)?))
+                });
+                match register {
+                    Some((register, value)) if value.len() == 2 => {
+                        let value = u16::from_le_bytes([value[0], value[1]]);
+                        gameboy.cpu.registers.set_u16(*register, value);
+                        "OK".to_string()
+                    }
+                    _ => "E01".to_string(),
+                }
+            }
+            "m" => match address_and_length(arguments) {
+                Some((address, length)) => (0..length)
+                    .map(|offset| {
+                        format!("{:02x}", gameboy.read_byte(address.wrapping_add(offset)))
+                    })
+                    .collect(),
+                None => "E01".to_string(),
+            },
+            "M" => {
+                let write = arguments.split_once(':').and_then(|(location, data)| {
+                    Some((address_and_length(location)?, decode_hex(data)?))
+                });
+                match write {
+                    Some(((address, _), data)) => {
+                        for (offset, byte) in data.into_iter().enumerate() {
+                            gameboy.write_byte(address.wrapping_add(offset as u16), byte);
+                        }
+                        "OK".to_string()
+                    }
+                    None => "E01".to_string(),
+                }
+            }
+            "s" | "c" => {
+                if let Some(address) = hex_u16(arguments) {
+                    gameboy.cpu.registers.pc = address;
+                }
+                if command == "s" {
+                    gameboy.paused = false;
+                    let result = gameboy.step();
+                    gameboy.paused = true;
+                    if let Err(error) = result {
+                        log::warn!("{}", error);
+                    }
+                    self.stop_reply(gameboy, SIGTRAP)
+                } else {
+                    gameboy.paused = false;
+                    self.running = true;
+                    return None;
+                }
+            }
+            "Z" | "z" => self.breakpoint(gameboy, command == "Z", arguments),
+            "k" | "D" => {
+                self.detach(gameboy);
+                "OK".to_string()
+            }
+            "H" => "OK".to_string(),
+            "q" => self.query(arguments),
+            _ => String::new(),
+        };
+        Some(reply)
+    }
+
+    fn query(&self, query: &str) -> String {
+        if query.starts_with("Supported") {
+            return "PacketSize=1000;qXfer:features:read+".to_string();
+        }
+        if let Some(range) = query.strip_prefix("Xfer:features:read:target.xml:") {
+            let Some((offset, length)) = range.split_once(',').and_then(|(offset, length)| {
+                Some((
+                    usize::from_str_radix(offset, 16).ok()?,
+                    usize::from_str_radix(length, 16).ok()?,
+                ))
+            }) else {
+                return "E01".to_string();
+            };
+            let start = offset.min(TARGET_XML.len());
+            let end = (start + length).min(TARGET_XML.len());
+            let more = if end < TARGET_XML.len() { 'm' } else { 'l' };
+            return format!("{}{}", more, &TARGET_XML[start..end]);
+        }
+        match query {
+            "Attached" => "1".to_string(),
+            "C" => "QC1".to_string(),
+            "fThreadInfo" => "m1".to_string(),
+            "sThreadInfo" => "l".to_string(),
+            _ => String::new(),
+        }
+    }
+
+    fn breakpoint(&mut self, gameboy: &mut Gameboy, insert: bool, arguments: &str) -> String {
+        let Some((kind, location)) = arguments.split_once(',') else {
+            return "E01".to_string();
+        };
+        let Some((address, length)) = address_and_length(location) else {
+            return "E01".to_string();
+        };
+        if !insert {
+            if let Some(id) = self.breakpoints.remove(arguments) {
+                gameboy.breakpoints.remove(id);
+            }
+            return "OK".to_string();
+        }
+
+        let end = address.wrapping_add(length.max(1) - 1);
+        let kind = match kind {
+            "0" | "1" => BreakpointKind::Execute {
+                address,
+                bank: None,
+            },
+            "2" => BreakpointKind::Watch {
+                range: address..=end,
+                access: Access::Write,
+            },
+            "3" => BreakpointKind::Watch {
+                range: address..=end,
+                access: Access::Read,
+            },
+            "4" => BreakpointKind::Watch {
+                range: address..=end,
+                access: Access::ReadWrite,
+            },
+            _ => return String::new(),
+        };
+        let id = gameboy.breakpoints.add(Breakpoint::new(kind));
+        self.breakpoints.insert(arguments.to_string(), id);
+        "OK".to_string()
+    }
+
+    fn stop_reply(&self, gameboy: &Gameboy, signal: u8) -> String {
+        match gameboy.breakpoints.last_hit() {
+            Some(Hit::Write { address, .. }) => format!("T{:02x}watch:{:x};", signal, address),
+            Some(Hit::Read { address, .. }) => format!("T{:02x}rwatch:{:x};", signal, address),
+            _ => format!("S{:02x}", signal),
+        }
+    }
+
+    /// Checks whether a running target has stopped, returning the stop reply.
+    pub fn poll_stop(&mut self, gameboy: &Gameboy) -> Option<String> {
+        if self.running && gameboy.paused {
+            self.running = false;
+            return Some(self.stop_reply(gameboy, SIGTRAP));
+        }
+        None
+    }
+
+    /// Stops a running target on the debugger's request (Ctrl-C).
+    pub fn interrupt(&mut self, gameboy: &mut Gameboy) -> Option<String> {
+        if !self.running {
+            return None;
+        }
+        self.running = false;
+        gameboy.paused = true;
+        Some(format!("S{:02x}", SIGINT))
+    }
+
+    /// Removes everything the debugger set up and lets the game run.
+    fn detach(&mut self, gameboy: &mut Gameboy) {
+        for (_, id) in self.breakpoints.drain() {
+            gameboy.breakpoints.remove(id);
+        }
+        self.running = false;
+        gameboy.paused = false;
+    }
+}
+
+/// Listens on a local port and serves one debugger at a time. Polled by the
+/// frontend between frames so the game keeps running while nobody is attached.
+pub struct GdbServer {
+    listener: TcpListener,
+    client: Option<(TcpStream, Session)>,
+    buffer: Vec<u8>,
+}
+
+impl GdbServer {
+    pub fn listen(port: u16) -> std::io::Result<GdbServer> {
+        let listener = TcpListener::bind(("127.0.0.1", port))?;
+        listener.set_nonblocking(true)?;
+        Ok(GdbServer {
+            listener,
+            client: None,
+            buffer: Vec::new(),
+        })
+    }
+
+    pub fn port(&self) -> u16 {
+        self.listener
+            .local_addr()
+            .map_or(0, |address| address.port())
+    }
+
+    /// Whether a debugger is attached, in which case it owns pausing and resuming.
+    pub fn attached(&self) -> bool {
+        self.client.is_some()
+    }
+
+    /// Accepts a new connection, handles any packets that arrived and reports
+    /// if the target stopped since the last poll.
+    pub fn poll(&mut self, gameboy: &mut Gameboy) {
+        if self.client.is_none() {
+            if let Ok((stream, address)) = self.listener.accept() {
+                log::info!("GDB attached from {}", address);
+                if stream.set_nonblocking(true).is_ok() {
+                    gameboy.paused = true;
+                    self.client = Some((stream, Session::default()));
+                    self.buffer.clear();
+                }
+            }
+        }
+        if let Err(error) = self.serve(gameboy) {
+            log::info!("GDB detached: {}", error);
+            if let Some((_, mut session)) = self.client.take() {
+                session.detach(gameboy);
+            }
+        }
+    }
+
+    fn serve(&mut self, gameboy: &mut Gameboy) -> std::io::Result<()> {
+        let Some((stream, session)) = &mut self.client else {
+            return Ok(());
+        };
+
+        let mut chunk = [0; 4096];
+        loop {
+            match stream.read(&mut chunk) {
+                Ok(0) => return Err(ErrorKind::ConnectionAborted.into()),
+                Ok(count) => self.buffer.extend_from_slice(&chunk[..count]),
+                Err(error) if error.kind() == ErrorKind::WouldBlock => break,
+                Err(error) => return Err(error),
+            }
+        }
+
+        let mut replies = Vec::new();
+        while let Some(start) = self
+            .buffer
+            .iter()
+            .position(|byte| *byte == b'$' || *byte == 0x03)
+        {
+            if self.buffer[start] == 0x03 {
+                self.buffer.drain(..=start);
+                replies.extend(session.interrupt(gameboy));
+                continue;
+            }
+            let Some(end) = self.buffer[start..].iter().position(|byte| *byte == b'#') else {
+                break;
+            };
+            let end = start + end;
+            if self.buffer.len() < end + 3 {
+                break;
+            }
+            let packet = String::from_utf8_lossy(&self.buffer[start + 1..end]).into_owned();
+            self.buffer.drain(..end + 3);
+
+            stream.write_all(b"+")?;
+            replies.extend(session.handle_packet(gameboy, &packet));
+        }
+        replies.extend(session.poll_stop(gameboy));
+
+        for reply in replies {
+            write!(stream, "${}#{:02x}", reply, checksum(&reply))?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gameboy::gameboy_with_program;
+
+    fn paused_gameboy<'a>(program: &[u8]) -> Gameboy<'a> {
+        let mut gameboy = gameboy_with_program(program);
+        gameboy.paused = true;
+        gameboy
+    }
+
+    fn reply(session: &mut Session, gameboy: &mut Gameboy, packet: &str) -> String {
+        session.handle_packet(gameboy, packet).unwrap()
+    }
+
+    #[test]
+    fn test_registers() {
+        let mut gameboy = paused_gameboy(&[]);
+        let mut session = Session::default();
+        gameboy.cpu.registers.a = 0x12;
+        gameboy.cpu.registers.h = 0xC1;
+
+        assert_eq!(
+            reply(&mut session, &mut gameboy, "g"),
+            "00120000000000c100d000c0"
+        );
+        assert_eq!(reply(&mut session, &mut gameboy, "p5"), "00c0");
+        assert_eq!(reply(&mut session, &mut gameboy, "P2=3412"), "OK");
+        assert_eq!(gameboy.cpu.registers.d, 0x12);
+        assert_eq!(gameboy.cpu.registers.e, 0x34);
+    }
+
+    #[test]
+    fn test_memory() {
+        let mut gameboy = paused_gameboy(&[0x3C, 0x3C]);
+        let mut session = Session::default();
+
+        assert_eq!(reply(&mut session, &mut gameboy, "mc000,3"), "3c3c00");
+        assert_eq!(reply(&mut session, &mut gameboy, "Mc001,2:abcd"), "OK");
+        assert_eq!(gameboy.read_byte(0xC002), 0xCD);
+    }
+
+    #[test]
+    fn test_step_and_continue_to_breakpoint() {
+        // INC A; INC A; INC A
+        let mut gameboy = paused_gameboy(&[0x3C, 0x3C, 0x3C]);
+        let mut session = Session::default();
+
+        assert_eq!(reply(&mut session, &mut gameboy, "s"), "S05");
+        assert_eq!(gameboy.cpu.registers.pc, 0xC001);
+
+        assert_eq!(reply(&mut session, &mut gameboy, "Z0,c002,1"), "OK");
+        assert_eq!(session.handle_packet(&mut gameboy, "c"), None);
+        assert_eq!(session.poll_stop(&gameboy), None);
+        while !gameboy.paused {
+            gameboy.step().unwrap();
+        }
+        assert_eq!(session.poll_stop(&gameboy), Some("S05".to_string()));
+        assert_eq!(gameboy.cpu.registers.pc, 0xC002);
+
+        assert_eq!(reply(&mut session, &mut gameboy, "z0,c002,1"), "OK");
+        assert!(gameboy.breakpoints.is_empty());
+    }
+
+    #[test]
+    fn test_target_xml() {
+        let mut gameboy = paused_gameboy(&[]);
+        let mut session = Session::default();
+        let start = reply(
+            &mut session,
+            &mut gameboy,
+            "qXfer:features:read:target.xml:0,10",
+        );
+        assert_eq!(start, "m<?xml version=\"1");
+        let end = reply(
+            &mut session,
+            &mut gameboy,
+            "qXfer:features:read:target.xml:0,1000",
+        );
+        assert!(end.starts_with('l') && end.ends_with("</target>\n"));
+    }
+
+    #[test]
+    fn test_server_over_tcp() {
+        let mut gameboy = paused_gameboy(&[]);
+        let mut server = GdbServer::listen(0).unwrap();
+        let mut client = TcpStream::connect(("127.0.0.1", server.port())).unwrap();
+        client.write_all(b"$p5#a5").unwrap();
+
+        let expected = b"+$00c0#f3";
+        let mut received = Vec::new();
+        for _ in 0..100 {
+            if received.len() >= expected.len() {
+                break;
+            }
+            server.poll(&mut gameboy);
+            client
+                .set_read_timeout(Some(std::time::Duration::from_millis(10)))
+                .unwrap();
+            let mut chunk = [0; 64];
+            if let Ok(count) = client.read(&mut chunk) {
+                received.extend_from_slice(&chunk[..count]);
+            }
+        }
+        assert_eq!(received, expected);
+        assert!(server.attached());
+    }
+}
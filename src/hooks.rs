@@ -0,0 +1,79 @@
+//! Observing the memory accesses the CPU makes, for tools built on top of the
+//! emulator such as tracers, cheat finders and achievement checkers.
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum AccessKind {
+    /// The first byte of an instruction being fetched for execution.
+    Execute,
+    /// Operands and data read by an instruction.
+    Read,
+    Write,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct MemoryAccess {
+    pub address: u16,
+    pub value: u8,
+    pub kind: AccessKind,
+    /// The t-state the access happened at. With `Timing::Instruction` all
+    /// accesses of an instruction report the t-state it started at.
+    pub cycle: u64,
+}
+
+/// Sees every access the CPU makes on the bus, in order, after it happened.
+/// Reads by debugging tools don't go through here.
+///
+/// Closures taking a `MemoryAccess` are observers too.
+pub trait MemoryObserver {
+    fn observe(&mut self, access: MemoryAccess);
+}
+
+impl<F: FnMut(MemoryAccess)> MemoryObserver for F {
+    fn observe(&mut self, access: MemoryAccess) {
+        self(access)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    use super::*;
+    use crate::gameboy::gameboy_with_program;
+
+    #[test]
+    fn test_observer_sees_cpu_accesses() {
+        // LD A,[$C100]; LD [$C101],A
+        let mut gameboy = gameboy_with_program(&[0xFA, 0x00, 0xC1, 0xEA, 0x01, 0xC1]);
+        gameboy.write_byte(0xC100, 0x42);
+        let seen = Rc::new(RefCell::new(Vec::new()));
+        let sink = seen.clone();
+        gameboy.observer = Some(Box::new(move |access: MemoryAccess| {
+            sink.borrow_mut().push(access)
+        }));
+        gameboy.step().unwrap();
+        gameboy.step().unwrap();
+
+        let seen = seen.borrow();
+        let accesses: Vec<(u16, u8, AccessKind)> = seen
+            .iter()
+            .map(|access| (access.address, access.value, access.kind))
+            .collect();
+        assert_eq!(
+            accesses,
+            [
+                (0xC000, 0xFA, AccessKind::Execute),
+                (0xC001, 0x00, AccessKind::Read),
+                (0xC002, 0xC1, AccessKind::Read),
+                (0xC100, 0x42, AccessKind::Read),
+                (0xC003, 0xEA, AccessKind::Execute),
+                (0xC004, 0x01, AccessKind::Read),
+                (0xC005, 0xC1, AccessKind::Read),
+                (0xC101, 0x42, AccessKind::Write),
+            ]
+        );
+        assert_eq!(seen[3].cycle, seen[0].cycle);
+        assert_eq!(seen[4].cycle, seen[0].cycle + 16);
+    }
+}
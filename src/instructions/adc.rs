@@ -42,7 +42,7 @@ pub fn adc_n8(gameboy: &mut Gameboy) -> u8 {
 
 pub fn adc_mem_at_hl(gameboy: &mut Gameboy) -> u8 {
     let addr = gameboy.cpu.registers.get_u16(Register16bTarget::HL);
-    let addend = gameboy.read_byte(addr);
+    let addend = gameboy.read_cycle(addr);
     let current_value = gameboy.cpu.registers.get_u8(RegisterTarget::A);
 
     let carry_in = if gameboy.cpu.registers.f.carry { 1 } else { 0 };
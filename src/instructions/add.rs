@@ -19,7 +19,7 @@ pub fn add(target: RegisterTarget) -> impl Fn(&mut Gameboy) -> u8 {
 
 pub fn add_mem_at_hl(gameboy: &mut Gameboy) -> u8 {
     let hl = gameboy.cpu.registers.get_u16(Register16bTarget::HL);
-    let target_value = gameboy.read_byte(hl);
+    let target_value = gameboy.read_cycle(hl);
     let current_value = gameboy.cpu.registers.a;
     let (new_value, did_overflow) = current_value.overflowing_add(target_value);
     gameboy.cpu.registers.a = new_value;
@@ -21,7 +21,7 @@ pub fn and(target: RegisterTarget) -> impl Fn(&mut Gameboy) -> u8 {
 
 pub fn and_mem_at_r16(gameboy: &mut Gameboy) -> u8 {
     let addr = gameboy.cpu.registers.get_u16(Register16bTarget::HL);
-    let value = gameboy.read_byte(addr);
+    let value = gameboy.read_cycle(addr);
     let a = gameboy.cpu.registers.get_u8(RegisterTarget::A);
 
     let result = a & value;
@@ -5,11 +5,11 @@ use crate::gameboy::Gameboy;
 pub fn set_mem_at_hl(bit_position: u8) -> impl Fn(&mut Gameboy) -> u8 {
     move |gameboy: &mut Gameboy| {
         let hl = gameboy.cpu.registers.get_u16(Register16bTarget::HL);
-        let value = gameboy.read_byte(hl);
+        let value = gameboy.read_cycle(hl);
         let result = value | (1 << bit_position);
 
-        gameboy.write_byte(hl, result);
-        const CYCLES: u8 = 4;
+        gameboy.write_cycle(hl, result);
+        const CYCLES: u8 = 16;
         CYCLES
     }
 }
@@ -20,7 +20,7 @@ pub fn set_r(target: RegisterTarget, bit_position: u8) -> impl Fn(&mut Gameboy)
         let result = value | (1 << bit_position);
 
         gameboy.cpu.registers.set_u8(target, result);
-        const CYCLES: u8 = 4;
+        const CYCLES: u8 = 8;
         CYCLES
     }
 }
@@ -28,11 +28,11 @@ pub fn set_r(target: RegisterTarget, bit_position: u8) -> impl Fn(&mut Gameboy)
 pub fn res_mem_at_hl(bit_position: u8) -> impl Fn(&mut Gameboy) -> u8 {
     move |gameboy: &mut Gameboy| {
         let hl = gameboy.cpu.registers.get_u16(Register16bTarget::HL);
-        let value = gameboy.read_byte(hl);
+        let value = gameboy.read_cycle(hl);
         let result = value & !(1 << bit_position);
 
-        gameboy.write_byte(hl, result);
-        const CYCLES: u8 = 4;
+        gameboy.write_cycle(hl, result);
+        const CYCLES: u8 = 16;
         CYCLES
     }
 }
@@ -43,7 +43,7 @@ pub fn res_r(target: RegisterTarget, bit_position: u8) -> impl Fn(&mut Gameboy)
         let result = value & !(1 << bit_position);
 
         gameboy.cpu.registers.set_u8(target, result);
-        const CYCLES: u8 = 4;
+        const CYCLES: u8 = 8;
         CYCLES
     }
 }
@@ -65,14 +65,14 @@ pub fn bit_r(target: RegisterTarget, bit_position: u8) -> impl Fn(&mut Gameboy)
 pub fn bit_mem_at_hl(bit_position: u8) -> impl Fn(&mut Gameboy) -> u8 {
     move |gameboy: &mut Gameboy| {
         let addr = gameboy.cpu.registers.get_u16(Register16bTarget::HL);
-        let value = gameboy.read_byte(addr);
+        let value = gameboy.read_cycle(addr);
         let result = value & (1 << bit_position);
 
         gameboy.cpu.registers.f.zero = result == 0;
         gameboy.cpu.registers.f.subtract = false;
         gameboy.cpu.registers.f.half_carry = true;
 
-        const CYCLES: u8 = 16;
+        const CYCLES: u8 = 12;
         CYCLES
     }
 }
@@ -1,63 +1,82 @@
+use crate::call_stack::FrameKind;
 use crate::gameboy::Gameboy;
 
 pub fn call_a16(gameboy: &mut Gameboy) -> u8 {
-    let [pc_high, pc_low] = gameboy.cpu.registers.pc.wrapping_add(3).to_be_bytes();
-    gameboy.cpu.registers.sp = gameboy.cpu.registers.sp.wrapping_sub(1);
-    gameboy.write_byte(gameboy.cpu.registers.sp, pc_high);
-    gameboy.cpu.registers.sp = gameboy.cpu.registers.sp.wrapping_sub(1);
-    gameboy.write_byte(gameboy.cpu.registers.sp, pc_low);
-
     let low = gameboy.read_next_byte();
     let high = gameboy.read_next_byte();
+    call(gameboy, low, high)
+}
+
+/// Calls the address whose bytes have just been read.
+fn call(gameboy: &mut Gameboy, low: u8, high: u8) -> u8 {
+    // PC now points at the high byte of the operand
+    let return_address = gameboy.cpu.registers.pc.wrapping_add(1);
+    gameboy.internal_cycle();
+    gameboy.stack_push(return_address);
 
     gameboy.cpu.registers.pc = u16::from_le_bytes([low, high]);
+    gameboy.enter_routine(
+        FrameKind::Call,
+        return_address.wrapping_sub(3),
+        return_address,
+    );
     const TICKS: u8 = 24;
     TICKS
 }
 
 pub fn call_nz_a16(gameboy: &mut Gameboy) -> u8 {
+    let low = gameboy.read_next_byte();
+    let high = gameboy.read_next_byte();
     if !gameboy.cpu.registers.f.zero {
-        call_a16(gameboy)
+        call(gameboy, low, high)
     } else {
-        gameboy.cpu.registers.pc = gameboy.cpu.registers.pc.wrapping_add(3);
+        gameboy.cpu.registers.pc = gameboy.cpu.registers.pc.wrapping_add(1);
         12
     }
 }
 
 pub fn call_z_a16(gameboy: &mut Gameboy) -> u8 {
+    let low = gameboy.read_next_byte();
+    let high = gameboy.read_next_byte();
     if gameboy.cpu.registers.f.zero {
-        call_a16(gameboy)
+        call(gameboy, low, high)
     } else {
-        gameboy.cpu.registers.pc = gameboy.cpu.registers.pc.wrapping_add(3);
+        gameboy.cpu.registers.pc = gameboy.cpu.registers.pc.wrapping_add(1);
         12
     }
 }
 
 pub fn call_nc_a16(gameboy: &mut Gameboy) -> u8 {
+    let low = gameboy.read_next_byte();
+    let high = gameboy.read_next_byte();
     if !gameboy.cpu.registers.f.carry {
-        call_a16(gameboy)
+        call(gameboy, low, high)
     } else {
-        gameboy.cpu.registers.pc = gameboy.cpu.registers.pc.wrapping_add(3);
+        gameboy.cpu.registers.pc = gameboy.cpu.registers.pc.wrapping_add(1);
         12
     }
 }
 
 pub fn call_c_a16(gameboy: &mut Gameboy) -> u8 {
+    let low = gameboy.read_next_byte();
+    let high = gameboy.read_next_byte();
     if gameboy.cpu.registers.f.carry {
-        call_a16(gameboy)
+        call(gameboy, low, high)
     } else {
-        gameboy.cpu.registers.pc = gameboy.cpu.registers.pc.wrapping_add(3);
+        gameboy.cpu.registers.pc = gameboy.cpu.registers.pc.wrapping_add(1);
         12
     }
 }
 
 pub fn ret(gameboy: &mut Gameboy) -> u8 {
-    let low = gameboy.read_byte(gameboy.cpu.registers.sp);
+    let (at, sp) = (gameboy.cpu.registers.pc, gameboy.cpu.registers.sp);
+    let low = gameboy.read_cycle(gameboy.cpu.registers.sp);
     gameboy.cpu.registers.sp = gameboy.cpu.registers.sp.wrapping_add(1);
-    let high = gameboy.read_byte(gameboy.cpu.registers.sp);
+    let high = gameboy.read_cycle(gameboy.cpu.registers.sp);
     gameboy.cpu.registers.sp = gameboy.cpu.registers.sp.wrapping_add(1);
 
     gameboy.cpu.registers.pc = u16::from_le_bytes([low, high]);
+    gameboy.leave_routine(at, sp, gameboy.cpu.registers.pc);
     const TICKS: u8 = 16;
     TICKS
 }
@@ -68,6 +87,7 @@ pub fn reti(gameboy: &mut Gameboy) -> u8 {
 }
 
 pub fn ret_nz(gameboy: &mut Gameboy) -> u8 {
+    gameboy.internal_cycle();
     if !gameboy.cpu.registers.f.zero {
         ret(gameboy);
         const TICKS: u8 = 20;
@@ -79,6 +99,7 @@ pub fn ret_nz(gameboy: &mut Gameboy) -> u8 {
 }
 
 pub fn ret_z(gameboy: &mut Gameboy) -> u8 {
+    gameboy.internal_cycle();
     if gameboy.cpu.registers.f.zero {
         ret(gameboy);
         const TICKS: u8 = 20;
@@ -90,6 +111,7 @@ pub fn ret_z(gameboy: &mut Gameboy) -> u8 {
 }
 
 pub fn ret_nc(gameboy: &mut Gameboy) -> u8 {
+    gameboy.internal_cycle();
     if !gameboy.cpu.registers.f.carry {
         ret(gameboy);
         const TICKS: u8 = 20;
@@ -101,6 +123,7 @@ pub fn ret_nc(gameboy: &mut Gameboy) -> u8 {
 }
 
 pub fn ret_c(gameboy: &mut Gameboy) -> u8 {
+    gameboy.internal_cycle();
     if gameboy.cpu.registers.f.carry {
         ret(gameboy);
         const TICKS: u8 = 20;
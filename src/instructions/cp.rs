@@ -29,7 +29,7 @@ pub(crate) fn cp_mem_at_r16(hl: super::Register16bTarget) -> impl Fn(&mut Gamebo
     move |gameboy| {
         let a = gameboy.cpu.registers.get_u8(RegisterTarget::A);
         let addr = gameboy.cpu.registers.get_u16(hl);
-        let value = gameboy.read_byte(addr);
+        let value = gameboy.read_cycle(addr);
         gameboy.cpu.registers.f.zero = a == value;
         gameboy.cpu.registers.f.subtract = true;
         gameboy.cpu.registers.f.half_carry = (a & 0x0F) < (value & 0x0F);
@@ -18,9 +18,9 @@ pub fn dec_r(target: RegisterTarget) -> impl Fn(&mut Gameboy) -> u8 {
 
 pub fn dec_mem_at_hl(gameboy: &mut Gameboy) -> u8 {
     let address = gameboy.cpu.registers.get_u16(Register16bTarget::HL);
-    let current_value = gameboy.read_byte(address);
+    let current_value = gameboy.read_cycle(address);
     let new_value = current_value.wrapping_sub(1);
-    gameboy.write_byte(address, new_value);
+    gameboy.write_cycle(address, new_value);
 
     gameboy.cpu.registers.f.zero = new_value == 0;
     gameboy.cpu.registers.f.subtract = true;
@@ -0,0 +1,272 @@
+//! Executes opcodes straight from a `match`, decoding register operands from the
+//! opcode's bit fields. Runs the same instruction implementations as
+//! `from_byte`/`from_prefixed_byte` without boxing or an indirect call.
+
+use super::*;
+use crate::gameboy::Gameboy;
+
+/// Decodes the 3-bit register field used by most opcodes; `None` means (HL).
+fn register(bits: u8) -> Option<RegisterTarget> {
+    match bits & 0b111 {
+        0 => Some(RegisterTarget::B),
+        1 => Some(RegisterTarget::C),
+        2 => Some(RegisterTarget::D),
+        3 => Some(RegisterTarget::E),
+        4 => Some(RegisterTarget::H),
+        5 => Some(RegisterTarget::L),
+        6 => None,
+        _ => Some(RegisterTarget::A),
+    }
+}
+
+/// Decodes the 2-bit register pair field of the 16-bit loads and arithmetic.
+fn register_pair(bits: u8) -> Register16bTarget {
+    match bits & 0b11 {
+        0 => Register16bTarget::BC,
+        1 => Register16bTarget::DE,
+        2 => Register16bTarget::HL,
+        _ => Register16bTarget::SP,
+    }
+}
+
+/// Like `register_pair`, but PUSH and POP use AF in place of SP.
+fn stack_register_pair(bits: u8) -> Register16bTarget {
+    match bits & 0b11 {
+        3 => Register16bTarget::AF,
+        other => register_pair(other),
+    }
+}
+
+/// Executes an unprefixed opcode and returns the t-states it took.
+pub fn execute(gameboy: &mut Gameboy, byte: u8) -> u8 {
+    let y = byte >> 3;
+    let p = byte >> 4;
+
+    match byte {
+        0x00 => pai(nop::nop)(gameboy),
+        0x10 => pai(misc::stop)(gameboy),
+        0x76 => pai(misc::halt)(gameboy),
+
+        0x01 | 0x11 | 0x21 | 0x31 => pai(ld::ld_r16_n16(register_pair(p)))(gameboy),
+        0x02 | 0x12 => pai(ld::ld_mem_at_r16_r(register_pair(p), RegisterTarget::A))(gameboy),
+        0x0A | 0x1A => pai(ld::ld_r_mem_at_r16(register_pair(p), RegisterTarget::A))(gameboy),
+        0x03 | 0x13 | 0x23 | 0x33 => pai(inc::inc_r16(register_pair(p)))(gameboy),
+        0x0B | 0x1B | 0x2B | 0x3B => pai(dec::dec_r16(register_pair(p)))(gameboy),
+        0x09 | 0x19 | 0x29 | 0x39 => pai(add::add_hl_r16(register_pair(p)))(gameboy),
+        0x08 => pai(ld::ld_mem_at_d16_sp)(gameboy),
+
+        0x22 => pai(ld::ld_mem_at_hl_a_inc)(gameboy),
+        0x32 => pai(ld::ld_mem_at_hl_a_dec)(gameboy),
+        0x2A => pai(ld::ld_a_mem_at_hl_inc)(gameboy),
+        0x3A => pai(ld::ld_a_mem_at_hl_dec)(gameboy),
+
+        0x04 | 0x0C | 0x14 | 0x1C | 0x24 | 0x2C | 0x34 | 0x3C => match register(y) {
+            Some(target) => pai(inc::inc_r(target))(gameboy),
+            None => pai(inc::inc_mem_at_hl)(gameboy),
+        },
+        0x05 | 0x0D | 0x15 | 0x1D | 0x25 | 0x2D | 0x35 | 0x3D => match register(y) {
+            Some(target) => pai(dec::dec_r(target))(gameboy),
+            None => pai(dec::dec_mem_at_hl)(gameboy),
+        },
+        0x06 | 0x0E | 0x16 | 0x1E | 0x26 | 0x2E | 0x36 | 0x3E => match register(y) {
+            Some(target) => pai(ld::ld_d8_r(target))(gameboy),
+            None => pai(ld::ld_d8_mem_at_r16(Register16bTarget::HL))(gameboy),
+        },
+
+        0x07 => pai(rot::rlc_a)(gameboy),
+        0x0F => pai(rot::rrc_a)(gameboy),
+        0x17 => pai(rot::rl_a)(gameboy),
+        0x1F => pai(rot::rr_a)(gameboy),
+        0x27 => pai(misc::daa)(gameboy),
+        0x2F => pai(misc::cpl)(gameboy),
+        0x37 => pai(misc::scf)(gameboy),
+        0x3F => pai(misc::ccf)(gameboy),
+
+        0x18 => jmp::jr(gameboy),
+        0x20 => jmp::jr_nz(gameboy),
+        0x28 => jmp::jr_z(gameboy),
+        0x30 => jmp::jr_nc(gameboy),
+        0x38 => jmp::jr_c(gameboy),
+
+        0x40..=0x7F => match (register(byte), register(y)) {
+            (Some(src), Some(dst)) => pai(ld::ld_r_r(src, dst))(gameboy),
+            (None, Some(dst)) => pai(ld::ld_r_mem_at_hl(dst))(gameboy),
+            (Some(src), None) => pai(ld::ld_mem_at_hl_r(src))(gameboy),
+            (None, None) => unreachable!("HALT is handled above"),
+        },
+
+        0x80..=0xBF => match (y & 0b111, register(byte)) {
+            (0, Some(target)) => pai(add::add(target))(gameboy),
+            (0, None) => pai(add::add_mem_at_hl)(gameboy),
+            (1, Some(target)) => pai(adc::adc(target))(gameboy),
+            (1, None) => pai(adc::adc_mem_at_hl)(gameboy),
+            (2, Some(target)) => pai(sub::sub_r_r_a(target))(gameboy),
+            (2, None) => pai(sub::sub_mem_at_hl)(gameboy),
+            (3, Some(target)) => pai(sbc::sbc_r_r_a(target))(gameboy),
+            (3, None) => pai(sbc::sbc_mem_at_hl)(gameboy),
+            (4, Some(target)) => pai(and::and(target))(gameboy),
+            (4, None) => pai(and::and_mem_at_r16)(gameboy),
+            (5, Some(target)) => pai(xor::xor(target))(gameboy),
+            (5, None) => pai(xor::xor_mem_at_r16(Register16bTarget::HL))(gameboy),
+            (6, Some(target)) => pai(or::or(target))(gameboy),
+            (6, None) => pai(or::or_mem_at_r16(Register16bTarget::HL))(gameboy),
+            (_, Some(target)) => pai(cp::cp(target))(gameboy),
+            (_, None) => pai(cp::cp_mem_at_r16(Register16bTarget::HL))(gameboy),
+        },
+
+        0xC6 => pai(add::add_d8)(gameboy),
+        0xCE => pai(adc::adc_n8)(gameboy),
+        0xD6 => pai(sub::sub_d8)(gameboy),
+        0xDE => pai(sbc::sbc_n8)(gameboy),
+        0xE6 => pai(and::and_d8)(gameboy),
+        0xEE => pai(xor::xor_d8)(gameboy),
+        0xF6 => pai(or::or_d8)(gameboy),
+        0xFE => pai(cp::cp_d8)(gameboy),
+
+        0xC1 | 0xD1 | 0xE1 | 0xF1 => pai(stack::pop(stack_register_pair(p)))(gameboy),
+        0xC5 | 0xD5 | 0xE5 | 0xF5 => pai(stack::push(stack_register_pair(p)))(gameboy),
+
+        0xC3 => jmp::jmp_a16(gameboy),
+        0xC2 => jmp::jp_nz_a16(gameboy),
+        0xCA => jmp::jp_z_a16(gameboy),
+        0xD2 => jmp::jp_nc_a16(gameboy),
+        0xDA => jmp::jp_c_a16(gameboy),
+        0xE9 => jmp::jp_hl(gameboy),
+
+        0xCD => call::call_a16(gameboy),
+        0xC4 => call::call_nz_a16(gameboy),
+        0xCC => call::call_z_a16(gameboy),
+        0xD4 => call::call_nc_a16(gameboy),
+        0xDC => call::call_c_a16(gameboy),
+
+        0xC9 => call::ret(gameboy),
+        0xD9 => call::reti(gameboy),
+        0xC0 => call::ret_nz(gameboy),
+        0xC8 => call::ret_z(gameboy),
+        0xD0 => call::ret_nc(gameboy),
+        0xD8 => call::ret_c(gameboy),
+
+        0xC7 | 0xCF | 0xD7 | 0xDF | 0xE7 | 0xEF | 0xF7 | 0xFF => rst::rst(byte & 0x38)(gameboy),
+
+        0xE0 => pai(ld::ld_mem_at_d8_a)(gameboy),
+        0xF0 => pai(ld::ld_a_mem_at_d8)(gameboy),
+        0xE2 => pai(ld::ld_mem_at_c_a)(gameboy),
+        0xF2 => pai(ld::ld_a_mem_at_c)(gameboy),
+        0xEA => pai(ld::ld_mem_at_d16_r(RegisterTarget::A))(gameboy),
+        0xFA => pai(ld::ld_r_mem_at_d16(RegisterTarget::A))(gameboy),
+
+        0xE8 => pai(add::add_sp_n8)(gameboy),
+        0xF8 => pai(ld::ld_hl_sp_n8)(gameboy),
+        0xF9 => pai(ld::ld_sp_hl)(gameboy),
+
+        0xF3 => pai(int::di)(gameboy),
+        0xFB => pai(int::ei)(gameboy),
+
+        0xD3 | 0xDB | 0xDD | 0xE3 | 0xE4 | 0xEB | 0xEC | 0xED | 0xF4 | 0xFC | 0xFD => {
+            misc::illegal(byte)(gameboy)
+        }
+
+        0xCB => unreachable!("CB is dispatched by execute_prefixed"),
+    }
+}
+
+/// Executes the opcode following a 0xCB prefix and returns the t-states it took.
+pub fn execute_prefixed(gameboy: &mut Gameboy, byte: u8) -> u8 {
+    let bit = (byte >> 3) & 0b111;
+
+    match (byte >> 6, register(byte)) {
+        (0, target) => match (bit, target) {
+            (0, Some(target)) => dpai(rot::rlc_r(target))(gameboy),
+            (0, None) => dpai(rot::rlc_mem_at_hl)(gameboy),
+            (1, Some(target)) => dpai(rot::rrc_r(target))(gameboy),
+            (1, None) => dpai(rot::rrc_mem_at_hl)(gameboy),
+            (2, Some(target)) => dpai(rot::rl_r(target))(gameboy),
+            (2, None) => dpai(rot::rl_mem_at_hl)(gameboy),
+            (3, Some(target)) => dpai(rot::rr_r(target))(gameboy),
+            (3, None) => dpai(rot::rr_mem_at_hl)(gameboy),
+            (4, Some(target)) => dpai(shift::sla(target))(gameboy),
+            (4, None) => dpai(shift::sla_mem_at_hl)(gameboy),
+            (5, Some(target)) => dpai(shift::sra(target))(gameboy),
+            (5, None) => dpai(shift::sra_mem_at_hl)(gameboy),
+            (6, Some(target)) => dpai(swap::swap(target))(gameboy),
+            (6, None) => dpai(swap::swap_mem_at_hl)(gameboy),
+            (_, Some(target)) => dpai(shift::srl(target))(gameboy),
+            (_, None) => dpai(shift::srl_mem_at_hl)(gameboy),
+        },
+        (1, Some(target)) => dpai(bit::bit_r(target, bit))(gameboy),
+        (1, None) => dpai(bit::bit_mem_at_hl(bit))(gameboy),
+        (2, Some(target)) => dpai(bit::res_r(target, bit))(gameboy),
+        (2, None) => dpai(bit::res_mem_at_hl(bit))(gameboy),
+        (_, Some(target)) => dpai(bit::set_r(target, bit))(gameboy),
+        (_, None) => dpai(bit::set_mem_at_hl(bit))(gameboy),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn gameboy_with_state<'a>(flags: u8) -> Gameboy<'a> {
+        let mut gameboy = Gameboy::default();
+        let registers = &mut gameboy.cpu.registers;
+        registers.a = 0x3C;
+        registers.b = 0x81;
+        registers.c = 0x12;
+        registers.d = 0xC2;
+        registers.e = 0x7F;
+        registers.h = 0xC1;
+        registers.l = 0x40;
+        registers.f = FlagsRegister::from(flags);
+        registers.pc = 0xC000;
+        registers.sp = 0xD000;
+        for (offset, value) in [0x34, 0xC1, 0xF0].into_iter().enumerate() {
+            gameboy.bus.memory[0xC001 + offset] = value;
+        }
+        gameboy.bus.memory[0xC140] = 0x5A;
+        gameboy
+    }
+
+    fn assert_same_state(byte: &str, table: &Gameboy, matched: &Gameboy) {
+        assert_eq!(
+            format!("{:?}", table.cpu.registers),
+            format!("{:?}", matched.cpu.registers),
+            "{}",
+            byte
+        );
+        assert_eq!(table.cpu.halted, matched.cpu.halted, "{}", byte);
+        assert_eq!(table.cpu.locked, matched.cpu.locked, "{}", byte);
+        assert!(table.bus.memory == matched.bus.memory, "{}", byte);
+    }
+
+    #[test]
+    fn test_execute_matches_table() {
+        for flags in [0x00, 0xF0] {
+            for byte in (0..=0xFF).filter(|byte| *byte != 0xCB) {
+                let mut table = gameboy_with_state(flags);
+                let mut matched = gameboy_with_state(flags);
+                let table_ticks = from_byte(byte)(&mut table);
+                let matched_ticks = execute(&mut matched, byte);
+
+                let name = format!("{:02X}", byte);
+                assert_eq!(table_ticks, matched_ticks, "{}", name);
+                assert_same_state(&name, &table, &matched);
+            }
+        }
+    }
+
+    #[test]
+    fn test_execute_prefixed_matches_table() {
+        for flags in [0x00, 0xF0] {
+            for byte in 0..=0xFF {
+                let mut table = gameboy_with_state(flags);
+                let mut matched = gameboy_with_state(flags);
+                let table_ticks = from_prefixed_byte(byte)(&mut table);
+                let matched_ticks = execute_prefixed(&mut matched, byte);
+
+                let name = format!("CB {:02X}", byte);
+                assert_eq!(table_ticks, matched_ticks, "{}", name);
+                assert_same_state(&name, &table, &matched);
+            }
+        }
+    }
+}
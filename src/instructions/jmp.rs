@@ -9,64 +9,64 @@ pub fn jmp_a16(gameboy: &mut Gameboy) -> u8 {
 }
 
 pub fn jp_nz_a16(gameboy: &mut Gameboy) -> u8 {
+    let low = gameboy.read_next_byte();
+    let high = gameboy.read_next_byte();
     if gameboy.cpu.registers.f.zero {
-        gameboy.cpu.registers.pc = gameboy.cpu.registers.pc.wrapping_add(3);
+        gameboy.cpu.registers.pc = gameboy.cpu.registers.pc.wrapping_add(1);
         const TICKS: u8 = 12;
         return TICKS;
     }
-    let low = gameboy.read_next_byte();
-    let high = gameboy.read_next_byte();
     gameboy.cpu.registers.pc = u16::from_be_bytes([high, low]);
     const TICKS: u8 = 16;
     TICKS
 }
 
 pub fn jp_z_a16(gameboy: &mut Gameboy) -> u8 {
+    let low = gameboy.read_next_byte();
+    let high = gameboy.read_next_byte();
     if !gameboy.cpu.registers.f.zero {
-        gameboy.cpu.registers.pc = gameboy.cpu.registers.pc.wrapping_add(3);
+        gameboy.cpu.registers.pc = gameboy.cpu.registers.pc.wrapping_add(1);
         const TICKS: u8 = 12;
         return TICKS;
     }
-    let low = gameboy.read_next_byte();
-    let high = gameboy.read_next_byte();
     gameboy.cpu.registers.pc = u16::from_be_bytes([high, low]);
     const TICKS: u8 = 16;
     TICKS
 }
 
 pub fn jp_nc_a16(gameboy: &mut Gameboy) -> u8 {
+    let low = gameboy.read_next_byte();
+    let high = gameboy.read_next_byte();
     if gameboy.cpu.registers.f.carry {
-        gameboy.cpu.registers.pc = gameboy.cpu.registers.pc.wrapping_add(3);
+        gameboy.cpu.registers.pc = gameboy.cpu.registers.pc.wrapping_add(1);
         const TICKS: u8 = 12;
         return TICKS;
     }
-    let low = gameboy.read_next_byte();
-    let high = gameboy.read_next_byte();
     gameboy.cpu.registers.pc = u16::from_be_bytes([high, low]);
     const TICKS: u8 = 16;
     TICKS
 }
 
 pub fn jp_c_a16(gameboy: &mut Gameboy) -> u8 {
+    let low = gameboy.read_next_byte();
+    let high = gameboy.read_next_byte();
     if !gameboy.cpu.registers.f.carry {
-        gameboy.cpu.registers.pc = gameboy.cpu.registers.pc.wrapping_add(3);
+        gameboy.cpu.registers.pc = gameboy.cpu.registers.pc.wrapping_add(1);
         const TICKS: u8 = 12;
         return TICKS;
     }
-    let low = gameboy.read_next_byte();
-    let high = gameboy.read_next_byte();
     gameboy.cpu.registers.pc = u16::from_be_bytes([high, low]);
     const TICKS: u8 = 16;
     TICKS
 }
 
 pub fn jr_z(gameboy: &mut Gameboy) -> u8 {
+    let offset = gameboy.read_next_byte();
     if !gameboy.cpu.registers.f.zero {
-        gameboy.cpu.registers.pc = gameboy.cpu.registers.pc.wrapping_add(2);
+        gameboy.cpu.registers.pc = gameboy.cpu.registers.pc.wrapping_add(1);
         const TICKS: u8 = 8;
         return TICKS;
     }
-    let offset = gameboy.read_next_byte();
     gameboy.cpu.registers.pc = gameboy
         .cpu
         .registers
@@ -77,12 +77,12 @@ pub fn jr_z(gameboy: &mut Gameboy) -> u8 {
 }
 
 pub fn jr_nz(gameboy: &mut Gameboy) -> u8 {
+    let offset = gameboy.read_next_byte();
     if gameboy.cpu.registers.f.zero {
-        gameboy.cpu.registers.pc = gameboy.cpu.registers.pc.wrapping_add(2);
+        gameboy.cpu.registers.pc = gameboy.cpu.registers.pc.wrapping_add(1);
         const TICKS: u8 = 8;
         return TICKS;
     }
-    let offset = gameboy.read_next_byte();
     gameboy.cpu.registers.pc = gameboy
         .cpu
         .registers
@@ -93,12 +93,12 @@ pub fn jr_nz(gameboy: &mut Gameboy) -> u8 {
 }
 
 pub fn jr_nc(gameboy: &mut Gameboy) -> u8 {
+    let offset = gameboy.read_next_byte();
     if gameboy.cpu.registers.f.carry {
-        gameboy.cpu.registers.pc = gameboy.cpu.registers.pc.wrapping_add(2);
+        gameboy.cpu.registers.pc = gameboy.cpu.registers.pc.wrapping_add(1);
         const TICKS: u8 = 8;
         return TICKS;
     }
-    let offset = gameboy.read_next_byte();
     gameboy.cpu.registers.pc = gameboy
         .cpu
         .registers
@@ -109,12 +109,12 @@ pub fn jr_nc(gameboy: &mut Gameboy) -> u8 {
 }
 
 pub fn jr_c(gameboy: &mut Gameboy) -> u8 {
+    let offset = gameboy.read_next_byte();
     if !gameboy.cpu.registers.f.carry {
-        gameboy.cpu.registers.pc = gameboy.cpu.registers.pc.wrapping_add(2);
+        gameboy.cpu.registers.pc = gameboy.cpu.registers.pc.wrapping_add(1);
         const TICKS: u8 = 8;
         return TICKS;
     }
-    let offset = gameboy.read_next_byte();
     gameboy.cpu.registers.pc = gameboy
         .cpu
         .registers
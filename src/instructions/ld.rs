@@ -16,8 +16,8 @@ pub fn ld_mem_at_d16_sp(gameboy: &mut Gameboy) -> u8 {
     let sp = gameboy.cpu.registers.get_u16(Register16bTarget::SP);
     let addr = u16::from_le_bytes([gameboy.read_next_byte(), gameboy.read_next_byte()]);
     let [low, high] = sp.to_le_bytes();
-    gameboy.write_byte(addr, low);
-    gameboy.write_byte(addr.wrapping_add(1), high);
+    gameboy.write_cycle(addr, low);
+    gameboy.write_cycle(addr.wrapping_add(1), high);
     const TICKS: u8 = 20;
     TICKS
 }
@@ -32,7 +32,7 @@ pub fn ld_r_r(src: RegisterTarget, dest: RegisterTarget) -> impl Fn(&mut Gameboy
 }
 
 pub fn ld_sp_hl(gameboy: &mut Gameboy) -> u8 {
-    const CYCLES: u8 = 2;
+    const CYCLES: u8 = 8;
     let hl = gameboy.cpu.registers.get_u16(Register16bTarget::HL);
     gameboy.cpu.registers.set_u16(Register16bTarget::SP, hl);
     CYCLES
@@ -44,7 +44,7 @@ pub fn ld_mem_at_d16_r(src: RegisterTarget) -> impl Fn(&mut Gameboy) -> u8 {
         let high = gameboy.read_next_byte();
         let addr = u16::from_be_bytes([high, low]);
         let value = gameboy.cpu.registers.get_u8(src);
-        gameboy.write_byte(addr, value);
+        gameboy.write_cycle(addr, value);
         const TICKS: u8 = 16;
         TICKS
     }
@@ -55,7 +55,7 @@ pub fn ld_r_mem_at_d16(target: RegisterTarget) -> impl Fn(&mut Gameboy) -> u8 {
         let low = gameboy.read_next_byte();
         let high = gameboy.read_next_byte();
         let addr = u16::from_be_bytes([high, low]);
-        let value = gameboy.read_byte(addr);
+        let value = gameboy.read_cycle(addr);
         gameboy.cpu.registers.set_u8(target, value);
         const TICKS: u8 = 16;
         TICKS
@@ -68,7 +68,7 @@ pub fn ld_r_mem_at_r16(
 ) -> impl Fn(&mut Gameboy) -> u8 {
     move |gameboy: &mut Gameboy| {
         let addr = gameboy.cpu.registers.get_u16(src);
-        let value = gameboy.read_byte(addr);
+        let value = gameboy.read_cycle(addr);
         gameboy.cpu.registers.set_u8(dest, value);
         const TICKS: u8 = 8;
         TICKS
@@ -83,7 +83,7 @@ pub fn ld_mem_at_r16_r(
     move |gameboy: &mut Gameboy| {
         let addr = gameboy.cpu.registers.get_u16(reg);
         let value = gameboy.cpu.registers.get_u8(target);
-        gameboy.write_byte(addr, value);
+        gameboy.write_cycle(addr, value);
         TICKS
     }
 }
@@ -92,7 +92,7 @@ pub fn ld_d8_mem_at_r16(reg: Register16bTarget) -> impl Fn(&mut Gameboy) -> u8 {
     move |gameboy: &mut Gameboy| {
         let addr = gameboy.cpu.registers.get_u16(reg);
         let value = gameboy.read_next_byte();
-        gameboy.write_byte(addr, value);
+        gameboy.write_cycle(addr, value);
         const TICKS: u8 = 12;
         TICKS
     }
@@ -110,14 +110,14 @@ pub fn ld_d8_r(target: RegisterTarget) -> impl Fn(&mut Gameboy) -> u8 {
 pub fn ld_mem_at_d8_a(gameboy: &mut Gameboy) -> u8 {
     let addr = 0xFF00 + gameboy.read_next_byte() as u16;
     let value = gameboy.cpu.registers.get_u8(RegisterTarget::A);
-    gameboy.write_byte(addr, value);
+    gameboy.write_cycle(addr, value);
     const TICKS: u8 = 12;
     TICKS
 }
 
 pub fn ld_a_mem_at_d8(gameboy: &mut Gameboy) -> u8 {
     let addr = 0xFF00 + gameboy.read_next_byte() as u16;
-    let value = gameboy.read_byte(addr);
+    let value = gameboy.read_cycle(addr);
     gameboy.cpu.registers.set_u8(RegisterTarget::A, value);
     const TICKS: u8 = 12;
     TICKS
@@ -127,7 +127,7 @@ pub fn ld_mem_at_hl_r(target: RegisterTarget) -> impl Fn(&mut Gameboy) -> u8 {
     move |gameboy: &mut Gameboy| {
         let addr = gameboy.cpu.registers.get_u16(Register16bTarget::HL);
         let value = gameboy.cpu.registers.get_u8(target);
-        gameboy.write_byte(addr, value);
+        gameboy.write_cycle(addr, value);
         const TICKS: u8 = 8;
         TICKS
     }
@@ -136,7 +136,7 @@ pub fn ld_mem_at_hl_r(target: RegisterTarget) -> impl Fn(&mut Gameboy) -> u8 {
 pub fn ld_r_mem_at_hl(target: RegisterTarget) -> impl Fn(&mut Gameboy) -> u8 {
     move |gameboy: &mut Gameboy| {
         let addr = gameboy.cpu.registers.get_u16(Register16bTarget::HL);
-        let value = gameboy.read_byte(addr);
+        let value = gameboy.read_cycle(addr);
         gameboy.cpu.registers.set_u8(target, value);
         const TICKS: u8 = 8;
         TICKS
@@ -146,7 +146,7 @@ pub fn ld_r_mem_at_hl(target: RegisterTarget) -> impl Fn(&mut Gameboy) -> u8 {
 pub fn ld_mem_at_hl_a_inc(gameboy: &mut Gameboy) -> u8 {
     let addr = gameboy.cpu.registers.get_u16(Register16bTarget::HL);
     let value = gameboy.cpu.registers.get_u8(RegisterTarget::A);
-    gameboy.write_byte(addr, value);
+    gameboy.write_cycle(addr, value);
     gameboy
         .cpu
         .registers
@@ -158,7 +158,7 @@ pub fn ld_mem_at_hl_a_inc(gameboy: &mut Gameboy) -> u8 {
 pub fn ld_mem_at_hl_a_dec(gameboy: &mut Gameboy) -> u8 {
     let addr = gameboy.cpu.registers.get_u16(Register16bTarget::HL);
     let value = gameboy.cpu.registers.get_u8(RegisterTarget::A);
-    gameboy.write_byte(addr, value);
+    gameboy.write_cycle(addr, value);
     gameboy
         .cpu
         .registers
@@ -169,7 +169,7 @@ pub fn ld_mem_at_hl_a_dec(gameboy: &mut Gameboy) -> u8 {
 
 pub fn ld_a_mem_at_hl_inc(gameboy: &mut Gameboy) -> u8 {
     let addr = gameboy.cpu.registers.get_u16(Register16bTarget::HL);
-    let value = gameboy.read_byte(addr);
+    let value = gameboy.read_cycle(addr);
     gameboy.cpu.registers.set_u8(RegisterTarget::A, value);
     gameboy
         .cpu
@@ -181,7 +181,7 @@ pub fn ld_a_mem_at_hl_inc(gameboy: &mut Gameboy) -> u8 {
 
 pub fn ld_a_mem_at_hl_dec(gameboy: &mut Gameboy) -> u8 {
     let hl = gameboy.cpu.registers.get_u16(Register16bTarget::HL);
-    let value = gameboy.read_byte(hl);
+    let value = gameboy.read_cycle(hl);
     gameboy.cpu.registers.set_u8(RegisterTarget::A, value);
     gameboy
         .cpu
@@ -195,14 +195,14 @@ const LD_HIGH_OFFSET: u16 = 0xFF00;
 pub fn ld_mem_at_c_a(gameboy: &mut Gameboy) -> u8 {
     let addr = LD_HIGH_OFFSET + gameboy.cpu.registers.get_u8(RegisterTarget::C) as u16;
     let value = gameboy.cpu.registers.get_u8(RegisterTarget::A);
-    gameboy.write_byte(addr, value);
+    gameboy.write_cycle(addr, value);
     const TICKS: u8 = 8;
     TICKS
 }
 
 pub fn ld_a_mem_at_c(gameboy: &mut Gameboy) -> u8 {
     let addr = LD_HIGH_OFFSET + gameboy.cpu.registers.get_u8(RegisterTarget::C) as u16;
-    let value = gameboy.read_byte(addr);
+    let value = gameboy.read_cycle(addr);
     gameboy.cpu.registers.set_u8(RegisterTarget::A, value);
     const TICKS: u8 = 8;
     TICKS
@@ -12,6 +12,17 @@ pub fn halt(gameboy: &mut Gameboy) -> u8 {
     TICKS
 }
 
+/// One of the unused opcodes (0xD3, 0xDB, ...). The program counter is left
+/// pointing at the offending byte, what happens next depends on the
+/// configured `IllegalOpcodePolicy`.
+pub fn illegal(opcode: u8) -> impl Fn(&mut Gameboy) -> u8 {
+    move |gameboy: &mut Gameboy| {
+        gameboy.illegal_opcode(opcode);
+        const TICKS: u8 = 4;
+        TICKS
+    }
+}
+
 pub fn daa(gameboy: &mut Gameboy) -> u8 {
     let mut value = gameboy.cpu.registers.a;
     let flags = gameboy.cpu.registers.f;
@@ -75,4 +86,13 @@ mod tests {
         assert!(gameboy.cpu.registers.f.zero);
         assert!(gameboy.cpu.registers.f.carry);
     }
+
+    #[test]
+    fn test_illegal_locks_cpu() {
+        let mut gameboy = Gameboy::default();
+        gameboy.cpu.registers.pc = 0xC000;
+        illegal(0xD3)(&mut gameboy);
+        assert!(gameboy.cpu.locked);
+        assert_eq!(gameboy.cpu.registers.pc, 0xC000);
+    }
 }
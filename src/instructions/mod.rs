@@ -5,6 +5,7 @@ mod bit;
 mod call;
 mod cp;
 mod dec;
+mod dispatch;
 mod inc;
 mod int;
 mod jmp;
@@ -26,6 +27,8 @@ use std::sync::OnceLock;
 use super::cpu::*;
 use super::gameboy;
 
+pub use dispatch::{execute, execute_prefixed};
+
 /// Represents an instruction that can be executed by the Gameboy.
 /// The instruction is a function that takes a mutable reference to a Gameboy and returns
 /// the number of t-states (system clock ticks) that the instruction took to execute.
@@ -346,6 +349,10 @@ pub fn _from_byte(byte: u8) -> Box<Instruction> {
         0xEE => Box::new(pai(xor::xor_d8)),
         0xFE => Box::new(pai(cp::cp_d8)),
 
+        0xD3 | 0xDB | 0xDD | 0xE3 | 0xE4 | 0xEB | 0xEC | 0xED | 0xF4 | 0xFC | 0xFD => {
+            Box::new(misc::illegal(byte))
+        }
+
         other => {
             panic!("Unsupported instruction {:02X}", other)
         }
@@ -630,3 +637,58 @@ pub fn _from_prefixed_byte(byte: u8) -> Box<Instruction> {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gameboy::Gameboy;
+    use crate::opcode_info;
+
+    /// Unprefixed opcodes that stop or hang the CPU, or are the prefix itself.
+    const NOT_EXECUTABLE: [u8; 14] = [
+        0x10, 0x76, 0xCB, 0xD3, 0xDB, 0xDD, 0xE3, 0xE4, 0xEB, 0xEC, 0xED, 0xF4, 0xFC, 0xFD,
+    ];
+
+    fn gameboy_for_timing<'a>(flags: u8) -> Gameboy<'a> {
+        let mut gameboy = Gameboy::default();
+        gameboy.cpu.registers.pc = 0xC000;
+        gameboy.cpu.registers.sp = 0xD000;
+        gameboy.cpu.registers.h = 0xC1;
+        gameboy.cpu.registers.f = FlagsRegister::from(flags);
+        gameboy
+    }
+
+    #[test]
+    fn test_ticks_match_opcode_table() {
+        for flags in [0x00, 0xF0] {
+            for byte in 0..=0xFF {
+                if NOT_EXECUTABLE.contains(&byte) {
+                    continue;
+                }
+                let mut gameboy = gameboy_for_timing(flags);
+                let ticks = from_byte(byte)(&mut gameboy);
+                let expected = opcode_info::unprefixed(byte);
+                assert!(
+                    expected.cycles.contains(&ticks),
+                    "{:02X} took {} t-states, expected {:?}",
+                    byte,
+                    ticks,
+                    expected.cycles
+                );
+            }
+            // every prefixed opcode is valid
+            for byte in 0..=0xFF {
+                let mut gameboy = gameboy_for_timing(flags);
+                let ticks = from_prefixed_byte(byte)(&mut gameboy);
+                let expected = opcode_info::cbprefixed(byte);
+                assert!(
+                    expected.cycles.contains(&ticks),
+                    "CB {:02X} took {} t-states, expected {:?}",
+                    byte,
+                    ticks,
+                    expected.cycles
+                );
+            }
+        }
+    }
+}
@@ -22,7 +22,7 @@ pub fn or(target: RegisterTarget) -> impl Fn(&mut Gameboy) -> u8 {
 pub fn or_mem_at_r16(reg: Register16bTarget) -> impl Fn(&mut Gameboy) -> u8 {
     move |gameboy: &mut Gameboy| {
         let addr = gameboy.cpu.registers.get_u16(reg);
-        let value = gameboy.read_byte(addr);
+        let value = gameboy.read_cycle(addr);
         let a = gameboy.cpu.registers.get_u8(RegisterTarget::A);
 
         let result = a | value;
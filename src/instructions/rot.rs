@@ -75,14 +75,14 @@ pub fn rlc_r(target: RegisterTarget) -> impl Fn(&mut Gameboy) -> u8 {
 
 pub fn rlc_mem_at_hl(gameboy: &mut Gameboy) -> u8 {
     let addr = gameboy.cpu.registers.get_u16(Register16bTarget::HL);
-    let value = gameboy.read_byte(addr);
+    let value = gameboy.read_cycle(addr);
     let new_carry = value & 0x80 != 0;
     let new_value = value << 1 | value >> 7;
     gameboy.cpu.registers.f.carry = new_carry;
     gameboy.cpu.registers.f.zero = new_value == 0;
     gameboy.cpu.registers.f.subtract = false;
     gameboy.cpu.registers.f.half_carry = false;
-    gameboy.write_byte(addr, new_value);
+    gameboy.write_cycle(addr, new_value);
     const TICKS: u8 = 16;
     TICKS
 }
@@ -105,7 +105,7 @@ pub fn rl_r(target: RegisterTarget) -> impl Fn(&mut Gameboy) -> u8 {
 
 pub fn rl_mem_at_hl(gameboy: &mut Gameboy) -> u8 {
     let addr = gameboy.cpu.registers.get_u16(Register16bTarget::HL);
-    let value = gameboy.read_byte(addr);
+    let value = gameboy.read_cycle(addr);
     let carry = gameboy.cpu.registers.f.carry;
     let new_carry = value & 0x80 != 0;
     let new_value = (value << 1) | (carry as u8);
@@ -113,7 +113,7 @@ pub fn rl_mem_at_hl(gameboy: &mut Gameboy) -> u8 {
     gameboy.cpu.registers.f.zero = new_value == 0;
     gameboy.cpu.registers.f.subtract = false;
     gameboy.cpu.registers.f.half_carry = false;
-    gameboy.write_byte(addr, new_value);
+    gameboy.write_cycle(addr, new_value);
     const TICKS: u8 = 16;
     TICKS
 }
@@ -136,7 +136,7 @@ pub fn rr_r(target: RegisterTarget) -> impl Fn(&mut Gameboy) -> u8 {
 
 pub fn rr_mem_at_hl(gameboy: &mut Gameboy) -> u8 {
     let addr = gameboy.cpu.registers.get_u16(Register16bTarget::HL);
-    let value = gameboy.read_byte(addr);
+    let value = gameboy.read_cycle(addr);
     let carry = gameboy.cpu.registers.f.carry;
     let new_carry = value & 0x01 != 0;
     let new_value = (value >> 1) | ((carry as u8) << 7);
@@ -144,7 +144,7 @@ pub fn rr_mem_at_hl(gameboy: &mut Gameboy) -> u8 {
     gameboy.cpu.registers.f.zero = new_value == 0;
     gameboy.cpu.registers.f.subtract = false;
     gameboy.cpu.registers.f.half_carry = false;
-    gameboy.write_byte(addr, new_value);
+    gameboy.write_cycle(addr, new_value);
     const TICKS: u8 = 16;
     TICKS
 }
@@ -166,14 +166,14 @@ pub fn rrc_r(target: RegisterTarget) -> impl Fn(&mut Gameboy) -> u8 {
 
 pub fn rrc_mem_at_hl(gameboy: &mut Gameboy) -> u8 {
     let addr = gameboy.cpu.registers.get_u16(Register16bTarget::HL);
-    let value = gameboy.read_byte(addr);
+    let value = gameboy.read_cycle(addr);
     let new_carry = value & 0x01 != 0;
     let new_value = (value >> 1) | value << 7;
     gameboy.cpu.registers.f.carry = new_carry;
     gameboy.cpu.registers.f.zero = new_value == 0;
     gameboy.cpu.registers.f.subtract = false;
     gameboy.cpu.registers.f.half_carry = false;
-    gameboy.write_byte(addr, new_value);
+    gameboy.write_cycle(addr, new_value);
     const TICKS: u8 = 16;
     TICKS
 }
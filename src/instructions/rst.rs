@@ -1,3 +1,4 @@
+use crate::call_stack::FrameKind;
 use crate::cpu::Register16bTarget;
 use crate::gameboy::Gameboy;
 
@@ -8,12 +9,10 @@ pub fn rst(offset: u8) -> impl Fn(&mut Gameboy) -> u8 {
             .registers
             .get_u16(Register16bTarget::PC)
             .wrapping_add(1);
-        let [high, low] = pc.to_be_bytes();
-        gameboy.cpu.registers.sp = gameboy.cpu.registers.sp.wrapping_sub(1);
-        gameboy.write_byte(gameboy.cpu.registers.sp, high);
-        gameboy.cpu.registers.sp = gameboy.cpu.registers.sp.wrapping_sub(1);
-        gameboy.write_byte(gameboy.cpu.registers.sp, low);
+        gameboy.internal_cycle();
+        gameboy.stack_push(pc);
         gameboy.cpu.registers.pc = u16::from(offset);
+        gameboy.enter_routine(FrameKind::Rst, pc.wrapping_sub(1), pc);
         const TICKS: u8 = 16;
         TICKS
     }
@@ -19,10 +19,10 @@ pub fn sla(target: RegisterTarget) -> impl Fn(&mut Gameboy) -> u8 {
 
 pub fn sla_mem_at_hl(gameboy: &mut Gameboy) -> u8 {
     let addr = gameboy.cpu.registers.get_u16(Register16bTarget::HL);
-    let value = gameboy.read_byte(addr);
+    let value = gameboy.read_cycle(addr);
     let result = value << 1;
 
-    gameboy.write_byte(addr, result);
+    gameboy.write_cycle(addr, result);
 
     gameboy.cpu.registers.f.zero = result == 0;
     gameboy.cpu.registers.f.subtract = false;
@@ -50,10 +50,10 @@ pub fn sra(target: RegisterTarget) -> impl Fn(&mut Gameboy) -> u8 {
 
 pub fn sra_mem_at_hl(gameboy: &mut Gameboy) -> u8 {
     let addr = gameboy.cpu.registers.get_u16(Register16bTarget::HL);
-    let value = gameboy.read_byte(addr);
+    let value = gameboy.read_cycle(addr);
     let result = (value >> 1) | (value & 0b1000_0000);
 
-    gameboy.write_byte(addr, result);
+    gameboy.write_cycle(addr, result);
 
     gameboy.cpu.registers.f.zero = result == 0;
     gameboy.cpu.registers.f.subtract = false;
@@ -81,10 +81,10 @@ pub fn srl(target: RegisterTarget) -> impl Fn(&mut Gameboy) -> u8 {
 
 pub fn srl_mem_at_hl(gameboy: &mut Gameboy) -> u8 {
     let addr = gameboy.cpu.registers.get_u16(Register16bTarget::HL);
-    let value = gameboy.read_byte(addr);
+    let value = gameboy.read_cycle(addr);
     let result = value >> 1;
 
-    gameboy.write_byte(addr, result);
+    gameboy.write_cycle(addr, result);
 
     gameboy.cpu.registers.f.zero = result == 0;
     gameboy.cpu.registers.f.subtract = false;
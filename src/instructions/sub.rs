@@ -12,7 +12,7 @@ pub fn sub_r_r_a(target: RegisterTarget) -> impl Fn(&mut Gameboy) -> u8 {
         gameboy.cpu.registers.f.zero = new_value == 0;
         gameboy.cpu.registers.f.subtract = true;
         gameboy.cpu.registers.f.half_carry = (current_value & 0xF) < (target_value & 0xF);
-        const TICKS: u8 = 8;
+        const TICKS: u8 = 4;
         TICKS
     }
 }
@@ -33,7 +33,7 @@ pub fn sub_d8(gameboy: &mut Gameboy) -> u8 {
 
 pub fn sub_mem_at_hl(gameboy: &mut Gameboy) -> u8 {
     let hl = gameboy.cpu.registers.get_u16(Register16bTarget::HL);
-    let target_value = gameboy.read_byte(hl);
+    let target_value = gameboy.read_cycle(hl);
     let current_value = gameboy.cpu.registers.a;
     let (new_value, did_overflow) = current_value.overflowing_sub(target_value);
     gameboy.cpu.registers.a = new_value;
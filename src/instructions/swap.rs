@@ -13,23 +13,23 @@ pub fn swap(target: RegisterTarget) -> impl Fn(&mut Gameboy) -> u8 {
         gameboy.cpu.registers.f.subtract = false;
         gameboy.cpu.registers.f.half_carry = false;
         gameboy.cpu.registers.f.carry = false;
-        const CYCLES: u8 = 2;
+        const CYCLES: u8 = 8;
         CYCLES
     }
 }
 
 pub fn swap_mem_at_hl(gameboy: &mut Gameboy) -> u8 {
     let addr = gameboy.cpu.registers.get_u16(Register16bTarget::HL);
-    let value = gameboy.read_byte(addr);
+    let value = gameboy.read_cycle(addr);
     let result = (value << 4) | (value >> 4);
 
-    gameboy.write_byte(addr, result);
+    gameboy.write_cycle(addr, result);
 
     gameboy.cpu.registers.f.zero = result == 0;
     gameboy.cpu.registers.f.subtract = false;
     gameboy.cpu.registers.f.half_carry = false;
     gameboy.cpu.registers.f.carry = false;
-    const CYCLES: u8 = 4;
+    const CYCLES: u8 = 16;
     CYCLES
 }
 
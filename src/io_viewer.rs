@@ -0,0 +1,393 @@
+//! The hardware registers at 0xFF00-0xFFFF with their bits and fields decoded,
+//! as text for the debugger and as a window.
+
+use sdl2::pixels::Color;
+
+use crate::font::{draw_text, CHAR_WIDTH, LINE_HEIGHT};
+use crate::gameboy::Gameboy;
+use crate::memory::special_addresses::*;
+use crate::ppu::pack;
+
+const MARGIN: usize = 2;
+/// Lines longer than this are cut off in the window.
+const COLUMNS: usize = 96;
+
+pub const WIDTH: usize = 2 * MARGIN + COLUMNS * CHAR_WIDTH;
+pub const HEIGHT: usize = 2 * MARGIN + REGISTERS.len() * LINE_HEIGHT;
+
+const BACKGROUND: Color = Color::RGB(0x20, 0x20, 0x20);
+const TEXT: Color = Color::RGB(0xC0, 0xC0, 0xC0);
+
+#[derive(Debug)]
+pub struct Register {
+    pub name: &'static str,
+    pub address: usize,
+    pub decode: fn(u8) -> String,
+}
+
+const fn register(name: &'static str, address: usize, decode: fn(u8) -> String) -> Register {
+    Register {
+        name,
+        address,
+        decode,
+    }
+}
+
+pub const REGISTERS: [Register; 42] = [
+    register("P1", P1, joypad),
+    register("SB", SB, number),
+    register("SC", SC, serial_control),
+    register("DIV", DIV, number),
+    register("TIMA", TIMA, number),
+    register("TMA", TMA, number),
+    register("TAC", TAC, timer_control),
+    register("IF", IF, interrupts),
+    register("IE", IE, interrupts),
+    register("LCDC", LCDC, lcd_control),
+    register("STAT", STAT, lcd_status),
+    register("SCY", SCY, number),
+    register("SCX", SCX, number),
+    register("LY", LY, number),
+    register("LYC", LYC, number),
+    register("DMA", DMA, dma),
+    register("BGP", BGP, palette),
+    register("OBP0", OBP0, palette),
+    register("OBP1", OBP1, palette),
+    register("WY", WY, number),
+    register("WX", WX, number),
+    register("NR10", NR10, sweep),
+    register("NR11", NR11, duty_and_length),
+    register("NR12", NR12, envelope),
+    register("NR13", NR13, period_low),
+    register("NR14", NR14, period_high_and_control),
+    register("NR21", NR21, duty_and_length),
+    register("NR22", NR22, envelope),
+    register("NR23", NR23, period_low),
+    register("NR24", NR24, period_high_and_control),
+    register("NR30", NR30, wave_dac),
+    register("NR31", NR31, number),
+    register("NR32", NR32, wave_level),
+    register("NR33", NR33, period_low),
+    register("NR34", NR34, period_high_and_control),
+    register("NR41", NR41, length),
+    register("NR42", NR42, envelope),
+    register("NR43", NR43, noise),
+    register("NR44", NR44, noise_control),
+    register("NR50", NR50, master_volume),
+    register("NR51", NR51, panning),
+    register("NR52", NR52, audio_control),
+];
+
+fn on_off(value: u8, bit: u8) -> &'static str {
+    if value & 1 << bit != 0 {
+        "on"
+    } else {
+        "off"
+    }
+}
+
+/// The names of the set bits, lowest first, or "none".
+fn bits(value: u8, names: &[&str]) -> String {
+    let set: Vec<&str> = names
+        .iter()
+        .enumerate()
+        .filter(|(bit, _)| value & 1 << bit != 0)
+        .map(|(_, name)| *name)
+        .collect();
+    if set.is_empty() {
+        "none".to_string()
+    } else {
+        set.join(" ")
+    }
+}
+
+fn number(value: u8) -> String {
+    format!("{}", value)
+}
+
+fn joypad(value: u8) -> String {
+    // everything here is active low
+    let mut names = Vec::new();
+    if value & 0x10 == 0 {
+        names.push(["right", "left", "up", "down"]);
+    }
+    if value & 0x20 == 0 {
+        names.push(["A", "B", "select", "start"]);
+    }
+    let selected = match value & 0x30 {
+        0x00 => "d-pad and buttons",
+        0x10 => "buttons",
+        0x20 => "d-pad",
+        _ => "nothing",
+    };
+    let pressed: Vec<String> = (0..4)
+        .filter(|bit| value & 1 << bit == 0)
+        .map(|bit| {
+            let names: Vec<&str> = names.iter().map(|group| group[bit]).collect();
+            names.join("/")
+        })
+        .filter(|name| !name.is_empty())
+        .collect();
+    let pressed = if pressed.is_empty() {
+        "none".to_string()
+    } else {
+        pressed.join(" ")
+    };
+    format!("selected {}, pressed {}", selected, pressed)
+}
+
+fn serial_control(value: u8) -> String {
+    format!(
+        "transfer {}, {} clock",
+        if value & 0x80 != 0 {
+            "requested"
+        } else {
+            "idle"
+        },
+        if value & 0x01 != 0 {
+            "internal"
+        } else {
+            "external"
+        }
+    )
+}
+
+fn timer_control(value: u8) -> String {
+    let hertz = [4096, 262144, 65536, 16384][(value & 0x03) as usize];
+    format!("timer {}, {} Hz", on_off(value, 2), hertz)
+}
+
+fn interrupts(value: u8) -> String {
+    bits(value, &["vblank", "stat", "timer", "serial", "joypad"])
+}
+
+fn lcd_control(value: u8) -> String {
+    let map = |bit: u8| {
+        if value & 1 << bit != 0 {
+            "9C00"
+        } else {
+            "9800"
+        }
+    };
+    format!(
+        "LCD {}, window map {}, window {}, tiles {}, BG map {}, OBJ {}, OBJ {}, BG {}",
+        on_off(value, 7),
+        map(6),
+        on_off(value, 5),
+        if value & 0x10 != 0 { "8000" } else { "8800" },
+        map(3),
+        if value & 0x04 != 0 { "8x16" } else { "8x8" },
+        on_off(value, 1),
+        on_off(value, 0)
+    )
+}
+
+fn lcd_status(value: u8) -> String {
+    let mode = ["HBlank", "VBlank", "OAM scan", "drawing"][(value & 0x03) as usize];
+    format!(
+        "mode {} ({}), LY{}LYC, interrupts on {}",
+        value & 0x03,
+        mode,
+        if value & 0x04 != 0 { "=" } else { "!=" },
+        bits(value >> 3, &["HBlank", "VBlank", "OAM", "LYC"])
+    )
+}
+
+fn dma(value: u8) -> String {
+    format!("from {:02X}00", value)
+}
+
+fn palette(value: u8) -> String {
+    let shades: Vec<&str> = (0..4)
+        .map(|color| ["white", "light", "dark", "black"][(value >> (color * 2) & 0x03) as usize])
+        .collect();
+    format!("colors 0-3: {}", shades.join(" "))
+}
+
+fn sweep(value: u8) -> String {
+    format!(
+        "sweep pace {}, {}, step {}",
+        value >> 4 & 0x07,
+        if value & 0x08 != 0 { "down" } else { "up" },
+        value & 0x07
+    )
+}
+
+fn length(value: u8) -> String {
+    format!("length timer {}", value & 0x3F)
+}
+
+fn duty_and_length(value: u8) -> String {
+    let duty = ["12.5", "25", "50", "75"][(value >> 6) as usize];
+    format!("duty {}%, {}", duty, length(value))
+}
+
+fn envelope(value: u8) -> String {
+    if value & 0xF8 == 0 {
+        return "DAC off".to_string();
+    }
+    format!(
+        "volume {}, {}, envelope pace {}",
+        value >> 4,
+        if value & 0x08 != 0 { "up" } else { "down" },
+        value & 0x07
+    )
+}
+
+fn period_low(value: u8) -> String {
+    format!("period bits 0-7: {:02X}", value)
+}
+
+fn period_high_and_control(value: u8) -> String {
+    format!(
+        "trigger {}, length {}, period bits 8-10: {}",
+        if value & 0x80 != 0 { "set" } else { "clear" },
+        if value & 0x40 != 0 {
+            "enabled"
+        } else {
+            "disabled"
+        },
+        value & 0x07
+    )
+}
+
+fn noise_control(value: u8) -> String {
+    format!(
+        "trigger {}, length {}",
+        if value & 0x80 != 0 { "set" } else { "clear" },
+        if value & 0x40 != 0 {
+            "enabled"
+        } else {
+            "disabled"
+        }
+    )
+}
+
+fn wave_dac(value: u8) -> String {
+    format!("DAC {}", on_off(value, 7))
+}
+
+fn wave_level(value: u8) -> String {
+    ["mute", "100%", "50%", "25%"][(value >> 5 & 0x03) as usize].to_string()
+}
+
+fn noise(value: u8) -> String {
+    format!(
+        "clock shift {}, {}-bit LFSR, divider {}",
+        value >> 4,
+        if value & 0x08 != 0 { 7 } else { 15 },
+        value & 0x07
+    )
+}
+
+fn master_volume(value: u8) -> String {
+    format!(
+        "left volume {}, right volume {}, VIN to {}",
+        value >> 4 & 0x07,
+        value & 0x07,
+        bits(value >> 3 & 0x11, &["right", "", "", "", "left"])
+    )
+}
+
+fn panning(value: u8) -> String {
+    let channels = ["1", "2", "3", "4"];
+    format!(
+        "left {}, right {}",
+        bits(value >> 4, &channels),
+        bits(value, &channels)
+    )
+}
+
+fn audio_control(value: u8) -> String {
+    format!(
+        "audio {}, channels on {}",
+        on_off(value, 7),
+        bits(value & 0x0F, &["1", "2", "3", "4"])
+    )
+}
+
+/// Looks a register up by name, ignoring case.
+pub fn find(name: &str) -> Option<&'static Register> {
+    REGISTERS
+        .iter()
+        .find(|register| register.name.eq_ignore_ascii_case(name))
+}
+
+/// One register as the CPU currently reads it, e.g.
+/// `TAC  FF07 05  timer on, 262144 Hz`.
+pub fn line(gameboy: &Gameboy, register: &Register) -> String {
+    let value = gameboy.read_byte(register.address as u16);
+    format!(
+        "{:<4} {:04X} {:02X}  {}",
+        register.name,
+        register.address,
+        value,
+        (register.decode)(value)
+    )
+}
+
+/// Every register, one per line, as shown by the debugger's `io` command.
+pub fn listing(gameboy: &Gameboy) -> String {
+    REGISTERS
+        .iter()
+        .map(|register| line(gameboy, register) + "\n")
+        .collect()
+}
+
+/// Renders the listing into a `WIDTH`×`HEIGHT` image.
+pub fn render(gameboy: &Gameboy) -> Vec<u32> {
+    let mut pixels = vec![pack(BACKGROUND); WIDTH * HEIGHT];
+    for (row, register) in REGISTERS.iter().enumerate() {
+        let text: String = line(gameboy, register).chars().take(COLUMNS).collect();
+        let y = MARGIN + row * LINE_HEIGHT;
+        draw_text(&mut pixels, WIDTH, (MARGIN, y), &text, TEXT);
+    }
+    pixels
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_decode() {
+        assert_eq!(
+            lcd_control(0x91),
+            "LCD on, window map 9800, window off, tiles 8000, BG map 9800, OBJ 8x8, OBJ off, BG on"
+        );
+        assert_eq!(
+            lcd_status(0x45),
+            "mode 1 (VBlank), LY=LYC, interrupts on LYC"
+        );
+        assert_eq!(timer_control(0x05), "timer on, 262144 Hz");
+        assert_eq!(interrupts(0x05), "vblank timer");
+        assert_eq!(interrupts(0xE0), "none");
+        assert_eq!(palette(0xE4), "colors 0-3: white light dark black");
+        assert_eq!(joypad(0xED), "selected d-pad, pressed left");
+        assert_eq!(joypad(0xCF), "selected d-pad and buttons, pressed none");
+        assert_eq!(
+            joypad(0xC7),
+            "selected d-pad and buttons, pressed down/start"
+        );
+        assert_eq!(envelope(0x07), "DAC off");
+        assert_eq!(envelope(0xF3), "volume 15, down, envelope pace 3");
+        assert_eq!(duty_and_length(0xBF), "duty 50%, length timer 63");
+        assert_eq!(panning(0xF3), "left 1 2 3 4, right 1 2");
+        assert_eq!(
+            master_volume(0xF7),
+            "left volume 7, right volume 7, VIN to left"
+        );
+        assert_eq!(audio_control(0xF1), "audio on, channels on 1");
+    }
+
+    #[test]
+    fn test_listing() {
+        let mut gameboy = Gameboy::default();
+        gameboy.write_byte(TAC as u16, 0x05);
+        let listing = listing(&gameboy);
+
+        assert_eq!(listing.lines().count(), REGISTERS.len());
+        assert!(listing.contains("TAC  FF07 05  timer on, 262144 Hz\n"));
+        assert_eq!(find("lcdc").unwrap().address, LCDC);
+    }
+}
@@ -1,11 +1,32 @@
+pub mod breakpoints;
+pub mod call_stack;
+pub mod cdl;
 pub mod cpu;
+pub mod debugger;
+pub mod disasm;
 pub mod emulator;
+pub mod font;
 pub mod gameboy;
+pub mod gdb;
+pub mod hooks;
 pub mod instructions;
+pub mod io_viewer;
 pub mod joypad;
 pub mod memory;
+pub mod memory_viewer;
+pub mod oam_viewer;
 pub mod opcode_info;
 pub mod ppu;
+pub mod profiler;
+pub mod rewind;
+pub mod save_state;
+pub mod scheduler;
+pub mod screen;
+pub mod speed;
+pub mod symbols;
+pub mod trace;
+pub mod views;
+pub mod vram_viewer;
 
 #[macro_use]
 extern crate lazy_static;
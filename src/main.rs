@@ -1,6 +1,9 @@
-use rust_game_boy_emulator::emulator;
+use rust_game_boy_emulator::cdl::CodeDataLog;
+use rust_game_boy_emulator::symbols::Symbols;
+use rust_game_boy_emulator::{disasm, emulator, speed};
 use std::fs::OpenOptions;
 use std::io::prelude::*;
+use std::path::Path;
 
 fn main() {
     env_logger::init();
@@ -9,12 +12,92 @@ fn main() {
 
     // load cartdrige file from command line argument
     let args: Vec<String> = std::env::args().collect();
-    if args.len() < 2 {
-        eprintln!("Usage: {} <cartdrige file>", args[0]);
-        std::process::exit(1);
+    if args.get(1).map(String::as_str) == Some("disasm") {
+        disassemble(&args);
+        return;
+    }
+    let mut options = emulator::Options::default();
+    let mut path = None;
+    let mut log_code_and_data = false;
+    let mut args_iter = args[1..].iter();
+    while let Some(arg) = args_iter.next() {
+        match arg.as_str() {
+            "--debug" => options.debug = true,
+            "--gdb" => {
+                let port = args_iter.next().and_then(|port| port.parse().ok());
+                if port.is_none() {
+                    eprintln!("--gdb needs a port number");
+                    std::process::exit(1);
+                }
+                options.gdb_port = port;
+            }
+            "--trace" => {
+                let Some(file) = args_iter.next() else {
+                    eprintln!("--trace needs a file name");
+                    std::process::exit(1);
+                };
+                options.trace = Some(file.into());
+            }
+            "--profile" | "--profile-folded" => {
+                let Some(file) = args_iter.next() else {
+                    eprintln!("{} needs a file name", arg);
+                    std::process::exit(1);
+                };
+                if arg == "--profile" {
+                    options.profile = Some(file.into());
+                } else {
+                    options.profile_folded = Some(file.into());
+                }
+            }
+            "--rewind-budget" | "--rewind-interval" | "--rewind-speed" => {
+                let value = args_iter.next();
+                let rewind = &mut options.rewind;
+                let parsed = match arg.as_str() {
+                    "--rewind-budget" => value
+                        .and_then(|mib| mib.parse::<usize>().ok())
+                        .map(|mib| rewind.budget = mib << 20),
+                    "--rewind-interval" => value
+                        .and_then(|frames| frames.parse().ok())
+                        .filter(|&frames| frames > 0)
+                        .map(|frames| rewind.interval = frames),
+                    _ => value
+                        .and_then(|speed| speed.parse().ok())
+                        .filter(|&speed: &f64| speed > 0.0)
+                        .map(|speed| rewind.speed = speed),
+                };
+                if parsed.is_none() {
+                    eprintln!("{} needs a number", arg);
+                    std::process::exit(1);
+                }
+            }
+            "--speed" => {
+                let multiplier = args_iter
+                    .next()
+                    .and_then(|multiplier| multiplier.parse().ok())
+                    .filter(|multiplier| (speed::MIN..=speed::MAX).contains(multiplier));
+                if multiplier.is_none() {
+                    eprintln!(
+                        "--speed needs a number from {} to {}",
+                        speed::MIN,
+                        speed::MAX
+                    );
+                    std::process::exit(1);
+                }
+                options.speed = multiplier;
+            }
+            "--fast-forward" => options.fast_forward = true,
+            "--cdl" => log_code_and_data = true,
+            "--skip-boot" => options.skip_boot_rom = true,
+            "--force-ly" => options.force_ly = true,
+            other => path = Some(other),
+        }
     }
+    let Some(path) = path else {
+        eprintln!("Usage: {} [--debug] [--gdb PORT] [--trace FILE] [--skip-boot] [--force-ly] [--profile FILE] [--profile-folded FILE] [--cdl] [--rewind-budget MIB] [--rewind-interval FRAMES] [--rewind-speed X] [--speed X] [--fast-forward] <cartdrige file>", args[0]);
+        std::process::exit(1);
+    };
 
-    let mut file = OpenOptions::new().read(true).open(&args[1]).unwrap();
+    let mut file = OpenOptions::new().read(true).open(path).unwrap();
     match file.read_exact(&mut cartridge) {
         Ok(_) => (),
         Err(ref error) if error.kind() == std::io::ErrorKind::UnexpectedEof => (),
@@ -24,5 +107,46 @@ fn main() {
         }
     }
 
-    emulator::run(&cartridge);
+    options.symbols = Symbols::for_rom(path).unwrap_or_default();
+    if log_code_and_data {
+        options.cdl = Some(Path::new(path).with_extension("cdl"));
+    }
+    emulator::run(&cartridge, options);
+}
+
+/// `disasm <rom> [BANK[-BANK]]`: prints RGBDS source for the given ROM banks,
+/// all of them by default. Banks are in hex, like everywhere else.
+fn disassemble(args: &[String]) {
+    let Some(path) = args.get(2) else {
+        eprintln!(
+            "Usage: {} disasm <rom> [BANK[-BANK]] (banks in hex)",
+            args[0]
+        );
+        std::process::exit(1);
+    };
+    let rom = std::fs::read(path).unwrap_or_else(|error| {
+        eprintln!("Error reading file: {}", error);
+        std::process::exit(1);
+    });
+
+    let banks = match args.get(3) {
+        Some(range) => {
+            let (start, end) = range.split_once('-').unwrap_or((range, range));
+            let bank = |bank: &str| u8::from_str_radix(bank.trim_start_matches('$'), 16);
+            match (bank(start), bank(end)) {
+                (Ok(start), Ok(end)) => start as usize..=end as usize,
+                _ => {
+                    eprintln!("Invalid bank range: {}", range);
+                    std::process::exit(1);
+                }
+            }
+        }
+        None => 0..=rom.len().saturating_sub(1) / 0x4000,
+    };
+    let symbols = Symbols::for_rom(path).unwrap_or_default();
+    let cdl = CodeDataLog::for_rom(path);
+    print!(
+        "{}",
+        disasm::disassemble_rom(&rom, banks, &symbols, cdl.as_ref())
+    );
 }
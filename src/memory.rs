@@ -3,6 +3,9 @@ pub struct MemoryBus<'a> {
     pub boot_rom: &'static [u8],
     pub cartridge_rom: &'a [u8],
     pub boot_rom_enabled: bool,
+    /// Treats the whole address space as plain RAM with no ROM, I/O or boot
+    /// ROM behind it, for testing the CPU in isolation.
+    pub flat: bool,
 }
 
 impl Default for MemoryBus<'_> {
@@ -12,6 +15,22 @@ impl Default for MemoryBus<'_> {
             boot_rom_enabled: true,
             boot_rom: include_bytes!("dmg.bin"),
             cartridge_rom: &[],
+            flat: false,
+        }
+    }
+}
+
+impl MemoryBus<'_> {
+    /// Reads straight from the address space, bypassing the boot ROM and any
+    /// side effects. Meant for the video hardware and debugging tools.
+    pub fn read(&self, address: u16) -> u8 {
+        match address {
+            0x0000..=0x7FFF => self
+                .cartridge_rom
+                .get(address as usize)
+                .copied()
+                .unwrap_or(0xFF),
+            _ => self.memory[address as usize],
         }
     }
 }
@@ -0,0 +1,289 @@
+//! A hex view of the whole address space as the CPU sees it, with bytes that
+//! changed since the previous frame highlighted and in-place editing.
+
+use sdl2::keyboard::Keycode;
+use sdl2::pixels::Color;
+
+use crate::font::{draw_text, CHAR_WIDTH, LINE_HEIGHT};
+use crate::gameboy::Gameboy;
+use crate::ppu::pack;
+
+pub const ROWS: usize = 32;
+const BYTES_PER_ROW: usize = 16;
+const MARGIN: usize = 2;
+/// Characters before the first byte of a row, e.g. `ROM01 4000 `.
+const PREFIX_COLUMNS: usize = 11;
+/// The top row can't go further than this without running off the end.
+const LAST_TOP: u16 = (0x10000 - ROWS * BYTES_PER_ROW) as u16;
+
+pub const WIDTH: usize = 2 * MARGIN + (PREFIX_COLUMNS + 3 * BYTES_PER_ROW - 1) * CHAR_WIDTH;
+pub const HEIGHT: usize = 2 * MARGIN + ROWS * LINE_HEIGHT;
+
+const BACKGROUND: Color = Color::RGB(0x20, 0x20, 0x20);
+const TEXT: Color = Color::RGB(0xC0, 0xC0, 0xC0);
+const DIM: Color = Color::RGB(0x70, 0x70, 0x70);
+const CHANGED: Color = Color::RGB(0xFF, 0xD0, 0x00);
+const CURSOR: Color = Color::RGB(0x30, 0x50, 0xC0);
+
+/// Names the part of the memory map `address` is in, with the bank for ROM.
+pub fn region(gameboy: &Gameboy, address: u16) -> String {
+    match address {
+        _ if gameboy.bus.boot_rom_enabled && address < gameboy.bus.boot_rom.len() as u16 => {
+            "BOOT".to_string()
+        }
+        0x0000..=0x7FFF => format!("ROM{:02X}", gameboy.rom_bank(address)),
+        0x8000..=0x9FFF => "VRAM".to_string(),
+        0xA000..=0xBFFF => "SRAM".to_string(),
+        0xC000..=0xDFFF => "WRAM".to_string(),
+        0xE000..=0xFDFF => "ECHO".to_string(),
+        0xFE00..=0xFE9F => "OAM".to_string(),
+        0xFEA0..=0xFEFF => "----".to_string(),
+        0xFF00..=0xFF7F => "I/O".to_string(),
+        0xFF80..=0xFFFE => "HRAM".to_string(),
+        0xFFFF => "IE".to_string(),
+    }
+}
+
+fn hex_digit(key: Keycode) -> Option<u8> {
+    let digit = match key {
+        Keycode::Num0 | Keycode::Kp0 => 0,
+        Keycode::Num1 | Keycode::Kp1 => 1,
+        Keycode::Num2 | Keycode::Kp2 => 2,
+        Keycode::Num3 | Keycode::Kp3 => 3,
+        Keycode::Num4 | Keycode::Kp4 => 4,
+        Keycode::Num5 | Keycode::Kp5 => 5,
+        Keycode::Num6 | Keycode::Kp6 => 6,
+        Keycode::Num7 | Keycode::Kp7 => 7,
+        Keycode::Num8 | Keycode::Kp8 => 8,
+        Keycode::Num9 | Keycode::Kp9 => 9,
+        Keycode::A => 0xA,
+        Keycode::B => 0xB,
+        Keycode::C => 0xC,
+        Keycode::D => 0xD,
+        Keycode::E => 0xE,
+        Keycode::F => 0xF,
+        _ => return None,
+    };
+    Some(digit)
+}
+
+fn fill(pixels: &mut [u32], (x, y): (usize, usize), (width, height): (usize, usize), color: Color) {
+    for row in y..y + height {
+        pixels[row * WIDTH + x..row * WIDTH + x + width].fill(pack(color));
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct MemoryViewer {
+    /// The address of the first row shown.
+    top: u16,
+    cursor: u16,
+    /// The high nibble typed so far for the byte at the cursor.
+    pending: Option<u8>,
+    current: Vec<u8>,
+    previous: Vec<u8>,
+}
+
+impl MemoryViewer {
+    pub fn cursor(&self) -> u16 {
+        self.cursor
+    }
+
+    /// Takes a new snapshot of memory; call once per frame.
+    pub fn update(&mut self, gameboy: &Gameboy) {
+        self.previous = std::mem::take(&mut self.current);
+        self.current = (0..=0xFFFF)
+            .map(|address| gameboy.read_byte(address))
+            .collect();
+    }
+
+    /// Whether the byte differs between the last two snapshots.
+    pub fn changed(&self, address: u16) -> bool {
+        let address = address as usize;
+        match (self.previous.get(address), self.current.get(address)) {
+            (Some(before), Some(after)) => before != after,
+            _ => false,
+        }
+    }
+
+    /// Moves the cursor to `address`, scrolling it into view.
+    pub fn select(&mut self, address: u16) {
+        self.cursor = address;
+        self.pending = None;
+        let row = address & !(BYTES_PER_ROW as u16 - 1);
+        let rows_above = ((ROWS - 1) * BYTES_PER_ROW) as u16;
+        if row < self.top {
+            self.top = row;
+        } else if row - self.top > rows_above {
+            self.top = row - rows_above;
+        }
+    }
+
+    /// Scrolls the view by `rows`, leaving the cursor where it is.
+    pub fn scroll(&mut self, rows: i32) {
+        let top = self.top as i32 + rows * BYTES_PER_ROW as i32;
+        self.top = top.clamp(0, LAST_TOP as i32) as u16;
+    }
+
+    fn move_cursor(&mut self, delta: i32) {
+        self.select((self.cursor as i32 + delta).clamp(0, 0xFFFF) as u16);
+    }
+
+    /// Handles a key pressed in the window, returning whether it was used.
+    /// Two hex digits write a byte at the cursor through `write_byte`, as if
+    /// the game had written it.
+    pub fn key(&mut self, gameboy: &mut Gameboy, key: Keycode) -> bool {
+        let page = (ROWS * BYTES_PER_ROW) as i32;
+        match key {
+            Keycode::Up => self.move_cursor(-(BYTES_PER_ROW as i32)),
+            Keycode::Down => self.move_cursor(BYTES_PER_ROW as i32),
+            Keycode::Left => self.move_cursor(-1),
+            Keycode::Right => self.move_cursor(1),
+            Keycode::PageUp => self.move_cursor(-page),
+            Keycode::PageDown => self.move_cursor(page),
+            Keycode::Escape => self.pending = None,
+            _ => match (hex_digit(key), self.pending.take()) {
+                (None, _) => return false,
+                (Some(digit), None) => self.pending = Some(digit),
+                (Some(digit), Some(high)) => {
+                    gameboy.write_byte(self.cursor, high << 4 | digit);
+                    self.move_cursor(1);
+                }
+            },
+        }
+        true
+    }
+
+    /// Selects the byte under `x`, `y` in the rendered image, if any.
+    pub fn click(&mut self, (x, y): (usize, usize)) {
+        let (Some(x), Some(y)) = (x.checked_sub(MARGIN), y.checked_sub(MARGIN)) else {
+            return;
+        };
+        let (column, row) = (x / CHAR_WIDTH, y / LINE_HEIGHT);
+        let Some(byte) = column.checked_sub(PREFIX_COLUMNS).map(|column| column / 3) else {
+            return;
+        };
+        if byte < BYTES_PER_ROW && row < ROWS {
+            self.select(self.top + (row * BYTES_PER_ROW + byte) as u16);
+        }
+    }
+
+    /// Renders the rows in view from the latest snapshot into a
+    /// `WIDTH`×`HEIGHT` image.
+    pub fn render(&self, gameboy: &Gameboy) -> Vec<u32> {
+        let mut pixels = vec![pack(BACKGROUND); WIDTH * HEIGHT];
+        for row in 0..ROWS {
+            let start = self.top + (row * BYTES_PER_ROW) as u16;
+            let y = MARGIN + row * LINE_HEIGHT;
+            draw_text(
+                &mut pixels,
+                WIDTH,
+                (MARGIN, y),
+                &region(gameboy, start),
+                DIM,
+            );
+            let text = format!("{:04X}", start);
+            draw_text(&mut pixels, WIDTH, (MARGIN + 6 * CHAR_WIDTH, y), &text, DIM);
+
+            for column in 0..BYTES_PER_ROW {
+                let address = start + column as u16;
+                let x = MARGIN + (PREFIX_COLUMNS + 3 * column) * CHAR_WIDTH;
+                let value = self.current.get(address as usize).copied().unwrap_or(0);
+                let text = match self.pending {
+                    Some(high) if address == self.cursor => format!("{:X}-", high),
+                    _ => format!("{:02X}", value),
+                };
+                if address == self.cursor {
+                    fill(
+                        &mut pixels,
+                        (x - 1, y - 1),
+                        (2 * CHAR_WIDTH + 1, LINE_HEIGHT),
+                        CURSOR,
+                    );
+                }
+                let color = if self.changed(address) { CHANGED } else { TEXT };
+                draw_text(&mut pixels, WIDTH, (x, y), &text, color);
+            }
+        }
+        pixels
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gameboy;
+
+    fn gameboy_after_boot<'a>() -> Gameboy<'a> {
+        let mut gameboy = Gameboy::default();
+        gameboy::initialize(&mut gameboy);
+        gameboy::skip_boot_rom(&mut gameboy);
+        gameboy.bus.cartridge_rom = vec![0; 0x8000].leak();
+        gameboy
+    }
+
+    #[test]
+    fn test_region() {
+        let mut gameboy = gameboy_after_boot();
+        assert_eq!(region(&gameboy, 0x0000), "ROM00");
+        assert_eq!(region(&gameboy, 0x4000), "ROM01");
+        assert_eq!(region(&gameboy, 0x9800), "VRAM");
+        assert_eq!(region(&gameboy, 0xFE9F), "OAM");
+        assert_eq!(region(&gameboy, 0xFEA0), "----");
+        assert_eq!(region(&gameboy, 0xFF40), "I/O");
+        assert_eq!(region(&gameboy, 0xFF80), "HRAM");
+        gameboy.bus.boot_rom_enabled = true;
+        assert_eq!(region(&gameboy, 0x0000), "BOOT");
+    }
+
+    #[test]
+    fn test_changed_since_last_update() {
+        let mut gameboy = gameboy_after_boot();
+        let mut viewer = MemoryViewer::default();
+        viewer.update(&gameboy);
+        assert!(!viewer.changed(0xC000));
+
+        gameboy.write_byte(0xC000, 0x42);
+        viewer.update(&gameboy);
+        assert!(viewer.changed(0xC000));
+        assert!(!viewer.changed(0xC001));
+
+        viewer.update(&gameboy);
+        assert!(!viewer.changed(0xC000));
+    }
+
+    #[test]
+    fn test_edit_goes_through_write_byte() {
+        let mut gameboy = gameboy_after_boot();
+        for offset in 0..0xA0 {
+            gameboy.write_byte(0xC000 + offset, offset as u8);
+        }
+        let mut viewer = MemoryViewer::default();
+        viewer.select(0xFF46);
+
+        // writing $C0 to DMA copies the sprites over
+        assert!(viewer.key(&mut gameboy, Keycode::C));
+        assert!(viewer.key(&mut gameboy, Keycode::Num0));
+        assert_eq!(gameboy.read_byte(0xFE9F), 0x9F);
+        assert_eq!(viewer.cursor(), 0xFF47);
+        assert!(!viewer.key(&mut gameboy, Keycode::G));
+    }
+
+    #[test]
+    fn test_cursor_scrolls_into_view() {
+        let mut gameboy = gameboy_after_boot();
+        let mut viewer = MemoryViewer::default();
+        viewer.select(0xC005);
+        assert_eq!(viewer.top, 0xC000 - 31 * 16);
+
+        viewer.key(&mut gameboy, Keycode::PageDown);
+        viewer.key(&mut gameboy, Keycode::PageDown);
+        viewer.key(&mut gameboy, Keycode::PageDown);
+        assert_eq!(viewer.top, 0xC600 - 31 * 16);
+
+        viewer.select(0xFFFF);
+        assert_eq!(viewer.top, LAST_TOP);
+        viewer.click((MARGIN + PREFIX_COLUMNS * CHAR_WIDTH, MARGIN + LINE_HEIGHT));
+        assert_eq!(viewer.cursor(), LAST_TOP + 16);
+    }
+}
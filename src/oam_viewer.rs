@@ -0,0 +1,208 @@
+//! The 40 OAM entries with their attributes decoded and whether the PPU
+//! actually draws them, as text for the debugger and as sprite thumbnails.
+
+use sdl2::pixels::Color;
+
+use crate::memory::MemoryBus;
+use crate::ppu::{
+    pack, shade, sprite_height, sprites_on_line, tile_row_colors, Sprite, SCREEN_HEIGHT,
+    SCREEN_WIDTH,
+};
+
+const COLUMNS: usize = 8;
+/// Room for an 8x16 sprite with a 2 pixel border.
+const CELL_WIDTH: usize = 12;
+const CELL_HEIGHT: usize = 20;
+
+pub const WIDTH: usize = COLUMNS * CELL_WIDTH;
+pub const HEIGHT: usize = 40 / COLUMNS * CELL_HEIGHT;
+
+const TRANSPARENT: Color = Color::RGB(0x40, 0x40, 0x40);
+const VISIBLE: Color = Color::RGB(0x00, 0xC0, 0x00);
+const HIDDEN: Color = Color::RGB(0x80, 0x80, 0x80);
+const DROPPED: Color = Color::RGB(0xFF, 0x00, 0x00);
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Entry {
+    pub sprite: Sprite,
+    /// Whether any part of it is within the screen.
+    pub on_screen: bool,
+    /// Lines it covers but isn't drawn on because ten other sprites came first.
+    pub dropped_lines: usize,
+}
+
+impl Entry {
+    fn status_color(&self) -> Color {
+        match (self.on_screen, self.dropped_lines) {
+            (false, _) => HIDDEN,
+            (true, 0) => VISIBLE,
+            (true, _) => DROPPED,
+        }
+    }
+}
+
+impl std::fmt::Display for Entry {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let sprite = &self.sprite;
+        write!(
+            f,
+            "{:>2}  {:>3} {:>3}  {:02X}    {}  {}{}  {}  ",
+            sprite.index,
+            sprite.x,
+            sprite.y,
+            sprite.tile,
+            if sprite.palette() == 0xFF49 {
+                "OBP1"
+            } else {
+                "OBP0"
+            },
+            if sprite.x_flip() { 'X' } else { '-' },
+            if sprite.y_flip() { 'Y' } else { '-' },
+            if sprite.behind_background() {
+                "behind BG"
+            } else {
+                "above BG "
+            },
+        )?;
+        match (self.on_screen, self.dropped_lines) {
+            (false, _) => write!(f, "off screen"),
+            (true, 0) => write!(f, "visible"),
+            (true, lines) => write!(f, "dropped on {} lines", lines),
+        }
+    }
+}
+
+/// Inspects every OAM entry against the current LCDC.
+pub fn inspect(bus: &MemoryBus) -> Vec<Entry> {
+    let height = sprite_height(bus);
+    let drawn: Vec<Vec<Sprite>> = (0..SCREEN_HEIGHT as u8)
+        .map(|line| sprites_on_line(bus, line))
+        .collect();
+    Sprite::all(bus)
+        .map(|sprite| {
+            let lines: Vec<usize> = (0..SCREEN_HEIGHT)
+                .filter(|&line| sprite.covers_line(line as u8, height))
+                .collect();
+            let on_screen =
+                !lines.is_empty() && sprite.left() > -8 && sprite.left() < SCREEN_WIDTH as i16;
+            let dropped_lines = lines
+                .iter()
+                .filter(|&&line| !drawn[line].contains(&sprite))
+                .count();
+            Entry {
+                sprite,
+                on_screen,
+                dropped_lines,
+            }
+        })
+        .collect()
+}
+
+/// A table of all entries, as shown by the debugger's `oam` command.
+pub fn listing(bus: &MemoryBus) -> String {
+    let mut out = String::from(" #    X   Y  tile  pal   flip  priority   status\n");
+    for entry in inspect(bus) {
+        out.push_str(&entry.to_string());
+        out.push('\n');
+    }
+    out
+}
+
+/// Renders each sprite with its own palette in a grid, in OAM order, framed
+/// green when drawn, red when dropped on some line and gray when off screen.
+pub fn render(bus: &MemoryBus) -> Vec<u32> {
+    let mut pixels = vec![pack(TRANSPARENT); WIDTH * HEIGHT];
+    let height = sprite_height(bus);
+    for entry in inspect(bus) {
+        let sprite = &entry.sprite;
+        let index = sprite.index as usize;
+        let (left, top) = (index % COLUMNS * CELL_WIDTH, index / COLUMNS * CELL_HEIGHT);
+
+        for x in 0..CELL_WIDTH {
+            for y in [0, CELL_HEIGHT - 1] {
+                pixels[(top + y) * WIDTH + left + x] = pack(entry.status_color());
+            }
+        }
+        for y in 0..CELL_HEIGHT {
+            for x in [0, CELL_WIDTH - 1] {
+                pixels[(top + y) * WIDTH + left + x] = pack(entry.status_color());
+            }
+        }
+
+        let palette = bus.read(sprite.palette());
+        for row in 0..height {
+            let source_row = if sprite.y_flip() {
+                height - 1 - row
+            } else {
+                row
+            };
+            let mut colors = tile_row_colors(bus, sprite.tile_address(height), source_row);
+            if sprite.x_flip() {
+                colors.reverse();
+            }
+            for (x, color_num) in colors.into_iter().enumerate() {
+                if color_num != 0 {
+                    pixels[(top + 2 + row as usize) * WIDTH + left + 2 + x] =
+                        pack(shade(color_num, palette));
+                }
+            }
+        }
+    }
+    pixels
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::memory::special_addresses::{LCDC, OBP1};
+
+    fn place(bus: &mut MemoryBus, index: usize, y: u8, x: u8, tile: u8, attributes: u8) {
+        bus.memory[0xFE00 + index * 4..0xFE00 + index * 4 + 4]
+            .copy_from_slice(&[y, x, tile, attributes]);
+    }
+
+    #[test]
+    fn test_inspect() {
+        let mut bus = MemoryBus::default();
+        bus.memory[LCDC] = 0x93;
+        // eleven sprites on lines 0-7; the last one only fits on none of them
+        for index in 0..11 {
+            place(&mut bus, index, 16, 8 + index as u8 * 8, 0, 0);
+        }
+        place(&mut bus, 11, 0, 50, 0, 0);
+        place(&mut bus, 12, 40, 20, 3, 0xF0);
+        let entries = inspect(&bus);
+
+        assert!(entries[0].on_screen);
+        assert_eq!(entries[9].dropped_lines, 0);
+        assert_eq!(entries[10].dropped_lines, 8);
+        assert!(!entries[11].on_screen);
+        assert_eq!(
+            entries[12].to_string(),
+            "12   20  40  03    OBP1  XY  behind BG  visible"
+        );
+        assert_eq!(
+            entries[10].to_string().rsplit("  ").next(),
+            Some("dropped on 8 lines")
+        );
+    }
+
+    #[test]
+    fn test_render_thumbnail() {
+        let mut bus = MemoryBus::default();
+        bus.memory[LCDC] = 0x93;
+        bus.memory[OBP1] = 0xE4;
+        // tile 1, top row: leftmost pixel color 3, rest transparent
+        bus.memory[0x8010] = 0x80;
+        bus.memory[0x8011] = 0x80;
+        place(&mut bus, 1, 16, 8, 1, 0x30);
+        let pixels = render(&bus);
+
+        let (left, top) = (CELL_WIDTH + 2, 2);
+        // flipped horizontally, so the opaque pixel is on the right
+        assert_eq!(pixels[top * WIDTH + left + 7], pack(Color::BLACK));
+        assert_eq!(pixels[top * WIDTH + left], pack(TRANSPARENT));
+        assert_eq!(pixels[CELL_WIDTH], pack(VISIBLE));
+        assert_eq!(pixels[WIDTH * CELL_HEIGHT], pack(HIDDEN));
+    }
+}
@@ -1,33 +1,56 @@
-use serde::Deserialize;
-use std::collections::HashMap;
+//! Mnemonics, lengths, timings and flag effects of every opcode, generated at
+//! build time from `Opcodes.json`.
 
-#[derive(Deserialize, Debug)]
-#[serde(rename_all = "UPPERCASE")]
+#[derive(Debug)]
 pub struct FlagInformation {
-    pub z: String,
-    pub n: String,
-    pub h: String,
-    pub c: String,
+    pub z: &'static str,
+    pub n: &'static str,
+    pub h: &'static str,
+    pub c: &'static str,
 }
 
-#[derive(Deserialize, Debug, Clone)]
+#[derive(Debug)]
 pub struct OperandInformation {
-    pub name: String,
+    pub name: &'static str,
     pub immediate: bool,
+    /// Size of the operand in the instruction stream, 0 for registers and constants.
+    pub bytes: u8,
+    pub increment: bool,
+    pub decrement: bool,
 }
 
-#[derive(Deserialize, Debug)]
+#[derive(Debug)]
 pub struct Opcode {
-    pub operands: Vec<OperandInformation>,
-    pub mnemonic: String,
+    pub operands: &'static [OperandInformation],
+    pub mnemonic: &'static str,
     pub bytes: u8,
     pub immediate: bool,
-    pub cycles: Vec<u8>,
+    pub cycles: &'static [u8],
     pub flags: FlagInformation,
 }
 
-#[derive(Deserialize, Debug)]
-pub struct OpcodeInfo {
-    pub unprefixed: HashMap<String, Opcode>,
-    pub cbprefixed: HashMap<String, Opcode>,
+include!(concat!(env!("OUT_DIR"), "/opcodes.rs"));
+
+pub fn unprefixed(byte: u8) -> &'static Opcode {
+    &UNPREFIXED[byte as usize]
+}
+
+pub fn cbprefixed(byte: u8) -> &'static Opcode {
+    &CBPREFIXED[byte as usize]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tables() {
+        assert_eq!(unprefixed(0x00).mnemonic, "NOP");
+        assert_eq!(unprefixed(0x20).cycles, &[12, 8]);
+        assert_eq!(unprefixed(0xFA).bytes, 3);
+        assert!(unprefixed(0x22).operands[0].increment);
+        assert_eq!(unprefixed(0xE0).operands[0].name, "a8");
+        assert_eq!(cbprefixed(0x7C).mnemonic, "BIT");
+        assert_eq!(cbprefixed(0x7C).flags.z, "Z");
+    }
 }
@@ -1,19 +1,23 @@
-extern crate sdl2;
-use std::cell::RefCell;
+use sdl2::pixels::Color;
 
-use sdl2::{
-    pixels::{Color, PixelFormatEnum},
-    render::Texture,
-    Sdl,
-};
+use crate::memory::{special_addresses, MemoryBus};
 
-use crate::{gameboy, memory::special_addresses};
+pub const SCREEN_WIDTH: usize = 160;
+pub const SCREEN_HEIGHT: usize = 144;
+
+/// The STAT mode the PPU is in, as reported in the low bits of STAT.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Mode {
+    HBlank = 0,
+    VBlank = 1,
+    OamScan = 2,
+    Drawing = 3,
+}
 
 pub struct PPU {
-    canvas: sdl2::render::Canvas<sdl2::video::Window>,
-    texture_creator: sdl2::render::TextureCreator<sdl2::video::WindowContext>,
-    texture: RefCell<sdl2::render::Texture<'static>>,
-    frambuffer_alpha: [u32; 160 * 144],
+    frambuffer_alpha: [u32; SCREEN_WIDTH * SCREEN_HEIGHT],
+    /// Set when the PPU enters VBlank, cleared by whoever presents the frame.
+    pub frame_ready: bool,
 }
 
 macro_rules! flag_set_at {
@@ -27,77 +31,186 @@ const LIGHT_GRAY: Color = Color::RGBA(0xCC, 0xCC, 0xCC, 0xFF);
 const DARK_GRAY: Color = Color::RGBA(0x77, 0x77, 0x77, 0xFF);
 const BLACK: Color = Color::BLACK;
 
-impl PPU {
-    pub fn new(sdl_context: &Sdl) -> PPU {
-        let video_subsystem = sdl_context.video().unwrap();
-
-        let window = video_subsystem
-            .window("Rust Gameboy Emulator", 800, 600)
-            .position_centered()
-            .resizable()
-            .opengl()
-            .build()
-            .map_err(|e| e.to_string())
-            .unwrap();
-
-        let canvas = window
-            .into_canvas()
-            .build()
-            .map_err(|e| e.to_string())
-            .unwrap();
-
-        let texture_creator = canvas.texture_creator();
-
-        let texture = texture_creator
-            .create_texture(
-                PixelFormatEnum::ABGR32,
-                sdl2::render::TextureAccess::Streaming,
-                160,
-                144,
-            )
-            .unwrap();
+/// Where tile `tile_num` starts, for tile data at `tiledata` (0x8000 or 0x8800)
+/// addressed with unsigned or signed tile numbers respectively.
+pub fn tile_address(tiledata: u16, unsigned: bool, tile_num: u8) -> u16 {
+    tiledata
+        + (if unsigned {
+            tile_num as u16
+        } else {
+            ((tile_num as i8 as i16) + 128) as u16
+        } * 16)
+}
+
+/// Decodes one 2bpp row of the tile at `tile_location` into color numbers,
+/// leftmost pixel first.
+pub fn tile_row_colors(bus: &MemoryBus, tile_location: u16, row: u8) -> [u8; 8] {
+    let line = (row as u16) * 2;
+    let data1 = bus.read(tile_location + line);
+    let data2 = bus.read(tile_location + line + 1);
+    std::array::from_fn(|pixel| {
+        let color_bit = 7 - pixel;
+        (((data2 >> color_bit) & 1) << 1) | ((data1 >> color_bit) & 1)
+    })
+}
+
+/// Looks a color number up in a palette register like BGP.
+pub fn shade(color_num: u8, pallete: u8) -> Color {
+    let (hi, lo) = match color_num {
+        0 => (1, 0),
+        1 => (3, 2),
+        2 => (5, 4),
+        3 => (7, 6),
+        _ => panic!("Invalid color number"),
+    };
+
+    let color = ((pallete >> hi) & 1) << 1 | ((pallete >> lo) & 1);
+
+    match color {
+        0 => WHITE,
+        1 => LIGHT_GRAY,
+        2 => DARK_GRAY,
+        3 => BLACK,
+        _ => panic!("Invalid color"),
+    }
+}
+
+/// Packs a color the way the framebuffer stores it.
+pub fn pack(color: Color) -> u32 {
+    u32::from_be_bytes(color.rgba().into())
+}
+
+/// At most this many sprites are drawn on one line.
+pub const SPRITES_PER_LINE: usize = 10;
+
+/// One of the 40 entries in OAM.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Sprite {
+    pub index: u8,
+    pub y: u8,
+    pub x: u8,
+    pub tile: u8,
+    pub attributes: u8,
+}
+
+impl Sprite {
+    pub fn read(bus: &MemoryBus, index: u8) -> Sprite {
+        let address = 0xFE00 + index as u16 * 4;
+        Sprite {
+            index,
+            y: bus.read(address),
+            x: bus.read(address + 1),
+            tile: bus.read(address + 2),
+            attributes: bus.read(address + 3),
+        }
+    }
+
+    /// All 40 entries in OAM order.
+    pub fn all<'b>(bus: &'b MemoryBus) -> impl Iterator<Item = Sprite> + 'b {
+        (0..40).map(|index| Sprite::read(bus, index))
+    }
 
-        let texture = unsafe { std::mem::transmute::<_, Texture<'static>>(texture) };
+    /// The screen row of the top edge; OAM stores it offset by 16.
+    pub fn top(&self) -> i16 {
+        self.y as i16 - 16
+    }
+
+    /// The screen column of the left edge; OAM stores it offset by 8.
+    pub fn left(&self) -> i16 {
+        self.x as i16 - 8
+    }
+
+    pub fn covers_line(&self, line: u8, height: u8) -> bool {
+        (self.top()..self.top() + height as i16).contains(&(line as i16))
+    }
+
+    /// Where the sprite's tile data starts. 8x16 sprites ignore the low bit of
+    /// the tile number.
+    pub fn tile_address(&self, height: u8) -> u16 {
+        let tile = if height == 16 {
+            self.tile & 0xFE
+        } else {
+            self.tile
+        };
+        0x8000 + tile as u16 * 16
+    }
+
+    pub fn behind_background(&self) -> bool {
+        flag_set_at!(self.attributes, 7)
+    }
+
+    pub fn y_flip(&self) -> bool {
+        flag_set_at!(self.attributes, 6)
+    }
+
+    pub fn x_flip(&self) -> bool {
+        flag_set_at!(self.attributes, 5)
+    }
+
+    /// The palette register the sprite is colored with, OBP0 or OBP1.
+    pub fn palette(&self) -> u16 {
+        if flag_set_at!(self.attributes, 4) {
+            special_addresses::OBP1 as u16
+        } else {
+            special_addresses::OBP0 as u16
+        }
+    }
+}
+
+/// 8 or 16 depending on LCDC bit 2.
+pub fn sprite_height(bus: &MemoryBus) -> u8 {
+    if flag_set_at!(bus.read(special_addresses::LCDC as u16), 2) {
+        16
+    } else {
+        8
+    }
+}
+
+/// The sprites the PPU picks for a line: the first ten in OAM order that
+/// overlap it. They're drawn in a different order, see `draw_order`.
+pub fn sprites_on_line(bus: &MemoryBus, line: u8) -> Vec<Sprite> {
+    let height = sprite_height(bus);
+    Sprite::all(bus)
+        .filter(|sprite| sprite.covers_line(line, height))
+        .take(SPRITES_PER_LINE)
+        .collect()
+}
+
+/// Sorts sprites so that drawing them in turn leaves the right one on top: on
+/// the DMG the sprite further left wins, then the one earlier in OAM.
+pub fn draw_order(sprites: &mut [Sprite]) {
+    sprites.sort_by_key(|sprite| std::cmp::Reverse((sprite.x, sprite.index)));
+}
+
+impl Default for PPU {
+    fn default() -> Self {
         PPU {
-            canvas,
-            texture_creator,
-            texture: RefCell::new(texture),
-            frambuffer_alpha: [0; 160 * 144],
+            frambuffer_alpha: [0; SCREEN_WIDTH * SCREEN_HEIGHT],
+            frame_ready: false,
         }
     }
+}
 
-    pub fn update(&mut self, gameboy: &gameboy::Gameboy, current_scanline: u8) {
-        // self.canvas.set_draw_color(Color::RGB(255, 0, 0));
-        let control = gameboy.read_byte(0xff40);
+impl PPU {
+    pub fn update(&mut self, bus: &MemoryBus, current_scanline: u8) {
+        let control = bus.read(0xff40);
 
         if flag_set_at!(control, 0) {
-            self.render_tiles(gameboy, current_scanline);
+            self.render_tiles(bus, current_scanline);
         }
 
         if flag_set_at!(control, 1) {
-            self.render_sprites(gameboy, current_scanline);
+            self.render_sprites(bus, current_scanline);
         }
     }
 
-    pub fn draw(&mut self) {
-        let mut texture = self.texture.borrow_mut();
-        texture
-            .update(None, self.data_raw(), 160 * 4)
-            .expect("Failed to update texture");
-        // texture.update(None, pixel_data, pitch);
-        self.canvas
-            .copy(&texture, None, None)
-            .expect("Failed to copy texture");
-        self.canvas.present();
-    }
-
-    fn render_tiles(&mut self, gameboy: &gameboy::Gameboy, current_scanline: u8) {
-        let scroll_y = gameboy.read_byte(0xff42);
-        let scroll_x = gameboy.read_byte(0xff43);
-        let window_y = gameboy.read_byte(0xff4a);
-        let window_x = gameboy.read_byte(0xff4b).wrapping_sub(7);
+    fn render_tiles(&mut self, bus: &MemoryBus, current_scanline: u8) {
+        let scroll_y = bus.read(0xff42);
+        let scroll_x = bus.read(0xff43);
+        let window_y = bus.read(0xff4a);
+        let window_x = bus.read(0xff4b).wrapping_sub(7);
 
-        let control = gameboy.read_byte(special_addresses::LCDC as u16);
+        let control = bus.read(special_addresses::LCDC as u16);
 
         let using_window = flag_set_at!(control, 5) && window_y <= current_scanline;
 
@@ -127,47 +240,42 @@ impl PPU {
 
         let tile_row = (y_pos as u16 / 8) * 32;
 
-        for pixel in 0..160 {
-            let mut x_pos = pixel + scroll_x;
+        for pixel in 0..160u8 {
+            let mut x_pos = pixel.wrapping_add(scroll_x);
             if using_window && pixel >= window_x {
                 x_pos = pixel - window_x;
             }
 
             let tile_col = x_pos / 8;
-            let tile_address = tilemap + tile_row + tile_col as u16;
-
-            let tile_num = gameboy.read_byte(tile_address);
-
-            let tile_location = tiledata
-                + (if unsigned {
-                    tile_num as u16
-                } else {
-                    ((tile_num as i8 as i16) + 128) as u16
-                } * 16);
-
-            let line = ((y_pos % 8) * 2) as u16;
-            let data1 = gameboy.read_byte(tile_location + line);
-            let data2 = gameboy.read_byte(tile_location + line + 1);
-
-            let color_bit = 7 - (x_pos % 8);
-
-            let mut color_num;
-
-            color_num = ((data2 >> color_bit) & 1) << 1;
-            color_num |= (data1 >> color_bit) & 1;
-
-            let color = self.get_color(color_num, gameboy.read_byte(0xFF47));
+            let tile_num = bus.read(tilemap + tile_row + tile_col as u16);
+            let tile_location = tile_address(tiledata, unsigned, tile_num);
+            let color_num = tile_row_colors(bus, tile_location, y_pos % 8)[(x_pos % 8) as usize];
+            let color = shade(color_num, bus.read(0xFF47));
 
             if (current_scanline > 143) || (pixel > 159) {
                 continue;
             }
 
-            self.frambuffer_alpha[current_scanline as usize * 160 + (pixel as usize)] =
-                u32::from_be_bytes(color.rgba().into());
+            self.frambuffer_alpha[current_scanline as usize * 160 + (pixel as usize)] = pack(color);
         }
     }
 
-    fn data_raw(&self) -> &[u8] {
+    /// The color of a pixel in the last rendered frame.
+    pub fn pixel(&self, x: usize, y: usize) -> Color {
+        let [r, g, b, a] = self.frambuffer_alpha[y * SCREEN_WIDTH + x].to_be_bytes();
+        Color::RGBA(r, g, b, a)
+    }
+
+    /// The last rendered frame, packed as `pack` does, row by row.
+    pub(crate) fn framebuffer(&self) -> &[u32] {
+        &self.frambuffer_alpha
+    }
+
+    pub(crate) fn framebuffer_mut(&mut self) -> &mut [u32] {
+        &mut self.frambuffer_alpha
+    }
+
+    pub fn data_raw(&self) -> &[u8] {
         unsafe {
             std::slice::from_raw_parts(
                 self.frambuffer_alpha.as_ptr() as *const u8,
@@ -176,89 +284,77 @@ impl PPU {
         }
     }
 
-    fn get_color(&self, color_num: u8, pallete: u8) -> Color {
-        let (hi, lo) = match color_num {
-            0 => (1, 0),
-            1 => (3, 2),
-            2 => (5, 4),
-            3 => (7, 6),
-            _ => panic!("Invalid color number"),
-        };
-
-        let color = ((pallete >> hi) & 1) << 1 | ((pallete >> lo) & 1);
-
-        match color {
-            0 => WHITE,
-            1 => LIGHT_GRAY,
-            2 => DARK_GRAY,
-            3 => BLACK,
-            _ => panic!("Invalid color"),
+    fn render_sprites(&mut self, bus: &MemoryBus, current_scanline: u8) {
+        if current_scanline as usize >= SCREEN_HEIGHT {
+            return;
         }
-    }
-
-    fn render_sprites(&mut self, gameboy: &gameboy::Gameboy, current_scanline: u8) {
-        let control = gameboy.read_byte(0xff40);
-        let use8x16 = flag_set_at!(control, 2);
-
-        for sprite in 0..40 {
-            let index = sprite * 4;
-            let mut y_pos = gameboy.read_byte(0xfe00 + index);
-            if y_pos == 0 || y_pos >= 160 {
-                continue;
+        let height = sprite_height(bus);
+        let mut sprites = sprites_on_line(bus, current_scanline);
+        draw_order(&mut sprites);
+
+        for sprite in sprites {
+            let mut row = (current_scanline as i16 - sprite.top()) as u8;
+            if sprite.y_flip() {
+                row = height - 1 - row;
             }
-            if y_pos >= 16 {
-                y_pos -= 16;
+            let mut colors = tile_row_colors(bus, sprite.tile_address(height), row);
+            if sprite.x_flip() {
+                colors.reverse();
             }
-            let x_pos = gameboy.read_byte(0xfe00 + index + 1) - 8;
-            let tile_location = gameboy.read_byte(0xfe00 + index + 2);
-            let attributes = gameboy.read_byte(0xfe00 + index + 3);
+            let palette = bus.read(sprite.palette());
 
-            let x_flip = flag_set_at!(attributes, 5);
-            let y_flip = flag_set_at!(attributes, 6);
-
-            let y_size = if use8x16 { 16 } else { 8 };
-
-            // Check if sprite is on this line
-            if (current_scanline >= y_pos) && (current_scanline < y_pos + y_size) {
-                let mut line = (current_scanline - y_pos) as i8;
-
-                if y_flip {
-                    line -= y_size as i8;
-                    line *= -1;
+            for (offset, color_num) in colors.into_iter().enumerate() {
+                let pixel = sprite.left() + offset as i16;
+                // color 0 is transparent for sprites
+                if color_num == 0 || !(0..SCREEN_WIDTH as i16).contains(&pixel) {
+                    continue;
                 }
+                self.frambuffer_alpha[current_scanline as usize * SCREEN_WIDTH + pixel as usize] =
+                    pack(shade(color_num, palette));
+            }
+        }
+    }
+}
 
-                line *= 2;
-
-                let data_address = 0x8000 + (tile_location as u16 * 16) + (line as u16);
-
-                let data1 = gameboy.read_byte(data_address);
-                let data2 = gameboy.read_byte(data_address + 1);
-
-                for tile_pixel in (0..=7).rev() {
-                    let mut color_bit = tile_pixel as i8;
-                    if x_flip {
-                        color_bit -= 7;
-                        color_bit *= -1;
-                    }
-
-                    let mut color_num = ((data2 >> color_bit) & 1) << 1;
-                    color_num |= (data1 >> color_bit) & 1;
-
-                    let color = self.get_color(color_num, gameboy.read_byte(0xFF48));
-                    if color == WHITE {
-                        continue;
-                    }
-
-                    let pixel = x_pos + 7 - tile_pixel;
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::memory::special_addresses::*;
 
-                    if (current_scanline > 143) || (pixel > 159) {
-                        continue;
-                    }
+    fn set_sprite(bus: &mut MemoryBus, index: usize, y: u8, x: u8, tile: u8, attributes: u8) {
+        bus.memory[0xFE00 + index * 4..0xFE00 + index * 4 + 4]
+            .copy_from_slice(&[y, x, tile, attributes]);
+    }
 
-                    self.frambuffer_alpha[current_scanline as usize * 160 + (pixel as usize)] =
-                        u32::from_be_bytes(color.rgba().into());
-                }
-            }
+    #[test]
+    fn test_render_sprites() {
+        let mut bus = MemoryBus::default();
+        bus.memory[LCDC] = 0x83;
+        bus.memory[BGP] = 0xE4;
+        bus.memory[OBP0] = 0xE4;
+        bus.memory[OBP1] = 0x1B;
+        // tile 1: color 3 across; tile 2: color 1 on the left half, 0 on the right
+        bus.memory[0x8010..0x8012].copy_from_slice(&[0xFF, 0xFF]);
+        bus.memory[0x8020..0x8022].copy_from_slice(&[0xF0, 0x00]);
+        // line 0: black at 2-9, dark under OBP1 at 0-3, and white under OBP1 at
+        // 4-11, which is last in OAM but furthest right so loses the overlap
+        set_sprite(&mut bus, 0, 16, 10, 1, 0x00);
+        set_sprite(&mut bus, 1, 16, 8, 2, 0x10);
+        set_sprite(&mut bus, 2, 16, 12, 1, 0x10);
+        // line 8: eleven sprites side by side, the last of which isn't drawn
+        for index in 3..14 {
+            set_sprite(&mut bus, index, 24, 8 + (index as u8 - 3) * 8, 1, 0x00);
         }
+        let mut ppu = PPU::default();
+        ppu.update(&bus, 0);
+        ppu.update(&bus, 8);
+
+        let line: Vec<Color> = (0..14).map(|x| ppu.pixel(x, 0)).collect();
+        let mut expected = vec![DARK_GRAY; 4];
+        expected.extend([BLACK; 6]);
+        expected.extend([WHITE; 4]);
+        assert_eq!(line, expected);
+        assert_eq!(ppu.pixel(79, 8), BLACK);
+        assert_eq!(ppu.pixel(80, 8), WHITE);
     }
 }
@@ -0,0 +1,314 @@
+//! Where the cycles go: t-states per instruction address and per stack of
+//! routines entered through CALL, RST and interrupts, as tracked by the
+//! `CallStack`, written out as a report or as folded stacks for flamegraph
+//! tools.
+
+use std::collections::HashMap;
+use std::fmt::Write;
+
+use crate::call_stack::{Frame, FrameKind};
+use crate::symbols::Symbols;
+
+/// How many rows the report's tables show.
+const TOP: usize = 50;
+
+/// A code address together with the ROM bank mapped there; the bank is 0
+/// outside ROM.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Location {
+    pub bank: u8,
+    pub address: u16,
+}
+
+impl Location {
+    fn name(self, symbols: &Symbols) -> String {
+        match symbols.name(self.bank, self.address) {
+            Some(name) => name.to_string(),
+            None if self.address < 0x8000 => format!("{:02X}:{:04X}", self.bank, self.address),
+            None => format!("${:04X}", self.address),
+        }
+    }
+}
+
+#[derive(Debug, Default, Copy, Clone)]
+struct Ticks {
+    running: u64,
+    halted: u64,
+}
+
+impl Ticks {
+    fn total(self) -> u64 {
+        self.running + self.halted
+    }
+}
+
+#[derive(Debug)]
+pub struct Profiler {
+    by_address: HashMap<Location, u64>,
+    /// The routines time is currently counted against, outermost first.
+    routines: Vec<Location>,
+    in_interrupt: bool,
+    /// Every distinct stack of routines seen so far, outermost first, with
+    /// the time spent in each.
+    stacks: Vec<(Vec<Location>, Ticks)>,
+    stack_ids: HashMap<Vec<Location>, usize>,
+    /// The index in `stacks` of the current stack.
+    current: usize,
+    total: u64,
+    halted: u64,
+    interrupts: u64,
+}
+
+impl Default for Profiler {
+    fn default() -> Self {
+        Profiler {
+            by_address: HashMap::new(),
+            routines: Vec::new(),
+            in_interrupt: false,
+            stacks: vec![(Vec::new(), Ticks::default())],
+            stack_ids: HashMap::from([(Vec::new(), 0)]),
+            current: 0,
+            total: 0,
+            halted: 0,
+            interrupts: 0,
+        }
+    }
+}
+
+impl Profiler {
+    fn count(&mut self, ticks: u64, halted: bool) {
+        self.total += ticks;
+        let stack = &mut self.stacks[self.current].1;
+        if halted {
+            self.halted += ticks;
+            stack.halted += ticks;
+        } else {
+            stack.running += ticks;
+        }
+        if self.in_interrupt {
+            self.interrupts += ticks;
+        }
+    }
+
+    /// Counts what follows against the routines of the call stack `frames`.
+    pub fn enter(&mut self, frames: &[Frame]) {
+        let unchanged = self.routines.len() == frames.len()
+            && self
+                .routines
+                .iter()
+                .zip(frames)
+                .all(|(routine, frame)| *routine == frame.target);
+        if unchanged {
+            return;
+        }
+        self.routines = frames.iter().map(|frame| frame.target).collect();
+        self.in_interrupt = frames
+            .iter()
+            .any(|frame| frame.kind == FrameKind::Interrupt);
+        self.current = match self.stack_ids.get(&self.routines) {
+            Some(&id) => id,
+            None => {
+                let id = self.stacks.len();
+                self.stack_ids.insert(self.routines.clone(), id);
+                self.stacks.push((self.routines.clone(), Ticks::default()));
+                id
+            }
+        };
+    }
+
+    /// Counts an instruction at `location` that took `ticks`.
+    pub fn instruction(&mut self, location: Location, ticks: u8) {
+        *self.by_address.entry(location).or_default() += ticks as u64;
+        self.count(ticks as u64, false);
+    }
+
+    /// Counts time the CPU spent halted.
+    pub fn halt(&mut self, ticks: u8) {
+        self.count(ticks as u64, true);
+    }
+
+    /// Counts the `ticks` an interrupt dispatch took.
+    pub fn interrupt(&mut self, ticks: u8) {
+        self.count(ticks as u64, false);
+    }
+
+    /// A summary with the routines and addresses that took the most time.
+    pub fn report(&self, symbols: &Symbols) -> String {
+        let percent = |ticks: u64| 100.0 * ticks as f64 / self.total.max(1) as f64;
+        let mut out = String::new();
+        writeln!(
+            out,
+            "{} t-states profiled, {} halted ({:.1}%), {} in interrupt handlers ({:.1}%)",
+            self.total,
+            self.halted,
+            percent(self.halted),
+            self.interrupts,
+            percent(self.interrupts)
+        )
+        .unwrap();
+
+        let mut routines: HashMap<Option<Location>, (u64, u64)> = HashMap::new();
+        for (stack, ticks) in &self.stacks {
+            routines.entry(stack.last().copied()).or_default().0 += ticks.total();
+            let mut seen = Vec::new();
+            for routine in stack.iter().map(|&routine| Some(routine)).chain([None]) {
+                if !seen.contains(&routine) {
+                    routines.entry(routine).or_default().1 += ticks.total();
+                    seen.push(routine);
+                }
+            }
+        }
+        let mut routines: Vec<_> = routines.into_iter().collect();
+        routines.sort_by_key(|&(routine, (own, _))| (std::cmp::Reverse(own), routine));
+        writeln!(out, "\nRoutines by self time, halted included:").unwrap();
+        writeln!(out, "      self      %       total      %  routine").unwrap();
+        for (routine, (own, total)) in routines.into_iter().take(TOP) {
+            let name = match routine {
+                Some(routine) => routine.name(symbols),
+                None => "(top level)".to_string(),
+            };
+            writeln!(
+                out,
+                "{:>10} {:>5.1}%  {:>10} {:>5.1}%  {}",
+                own,
+                percent(own),
+                total,
+                percent(total),
+                name
+            )
+            .unwrap();
+        }
+
+        if !symbols.is_empty() {
+            let mut labels: HashMap<&str, u64> = HashMap::new();
+            for (location, ticks) in &self.by_address {
+                let label = symbols
+                    .containing(location.bank, location.address)
+                    .map_or("(no label)", |(name, _)| name);
+                *labels.entry(label).or_default() += ticks;
+            }
+            let mut labels: Vec<_> = labels.into_iter().collect();
+            labels.sort_by_key(|&(label, ticks)| (std::cmp::Reverse(ticks), label));
+            writeln!(out, "\nLabels by time spent in the code after them:").unwrap();
+            for (label, ticks) in labels.into_iter().take(TOP) {
+                writeln!(out, "{:>10} {:>5.1}%  {}", ticks, percent(ticks), label).unwrap();
+            }
+        }
+
+        let mut addresses: Vec<_> = self.by_address.iter().collect();
+        addresses.sort_by_key(|&(location, ticks)| (std::cmp::Reverse(*ticks), *location));
+        writeln!(out, "\nHottest addresses:").unwrap();
+        for (location, ticks) in addresses.into_iter().take(TOP) {
+            writeln!(
+                out,
+                "{:>10} {:>5.1}%  {:02X}:{:04X}  {}",
+                ticks,
+                percent(*ticks),
+                location.bank,
+                location.address,
+                symbols.describe(location.bank, location.address)
+            )
+            .unwrap();
+        }
+        out
+    }
+
+    /// One `top;Outer;Inner COUNT` line per stack, the format flamegraph.pl
+    /// and inferno read. Time spent halted gets its own `[halt]` frame.
+    pub fn folded(&self, symbols: &Symbols) -> String {
+        let mut lines = Vec::new();
+        for (stack, ticks) in &self.stacks {
+            let names: Vec<String> = std::iter::once("top".to_string())
+                .chain(stack.iter().map(|routine| routine.name(symbols)))
+                .collect();
+            let names = names.join(";");
+            if ticks.running > 0 {
+                lines.push(format!("{} {}", names, ticks.running));
+            }
+            if ticks.halted > 0 {
+                lines.push(format!("{};[halt] {}", names, ticks.halted));
+            }
+        }
+        lines.sort();
+        lines.into_iter().map(|line| line + "\n").collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gameboy::{gameboy_with_program, Gameboy};
+
+    fn profiled_gameboy<'a>(program: &[u8]) -> Gameboy<'a> {
+        let mut gameboy = gameboy_with_program(program);
+        gameboy.profiler = Some(Profiler::default());
+        gameboy
+    }
+
+    #[test]
+    fn test_call_and_return() {
+        // CALL $C010; NOP; ...; $C010: NOP; RET
+        let mut program = [0; 0x12];
+        program[..4].copy_from_slice(&[0xCD, 0x10, 0xC0, 0x00]);
+        program[0x10..].copy_from_slice(&[0x00, 0xC9]);
+        let mut gameboy = profiled_gameboy(&program);
+        for _ in 0..4 {
+            gameboy.step().unwrap();
+        }
+        let mut symbols = Symbols::default();
+        symbols.insert(0, 0xC010, "Sub");
+        let profiler = gameboy.profiler.unwrap();
+
+        assert_eq!(profiler.folded(&symbols), "top 28\ntop;Sub 20\n");
+        let report = profiler.report(&symbols);
+        assert!(report.starts_with("48 t-states profiled, 0 halted (0.0%)"));
+        assert!(report.contains("        28  58.3%          48 100.0%  (top level)\n"));
+        assert!(report.contains("        20  41.7%          20  41.7%  Sub\n"));
+        assert!(report.contains("        24  50.0%  00:C000  $C000\n"));
+    }
+
+    #[test]
+    fn test_follows_call_stack() {
+        // CALL $C010; NOP; ...; $C010: CALL $C020; $C020: POP HL; RET
+        let mut program = [0; 0x22];
+        program[..4].copy_from_slice(&[0xCD, 0x10, 0xC0, 0x00]);
+        program[0x10..0x13].copy_from_slice(&[0xCD, 0x20, 0xC0]);
+        program[0x20..].copy_from_slice(&[0xE1, 0xC9]);
+        let mut gameboy = profiled_gameboy(&program);
+        for _ in 0..5 {
+            gameboy.step().unwrap();
+        }
+
+        // the RET skips $C010, which the call stack drops as well
+        assert!(gameboy.call_stack.frames().is_empty());
+        assert_eq!(
+            gameboy.profiler.unwrap().folded(&Symbols::default()),
+            "top 28\ntop;$C010 24\ntop;$C010;$C020 28\n"
+        );
+    }
+
+    #[test]
+    fn test_interrupts_and_halt() {
+        // NOP; HALT, with a vblank handler that's just RETI
+        let mut gameboy = profiled_gameboy(&[0x00, 0x76]);
+        let cartridge = vec![0; 0x8000].leak();
+        cartridge[0x40] = 0xD9;
+        gameboy.bus.cartridge_rom = cartridge;
+        gameboy.bus.boot_rom_enabled = false;
+        gameboy.interrupts_enabled = true;
+        gameboy.bus.memory[0xFFFF] = 0x01;
+        gameboy.bus.memory[0xFF0F] = 0x01;
+        // NOP and dispatch, RETI, HALT, then two idle steps
+        for _ in 0..5 {
+            gameboy.step().unwrap();
+        }
+        let profiler = gameboy.profiler.unwrap();
+
+        assert_eq!(profiler.interrupts, 36);
+        assert_eq!(profiler.halted, 8);
+        assert_eq!(
+            profiler.folded(&Symbols::default()),
+            "top 8\ntop;00:0040 36\ntop;[halt] 8\n"
+        );
+    }
+}
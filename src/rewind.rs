@@ -0,0 +1,299 @@
+//! Going back in time: save states taken every few frames, kept as the newest
+//! one in full plus a chain of deltas leading back to older ones, within a
+//! memory budget.
+//!
+//! Each delta is the XOR of two consecutive states, which is mostly zeros
+//! because little changes in a few frames, run-length encoded.
+
+use std::collections::VecDeque;
+
+use crate::gameboy::Gameboy;
+use crate::save_state;
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Settings {
+    /// Frames between snapshots.
+    pub interval: u32,
+    /// Bytes the snapshots may take up in total; 0 turns rewinding off.
+    pub budget: usize,
+    /// How fast time runs backwards, 1.0 being as fast as it ran forwards.
+    pub speed: f64,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            interval: 4,
+            budget: 32 << 20,
+            speed: 2.0,
+        }
+    }
+}
+
+/// Writes `value` in LEB128.
+fn write_varint(out: &mut Vec<u8>, mut value: usize) {
+    while value >= 0x80 {
+        out.push(value as u8 | 0x80);
+        value >>= 7;
+    }
+    out.push(value as u8);
+}
+
+fn read_varint(bytes: &mut &[u8]) -> usize {
+    let mut value = 0;
+    let mut shift = 0;
+    while let Some((&byte, rest)) = bytes.split_first() {
+        *bytes = rest;
+        value |= ((byte & 0x7F) as usize) << shift;
+        if byte & 0x80 == 0 {
+            break;
+        }
+        shift += 7;
+    }
+    value
+}
+
+/// What turns `from` into `to`: the length of `to`, then alternating counts
+/// of unchanged bytes and of changed bytes followed by their XOR.
+fn delta(from: &[u8], to: &[u8]) -> Vec<u8> {
+    let xor = |index: usize| to[index] ^ from.get(index).copied().unwrap_or(0);
+    let mut out = Vec::new();
+    write_varint(&mut out, to.len());
+    let mut index = 0;
+    while index < to.len() {
+        let start = index;
+        while index < to.len() && xor(index) == 0 {
+            index += 1;
+        }
+        write_varint(&mut out, index - start);
+        let start = index;
+        while index < to.len() && xor(index) != 0 {
+            index += 1;
+        }
+        write_varint(&mut out, index - start);
+        out.extend((start..index).map(xor));
+    }
+    out
+}
+
+/// Turns `from` into what the `delta` it was given was made towards.
+fn apply(from: &[u8], mut delta: &[u8]) -> Vec<u8> {
+    let length = read_varint(&mut delta);
+    let mut out = from.to_vec();
+    out.resize(length, 0);
+    let mut index = 0;
+    while index < length {
+        index += read_varint(&mut delta);
+        let changed = read_varint(&mut delta);
+        for (byte, xor) in out[index..index + changed].iter_mut().zip(delta) {
+            *byte ^= xor;
+        }
+        delta = &delta[changed..];
+        index += changed;
+    }
+    out
+}
+
+#[derive(Debug)]
+pub struct Rewind {
+    settings: Settings,
+    /// The newest snapshot.
+    latest: Option<Vec<u8>>,
+    /// Deltas from each snapshot to the one before it, oldest first.
+    deltas: VecDeque<Vec<u8>>,
+    used: usize,
+    /// Frames run since `latest` was taken.
+    frames: u32,
+    /// Frames' worth of rewinding owed to the next `rewind` call.
+    progress: f64,
+}
+
+impl Rewind {
+    pub fn new(settings: Settings) -> Rewind {
+        Rewind {
+            settings,
+            latest: None,
+            deltas: VecDeque::new(),
+            used: 0,
+            frames: 0,
+            progress: 0.0,
+        }
+    }
+
+    pub fn enabled(&self) -> bool {
+        self.settings.budget > 0
+    }
+
+    /// How many snapshots there are to go back to.
+    pub fn len(&self) -> usize {
+        self.latest.as_ref().map_or(0, |_| self.deltas.len() + 1)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.latest.is_none()
+    }
+
+    /// Bytes the snapshots currently take up.
+    pub fn used(&self) -> usize {
+        self.used
+    }
+
+    /// Call after each frame run forwards; takes a snapshot when it's due.
+    pub fn frame(&mut self, gameboy: &Gameboy) {
+        if !self.enabled() {
+            return;
+        }
+        self.frames += 1;
+        self.progress = 0.0;
+        if self.latest.is_some() && self.frames < self.settings.interval {
+            return;
+        }
+        self.frames = 0;
+        let state = save_state::save(gameboy);
+        if let Some(previous) = self.latest.take() {
+            let back = delta(&state, &previous);
+            self.used += back.len();
+            self.used -= previous.len();
+            self.deltas.push_back(back);
+        }
+        self.used += state.len();
+        self.latest = Some(state);
+        while self.used > self.settings.budget {
+            match self.deltas.pop_front() {
+                Some(oldest) => self.used -= oldest.len(),
+                None => break,
+            }
+        }
+    }
+
+    /// Call once per frame while rewinding. Goes back as many snapshots as
+    /// the rewind speed calls for and returns whether any were restored.
+    pub fn rewind(&mut self, gameboy: &mut Gameboy) -> bool {
+        self.progress += self.settings.speed;
+        let mut restored = false;
+        while self.progress >= 1.0 {
+            // the newest snapshot is a partial interval back, the rest a whole one
+            let frames = if self.frames > 0 {
+                self.frames
+            } else {
+                self.settings.interval
+            };
+            if self.progress < frames as f64 {
+                break;
+            }
+            if !self.step_back() {
+                self.progress = 0.0;
+                break;
+            }
+            self.progress -= frames as f64;
+            restored = true;
+        }
+        if restored {
+            let latest = self.latest.as_ref().unwrap();
+            save_state::load(gameboy, latest).expect("rewind snapshots are valid");
+        }
+        restored
+    }
+
+    /// Makes the snapshot to restore next current, returning false when
+    /// there's none.
+    fn step_back(&mut self) -> bool {
+        if self.latest.is_none() {
+            return false;
+        }
+        if self.frames > 0 {
+            self.frames = 0;
+            return true;
+        }
+        let Some(back) = self.deltas.pop_back() else {
+            return false;
+        };
+        let latest = self.latest.as_mut().unwrap();
+        let previous = apply(latest, &back);
+        self.used -= back.len() + latest.len();
+        self.used += previous.len();
+        *latest = previous;
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gameboy::gameboy_with_program;
+
+    #[test]
+    fn test_delta() {
+        let from = [1, 2, 3, 4, 5, 6, 7, 8];
+        let to = [1, 2, 9, 4, 5, 0, 0, 8, 10];
+        let forward = delta(&from, &to);
+        assert_eq!(forward, [9, 2, 1, 3 ^ 9, 2, 2, 6, 7, 1, 1, 10]);
+        assert_eq!(apply(&from, &forward), to);
+        assert_eq!(apply(&to, &delta(&to, &from)), from);
+        assert_eq!(delta(&to, &to), [9, 9, 0]);
+    }
+
+    fn gameboy_counting_frames<'a>() -> Gameboy<'a> {
+        // INC [HL]; HALT; JR -3, woken up by every vblank
+        let mut gameboy = gameboy_with_program(&[0x34, 0x76, 0x18, 0xFC]);
+        let cartridge = vec![0; 0x8000].leak();
+        cartridge[0x40] = 0xD9;
+        gameboy.bus.cartridge_rom = cartridge;
+        gameboy.bus.boot_rom_enabled = false;
+        gameboy.cpu.registers.h = 0xC1;
+        gameboy.interrupts_enabled = true;
+        gameboy.write_byte(0xFF40, 0x80);
+        gameboy.write_byte(0xFFFF, 0x01);
+        gameboy
+    }
+
+    #[test]
+    fn test_rewind() {
+        let mut gameboy = gameboy_counting_frames();
+        let mut rewind = Rewind::new(Settings {
+            interval: 2,
+            budget: 1 << 20,
+            speed: 1.0,
+        });
+        for _ in 0..7 {
+            gameboy.run_frame().unwrap();
+            rewind.frame(&gameboy);
+        }
+        let counter = gameboy.read_byte(0xC100);
+        assert_eq!(rewind.len(), 4);
+
+        // snapshots are after frames 1, 3, 5 and 7, and it takes two calls
+        // to go back each one
+        assert!(!rewind.rewind(&mut gameboy));
+        assert!(rewind.rewind(&mut gameboy));
+        assert_eq!(gameboy.read_byte(0xC100), counter - 2);
+        for _ in 0..4 {
+            rewind.rewind(&mut gameboy);
+        }
+        assert_eq!(gameboy.read_byte(0xC100), counter - 6);
+        assert!(!rewind.rewind(&mut gameboy));
+        assert!(!rewind.rewind(&mut gameboy));
+        assert_eq!(rewind.len(), 1);
+
+        // running on from a rewound state carries on from there
+        gameboy.run_frame().unwrap();
+        assert_eq!(gameboy.read_byte(0xC100), counter - 5);
+    }
+
+    #[test]
+    fn test_budget() {
+        let mut gameboy = gameboy_counting_frames();
+        let state_size = save_state::save(&gameboy).len();
+        let mut rewind = Rewind::new(Settings {
+            interval: 1,
+            budget: state_size + 200,
+            speed: 1.0,
+        });
+        for _ in 0..20 {
+            gameboy.run_frame().unwrap();
+            rewind.frame(&gameboy);
+        }
+        assert!(rewind.used() <= state_size + 200);
+        assert!(rewind.len() > 1 && rewind.len() < 20);
+    }
+}
@@ -0,0 +1,218 @@
+//! Snapshots of the whole machine as bytes, from which emulation can carry on
+//! exactly where it was. The cartridge ROM isn't included, and neither is
+//! debugging state such as breakpoints, or the joypad, which follows the keys
+//! actually held.
+
+use crate::call_stack::CallStack;
+use crate::cpu::FlagsRegister;
+use crate::gameboy::Gameboy;
+use crate::ppu::{SCREEN_HEIGHT, SCREEN_WIDTH};
+use crate::scheduler::{EventKind, Scheduler};
+
+const MAGIC: &[u8; 4] = b"GBSS";
+const VERSION: u8 = 1;
+
+/// Scheduler events are stored as their index in here.
+const EVENT_KINDS: [EventKind; 8] = [
+    EventKind::OamScan,
+    EventKind::Drawing,
+    EventKind::HBlank,
+    EventKind::LineEnd,
+    EventKind::DividerTick,
+    EventKind::TimerTick,
+    EventKind::SerialTransfer,
+    EventKind::FrameSequencer,
+];
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LoadError {
+    /// Not a save state, or one from an incompatible version.
+    BadHeader,
+    Truncated,
+    UnknownEvent(u8),
+}
+
+impl std::fmt::Display for LoadError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LoadError::BadHeader => write!(f, "not a version {} save state", VERSION),
+            LoadError::Truncated => write!(f, "save state is truncated"),
+            LoadError::UnknownEvent(kind) => write!(f, "unknown scheduler event {}", kind),
+        }
+    }
+}
+
+impl std::error::Error for LoadError {}
+
+pub fn save(gameboy: &Gameboy) -> Vec<u8> {
+    let registers = &gameboy.cpu.registers;
+    let mut out = Vec::with_capacity(0x10000 + SCREEN_WIDTH * SCREEN_HEIGHT * 4 + 128);
+    out.extend_from_slice(MAGIC);
+    out.push(VERSION);
+    out.extend_from_slice(&[
+        registers.a,
+        registers.b,
+        registers.c,
+        registers.d,
+        registers.e,
+        registers.f.into(),
+        registers.h,
+        registers.l,
+    ]);
+    out.extend_from_slice(&registers.sp.to_le_bytes());
+    out.extend_from_slice(&registers.pc.to_le_bytes());
+    out.extend_from_slice(&[
+        gameboy.cpu.halted as u8,
+        gameboy.cpu.locked as u8,
+        gameboy.interrupts_enabled as u8,
+        gameboy.bus.boot_rom_enabled as u8,
+        gameboy.instruction_ticks,
+        gameboy.frame_sequencer_step,
+        gameboy.ppu.frame_ready as u8,
+    ]);
+    out.extend_from_slice(&gameboy.divider_reset_at.to_le_bytes());
+    out.extend_from_slice(&gameboy.scheduler.now().to_le_bytes());
+    let events = gameboy.scheduler.pending();
+    out.push(events.len() as u8);
+    for (at, kind) in events {
+        out.push(EVENT_KINDS.iter().position(|&other| other == kind).unwrap() as u8);
+        out.extend_from_slice(&at.to_le_bytes());
+    }
+    out.extend_from_slice(&gameboy.bus.memory);
+    for pixel in gameboy.ppu.framebuffer() {
+        out.extend_from_slice(&pixel.to_le_bytes());
+    }
+    out
+}
+
+struct Reader<'b> {
+    bytes: &'b [u8],
+}
+
+impl<'b> Reader<'b> {
+    fn take(&mut self, count: usize) -> Result<&'b [u8], LoadError> {
+        if self.bytes.len() < count {
+            return Err(LoadError::Truncated);
+        }
+        let (taken, rest) = self.bytes.split_at(count);
+        self.bytes = rest;
+        Ok(taken)
+    }
+
+    fn u8(&mut self) -> Result<u8, LoadError> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn bool(&mut self) -> Result<bool, LoadError> {
+        Ok(self.u8()? != 0)
+    }
+
+    fn u16(&mut self) -> Result<u16, LoadError> {
+        Ok(u16::from_le_bytes(self.take(2)?.try_into().unwrap()))
+    }
+
+    fn u64(&mut self) -> Result<u64, LoadError> {
+        Ok(u64::from_le_bytes(self.take(8)?.try_into().unwrap()))
+    }
+}
+
+/// Restores a state made by `save`, leaving `gameboy` untouched if it can't
+/// be read. The call stack starts over empty.
+pub fn load(gameboy: &mut Gameboy, state: &[u8]) -> Result<(), LoadError> {
+    let mut reader = Reader { bytes: state };
+    if reader.take(4).ok() != Some(MAGIC) || reader.u8().ok() != Some(VERSION) {
+        return Err(LoadError::BadHeader);
+    }
+    let registers = reader.take(8)?;
+    let sp = reader.u16()?;
+    let pc = reader.u16()?;
+    let halted = reader.bool()?;
+    let locked = reader.bool()?;
+    let interrupts_enabled = reader.bool()?;
+    let boot_rom_enabled = reader.bool()?;
+    let instruction_ticks = reader.u8()?;
+    let frame_sequencer_step = reader.u8()?;
+    let frame_ready = reader.bool()?;
+    let divider_reset_at = reader.u64()?;
+    let mut scheduler = Scheduler::default();
+    scheduler.advance(reader.u64()?);
+    for _ in 0..reader.u8()? {
+        let index = reader.u8()?;
+        let kind = *EVENT_KINDS
+            .get(index as usize)
+            .ok_or(LoadError::UnknownEvent(index))?;
+        scheduler.schedule(reader.u64()?, kind);
+    }
+    let memory = reader.take(0x10000)?;
+    let framebuffer = reader.take(SCREEN_WIDTH * SCREEN_HEIGHT * 4)?;
+
+    let cpu_registers = &mut gameboy.cpu.registers;
+    cpu_registers.a = registers[0];
+    cpu_registers.b = registers[1];
+    cpu_registers.c = registers[2];
+    cpu_registers.d = registers[3];
+    cpu_registers.e = registers[4];
+    cpu_registers.f = FlagsRegister::from(registers[5]);
+    cpu_registers.h = registers[6];
+    cpu_registers.l = registers[7];
+    cpu_registers.sp = sp;
+    cpu_registers.pc = pc;
+    gameboy.cpu.halted = halted;
+    gameboy.cpu.locked = locked;
+    gameboy.interrupts_enabled = interrupts_enabled;
+    gameboy.bus.boot_rom_enabled = boot_rom_enabled;
+    gameboy.instruction_ticks = instruction_ticks;
+    gameboy.frame_sequencer_step = frame_sequencer_step;
+    gameboy.ppu.frame_ready = frame_ready;
+    gameboy.divider_reset_at = divider_reset_at;
+    gameboy.scheduler = scheduler;
+    gameboy.bus.memory.copy_from_slice(memory);
+    for (pixel, bytes) in gameboy
+        .ppu
+        .framebuffer_mut()
+        .iter_mut()
+        .zip(framebuffer.as_chunks::<4>().0)
+    {
+        *pixel = u32::from_le_bytes(*bytes);
+    }
+    gameboy.call_stack = CallStack::default();
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gameboy::gameboy_with_program;
+
+    #[test]
+    fn test_round_trip() {
+        // LD A,$42; LD [$C100],A; JR -2
+        let mut gameboy = gameboy_with_program(&[0x3E, 0x42, 0xEA, 0x00, 0xC1, 0x18, 0xFE]);
+        gameboy.bus.cartridge_rom = vec![0; 0x8000].leak();
+        let state = save(&gameboy);
+        for _ in 0..3 {
+            gameboy.step().unwrap();
+        }
+        let later = save(&gameboy);
+        assert_ne!(later, state);
+
+        load(&mut gameboy, &state).unwrap();
+        assert_eq!(save(&gameboy), state);
+        assert_eq!(gameboy.read_byte(0xC100), 0x00);
+        for _ in 0..3 {
+            gameboy.step().unwrap();
+        }
+        assert_eq!(save(&gameboy), later);
+    }
+
+    #[test]
+    fn test_bad_states() {
+        let mut gameboy = Gameboy::default();
+        let state = save(&gameboy);
+        assert_eq!(load(&mut gameboy, b"GBSS"), Err(LoadError::BadHeader));
+        assert_eq!(
+            load(&mut gameboy, &state[..state.len() - 1]),
+            Err(LoadError::Truncated)
+        );
+    }
+}
@@ -0,0 +1,111 @@
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+
+/// Something that happens at a known point in time, measured in t-states.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub enum EventKind {
+    /// The PPU starts a visible line by searching OAM (mode 2).
+    OamScan,
+    /// The PPU starts pushing pixels to the LCD (mode 3).
+    Drawing,
+    /// The current line is finished drawing (mode 0).
+    HBlank,
+    /// LY moves on to the next line.
+    LineEnd,
+    /// DIV increments.
+    DividerTick,
+    /// TIMA increments, reloading from TMA when it overflows.
+    TimerTick,
+    /// The byte in SB has been shifted out.
+    SerialTransfer,
+    /// The APU frame sequencer advances one step.
+    FrameSequencer,
+}
+
+/// A queue of events ordered by the t-state at which they fire.
+#[derive(Debug, Default)]
+pub struct Scheduler {
+    now: u64,
+    events: BinaryHeap<Reverse<(u64, EventKind)>>,
+}
+
+impl Scheduler {
+    /// T-states elapsed since power on.
+    pub fn now(&self) -> u64 {
+        self.now
+    }
+
+    pub fn advance(&mut self, ticks: u64) {
+        self.now += ticks;
+    }
+
+    pub fn schedule(&mut self, at: u64, kind: EventKind) {
+        self.events.push(Reverse((at, kind)));
+    }
+
+    pub fn schedule_in(&mut self, ticks: u64, kind: EventKind) {
+        self.schedule(self.now + ticks, kind);
+    }
+
+    pub fn cancel(&mut self, kind: EventKind) {
+        self.events.retain(|Reverse((_, other))| *other != kind);
+    }
+
+    /// When the next event of this kind is due, if any is scheduled.
+    pub fn scheduled_at(&self, kind: EventKind) -> Option<u64> {
+        self.events
+            .iter()
+            .filter(|Reverse((_, other))| *other == kind)
+            .map(|Reverse((at, _))| *at)
+            .min()
+    }
+
+    /// Every scheduled event, earliest first.
+    pub fn pending(&self) -> Vec<(u64, EventKind)> {
+        let mut events: Vec<_> = self.events.iter().map(|Reverse(event)| *event).collect();
+        events.sort();
+        events
+    }
+
+    /// Removes and returns the earliest event that is due by now.
+    pub fn pop_due(&mut self) -> Option<(u64, EventKind)> {
+        match self.events.peek() {
+            Some(Reverse((at, _))) if *at <= self.now => {
+                self.events.pop().map(|Reverse(event)| event)
+            }
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_events_fire_in_order() {
+        let mut scheduler = Scheduler::default();
+        scheduler.schedule(20, EventKind::HBlank);
+        scheduler.schedule(10, EventKind::DividerTick);
+        scheduler.schedule(30, EventKind::TimerTick);
+
+        assert_eq!(scheduler.pop_due(), None);
+        scheduler.advance(25);
+        assert_eq!(scheduler.pop_due(), Some((10, EventKind::DividerTick)));
+        assert_eq!(scheduler.pop_due(), Some((20, EventKind::HBlank)));
+        assert_eq!(scheduler.pop_due(), None);
+    }
+
+    #[test]
+    fn test_cancel() {
+        let mut scheduler = Scheduler::default();
+        scheduler.schedule_in(10, EventKind::TimerTick);
+        scheduler.schedule_in(20, EventKind::SerialTransfer);
+        scheduler.cancel(EventKind::TimerTick);
+
+        assert_eq!(scheduler.scheduled_at(EventKind::TimerTick), None);
+        assert_eq!(scheduler.scheduled_at(EventKind::SerialTransfer), Some(20));
+        scheduler.advance(20);
+        assert_eq!(scheduler.pop_due(), Some((20, EventKind::SerialTransfer)));
+    }
+}
@@ -0,0 +1,134 @@
+extern crate sdl2;
+use std::cell::RefCell;
+
+use sdl2::{pixels::PixelFormatEnum, render::Texture, Sdl};
+
+use crate::ppu::{PPU, SCREEN_HEIGHT, SCREEN_WIDTH};
+
+/// The SDL window the PPU's framebuffer is presented in.
+pub struct Screen {
+    canvas: sdl2::render::Canvas<sdl2::video::Window>,
+    texture_creator: sdl2::render::TextureCreator<sdl2::video::WindowContext>,
+    texture: RefCell<sdl2::render::Texture<'static>>,
+}
+
+impl Screen {
+    pub fn new(sdl_context: &Sdl) -> Screen {
+        let video_subsystem = sdl_context.video().unwrap();
+
+        let window = video_subsystem
+            .window("Rust Gameboy Emulator", 800, 600)
+            .position_centered()
+            .resizable()
+            .opengl()
+            .build()
+            .map_err(|e| e.to_string())
+            .unwrap();
+
+        let canvas = window
+            .into_canvas()
+            .build()
+            .map_err(|e| e.to_string())
+            .unwrap();
+
+        let texture_creator = canvas.texture_creator();
+
+        let texture = texture_creator
+            .create_texture(
+                PixelFormatEnum::ABGR32,
+                sdl2::render::TextureAccess::Streaming,
+                SCREEN_WIDTH as u32,
+                SCREEN_HEIGHT as u32,
+            )
+            .unwrap();
+
+        let texture = unsafe { std::mem::transmute::<_, Texture<'static>>(texture) };
+        Screen {
+            canvas,
+            texture_creator,
+            texture: RefCell::new(texture),
+        }
+    }
+
+    pub fn set_title(&mut self, title: &str) {
+        if let Err(error) = self.canvas.window_mut().set_title(title) {
+            log::warn!("Failed to set window title: {}", error);
+        }
+    }
+
+    pub fn draw(&mut self, ppu: &PPU) {
+        let mut texture = self.texture.borrow_mut();
+        texture
+            .update(None, ppu.data_raw(), SCREEN_WIDTH * 4)
+            .expect("Failed to update texture");
+        self.canvas
+            .copy(&texture, None, None)
+            .expect("Failed to copy texture");
+        self.canvas.present();
+    }
+}
+
+/// A secondary window for debug views, showing an image packed like the
+/// PPU's framebuffer.
+pub struct DebugWindow {
+    canvas: sdl2::render::Canvas<sdl2::video::Window>,
+}
+
+impl DebugWindow {
+    /// Opens a window sized to show a `width`×`height` image at `scale`.
+    pub fn new(
+        sdl_context: &Sdl,
+        title: &str,
+        width: usize,
+        height: usize,
+        scale: u32,
+    ) -> DebugWindow {
+        let window = sdl_context
+            .video()
+            .unwrap()
+            .window(title, width as u32 * scale, height as u32 * scale)
+            .resizable()
+            .build()
+            .unwrap();
+        DebugWindow {
+            canvas: window.into_canvas().build().unwrap(),
+        }
+    }
+
+    pub fn window_id(&self) -> u32 {
+        self.canvas.window().id()
+    }
+
+    /// Maps a point in the window to the `width`×`height` image stretched
+    /// across it.
+    pub fn image_position(
+        &self,
+        (x, y): (i32, i32),
+        width: usize,
+        height: usize,
+    ) -> (usize, usize) {
+        let (window_width, window_height) = self.canvas.window().size();
+        (
+            x.max(0) as usize * width / window_width.max(1) as usize,
+            y.max(0) as usize * height / window_height.max(1) as usize,
+        )
+    }
+
+    pub fn draw(&mut self, pixels: &[u32], width: usize, height: usize) {
+        let bytes: Vec<u8> = pixels
+            .iter()
+            .flat_map(|pixel| pixel.to_ne_bytes())
+            .collect();
+        let texture_creator = self.canvas.texture_creator();
+        let mut texture = texture_creator
+            .create_texture_streaming(PixelFormatEnum::ABGR32, width as u32, height as u32)
+            .unwrap();
+        texture
+            .update(None, &bytes, width * 4)
+            .expect("Failed to update texture");
+        self.canvas
+            .copy(&texture, None, None)
+            .expect("Failed to copy texture");
+        self.canvas.present();
+    }
+}
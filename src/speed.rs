@@ -0,0 +1,187 @@
+//! How fast emulation runs compared to the real hardware, and pausing it
+//! from the keyboard as opposed to the debugger.
+
+use sdl2::keyboard::Keycode;
+
+/// The speeds that slowing down and speeding up step through.
+pub const STEPS: [f64; 6] = [0.25, 0.5, 1.0, 2.0, 4.0, 8.0];
+pub const MIN: f64 = STEPS[0];
+pub const MAX: f64 = STEPS[STEPS.len() - 1];
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Speed {
+    multiplier: f64,
+    /// Toggled fast-forward.
+    pub fast_forward: bool,
+    /// Fast-forward while a key is held.
+    pub fast_forward_held: bool,
+    paused: bool,
+    advance: bool,
+    /// Fractions of a frame carried over at speeds that aren't whole numbers.
+    owed: f64,
+}
+
+impl Default for Speed {
+    fn default() -> Self {
+        Speed::new(1.0)
+    }
+}
+
+impl Speed {
+    /// Runs at `multiplier` times normal speed, clamped to `MIN`..=`MAX`.
+    pub fn new(multiplier: f64) -> Speed {
+        Speed {
+            multiplier: multiplier.clamp(MIN, MAX),
+            fast_forward: false,
+            fast_forward_held: false,
+            paused: false,
+            advance: false,
+            owed: 0.0,
+        }
+    }
+
+    pub fn multiplier(&self) -> f64 {
+        self.multiplier
+    }
+
+    /// Whether frames are run as fast as the host allows.
+    pub fn unthrottled(&self) -> bool {
+        self.fast_forward || self.fast_forward_held
+    }
+
+    pub fn paused(&self) -> bool {
+        self.paused
+    }
+
+    pub fn faster(&mut self) {
+        if let Some(&step) = STEPS.iter().find(|&&step| step > self.multiplier) {
+            self.multiplier = step;
+        }
+    }
+
+    pub fn slower(&mut self) {
+        if let Some(&step) = STEPS.iter().rev().find(|&&step| step < self.multiplier) {
+            self.multiplier = step;
+        }
+    }
+
+    pub fn reset(&mut self) {
+        self.multiplier = 1.0;
+    }
+
+    pub fn toggle_pause(&mut self) {
+        self.paused = !self.paused;
+        self.advance = false;
+    }
+
+    /// Runs a single frame if paused.
+    pub fn advance(&mut self) {
+        self.advance = self.paused;
+    }
+
+    /// Handles a key press, returning false if it isn't one of the speed keys:
+    /// Tab fast-forwards while held and F toggles it, P pauses, N advances a
+    /// frame, - and = step the speed down and up and 0 resets it.
+    pub fn key_down(&mut self, key: Keycode) -> bool {
+        match key {
+            Keycode::Tab => self.fast_forward_held = true,
+            Keycode::F => self.fast_forward = !self.fast_forward,
+            Keycode::P => self.toggle_pause(),
+            Keycode::N => self.advance(),
+            Keycode::Minus => self.slower(),
+            Keycode::Equals => self.faster(),
+            Keycode::Num0 => self.reset(),
+            _ => return false,
+        }
+        true
+    }
+
+    pub fn key_up(&mut self, key: Keycode) -> bool {
+        if key == Keycode::Tab {
+            self.fast_forward_held = false;
+        }
+        key == Keycode::Tab
+    }
+
+    /// How many frames to run in the next 1/60th of a second, or `None` for
+    /// as many as fit in it.
+    pub fn frames_due(&mut self) -> Option<u32> {
+        if self.paused {
+            return Some(std::mem::take(&mut self.advance) as u32);
+        }
+        if self.unthrottled() {
+            return None;
+        }
+        self.owed += self.multiplier;
+        let frames = self.owed.floor();
+        self.owed -= frames;
+        Some(frames as u32)
+    }
+
+    /// What the window title shows, or `None` at normal speed.
+    pub fn status(&self) -> Option<String> {
+        if self.paused {
+            Some("Paused".to_string())
+        } else if self.unthrottled() {
+            Some("Fast-forward".to_string())
+        } else if self.multiplier != 1.0 {
+            Some(format!("{}x", self.multiplier))
+        } else {
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_frames_due() {
+        let mut speed = Speed::new(0.25);
+        let frames: Vec<_> = (0..8).map(|_| speed.frames_due()).collect();
+        assert_eq!(frames, [0, 0, 0, 1, 0, 0, 0, 1].map(Some));
+
+        speed.faster();
+        speed.faster();
+        speed.faster();
+        assert_eq!(speed.status().unwrap(), "2x");
+        assert_eq!(speed.frames_due(), Some(2));
+
+        speed.fast_forward_held = true;
+        assert_eq!(speed.frames_due(), None);
+        assert_eq!(speed.status().unwrap(), "Fast-forward");
+    }
+
+    #[test]
+    fn test_pause_and_advance() {
+        let mut speed = Speed::default();
+        speed.advance();
+        assert_eq!(speed.frames_due(), Some(1));
+
+        speed.toggle_pause();
+        assert_eq!(speed.frames_due(), Some(0));
+        speed.advance();
+        assert_eq!(speed.frames_due(), Some(1));
+        assert_eq!(speed.frames_due(), Some(0));
+
+        speed.toggle_pause();
+        assert_eq!(speed.frames_due(), Some(1));
+        assert_eq!(speed.status(), None);
+    }
+
+    #[test]
+    fn test_steps() {
+        let mut speed = Speed::new(100.0);
+        assert_eq!(speed.multiplier(), MAX);
+        speed.faster();
+        assert_eq!(speed.multiplier(), MAX);
+        let mut speed = Speed::new(3.0);
+        speed.slower();
+        assert_eq!(speed.multiplier(), 2.0);
+        for _ in 0..10 {
+            speed.slower();
+        }
+        assert_eq!(speed.multiplier(), MIN);
+    }
+}
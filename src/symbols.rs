@@ -0,0 +1,169 @@
+//! Labels from the `.sym` files RGBDS writes next to a ROM, one `bank:addr name`
+//! per line.
+
+use std::collections::{BTreeMap, HashMap};
+use std::path::Path;
+
+/// How far past a label an address may be and still be shown relative to it.
+const MAX_OFFSET: u16 = 0x1000;
+
+#[derive(Debug, Default, Clone)]
+pub struct Symbols {
+    /// ROM labels, by bank and address.
+    by_location: BTreeMap<(u8, u16), String>,
+    /// Labels everywhere else, by address alone since nothing else is banked yet.
+    by_address: BTreeMap<u16, String>,
+    by_name: HashMap<String, (u8, u16)>,
+}
+
+impl Symbols {
+    pub fn parse(text: &str) -> Symbols {
+        let mut symbols = Symbols::default();
+        for line in text.lines() {
+            let line = line.split(';').next().unwrap_or_default().trim();
+            if line.is_empty() {
+                continue;
+            }
+            let parsed = line.split_once(' ').and_then(|(location, name)| {
+                let (bank, address) = location.split_once(':')?;
+                Some((
+                    u8::from_str_radix(bank, 16).ok()?,
+                    u16::from_str_radix(address, 16).ok()?,
+                    name.trim(),
+                ))
+            });
+            match parsed {
+                Some((bank, address, name)) => symbols.insert(bank, address, name),
+                None => log::warn!("Ignoring malformed symbol: {}", line),
+            }
+        }
+        symbols
+    }
+
+    /// Loads the `.sym` file next to `rom`, if there is one.
+    pub fn for_rom(rom: impl AsRef<Path>) -> Option<Symbols> {
+        let text = std::fs::read_to_string(rom.as_ref().with_extension("sym")).ok()?;
+        Some(Symbols::parse(&text))
+    }
+
+    pub fn insert(&mut self, bank: u8, address: u16, name: &str) {
+        // keep the first label when several share an address
+        if address < 0x8000 {
+            self.by_location
+                .entry((bank, address))
+                .or_insert_with(|| name.to_string());
+        } else {
+            self.by_address
+                .entry(address)
+                .or_insert_with(|| name.to_string());
+        }
+        self.by_name.insert(name.to_string(), (bank, address));
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.by_name.is_empty()
+    }
+
+    /// The bank and address of a label.
+    pub fn lookup(&self, name: &str) -> Option<(u8, u16)> {
+        self.by_name.get(name).copied()
+    }
+
+    /// The label at exactly this location. Banks are only compared for ROM,
+    /// since nothing else is banked yet.
+    pub fn name(&self, bank: u8, address: u16) -> Option<&str> {
+        let name = if address < 0x8000 {
+            self.by_location.get(&(bank, address))
+        } else {
+            self.by_address.get(&address)
+        };
+        name.map(String::as_str)
+    }
+
+    /// Looks up labels for code running with `romx_bank` mapped at 0x4000, in
+    /// the shape the disassembler wants.
+    pub fn labeler(&self, romx_bank: u8) -> impl Fn(u16) -> Option<String> + '_ {
+        move |address| {
+            let bank = if address < 0x4000 { 0 } else { romx_bank };
+            self.name(bank, address).map(str::to_string)
+        }
+    }
+
+    /// The closest label at or before `address`, in the same bank for ROM, and
+    /// how far past it `address` is.
+    pub fn containing(&self, bank: u8, address: u16) -> Option<(&str, u16)> {
+        if let Some(name) = self.name(bank, address) {
+            return Some((name, 0));
+        }
+        let preceding = if address < 0x8000 {
+            self.by_location
+                .range(..(bank, address))
+                .next_back()
+                .filter(|((other_bank, _), _)| *other_bank == bank)
+                .map(|((_, start), name)| (*start, name))
+        } else {
+            self.by_address
+                .range(..address)
+                .next_back()
+                .map(|(start, name)| (*start, name))
+        };
+        match preceding {
+            Some((start, name)) if address - start < MAX_OFFSET => {
+                Some((name.as_str(), address - start))
+            }
+            _ => None,
+        }
+    }
+
+    /// Names an address as `label` or `label+$offset` relative to the closest
+    /// label before it in the same bank, falling back to hex.
+    pub fn describe(&self, bank: u8, address: u16) -> String {
+        match self.containing(bank, address) {
+            Some((name, 0)) => name.to_string(),
+            Some((name, offset)) => format!("{}+${:X}", name, offset),
+            None => format!("${:04X}", address),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SYM: &str = "\
+; File generated by rgblink
+00:0150 Start
+00:0158 Start.loop
+01:4000 Bank1Routine
+02:4000 Bank2Routine
+00:c000 wBuffer
+01:d000 wBankedBuffer
+bad line
+";
+
+    #[test]
+    fn test_parse_and_lookup() {
+        let symbols = Symbols::parse(SYM);
+        assert_eq!(symbols.lookup("Start.loop"), Some((0, 0x0158)));
+        assert_eq!(symbols.name(2, 0x4000), Some("Bank2Routine"));
+        assert_eq!(symbols.name(3, 0x4000), None);
+        assert_eq!(symbols.name(1, 0xC000), Some("wBuffer"));
+        assert_eq!(symbols.name(0, 0xD000), Some("wBankedBuffer"));
+    }
+
+    #[test]
+    fn test_describe() {
+        let symbols = Symbols::parse(SYM);
+        assert_eq!(symbols.describe(0, 0x0150), "Start");
+        assert_eq!(symbols.describe(0, 0x015A), "Start.loop+$2");
+        assert_eq!(symbols.describe(1, 0x4010), "Bank1Routine+$10");
+        assert_eq!(symbols.describe(3, 0x4010), "$4010");
+        assert_eq!(symbols.describe(0, 0x0100), "$0100");
+        assert_eq!(symbols.describe(0, 0xC010), "wBuffer+$10");
+        assert_eq!(symbols.describe(0, 0xD805), "wBankedBuffer+$805");
+        assert_eq!(symbols.describe(1, 0xE000), "$E000");
+        let label = symbols.labeler(2);
+        assert_eq!(label(0x4000).as_deref(), Some("Bank2Routine"));
+        assert_eq!(label(0x0150).as_deref(), Some("Start"));
+    }
+}
@@ -0,0 +1,87 @@
+//! Instruction traces in the gameboy-doctor format, one line per instruction
+//! with the CPU state before it runs, so runs can be diffed against logs from
+//! reference emulators.
+
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+use crate::gameboy::Gameboy;
+
+/// What gameboy-doctor logs expect LY to read as, since they're recorded with
+/// the LCD stubbed out.
+pub const DOCTOR_LY: u8 = 0x90;
+
+/// Formats the state before the instruction at PC, e.g.
+/// `A:01 F:B0 B:00 C:13 D:00 E:D8 H:01 L:4D SP:FFFE PC:0100 PCMEM:00,C3,13,02`.
+pub fn line(gameboy: &Gameboy) -> String {
+    let registers = &gameboy.cpu.registers;
+    let pc = registers.pc;
+    let pcmem = (0..4)
+        .map(|offset| format!("{:02X}", gameboy.read_byte(pc.wrapping_add(offset))))
+        .collect::<Vec<_>>()
+        .join(",");
+    format!(
+        "A:{:02X} F:{:02X} B:{:02X} C:{:02X} D:{:02X} E:{:02X} H:{:02X} L:{:02X} SP:{:04X} PC:{:04X} PCMEM:{}",
+        registers.a,
+        u8::from(registers.f),
+        registers.b,
+        registers.c,
+        registers.d,
+        registers.e,
+        registers.h,
+        registers.l,
+        registers.sp,
+        pc,
+        pcmem
+    )
+}
+
+/// Where trace lines go.
+pub struct Tracer {
+    out: Box<dyn Write>,
+}
+
+impl Tracer {
+    pub fn new(out: impl Write + 'static) -> Tracer {
+        Tracer { out: Box::new(out) }
+    }
+
+    pub fn create(path: impl AsRef<Path>) -> std::io::Result<Tracer> {
+        Ok(Tracer::new(BufWriter::new(File::create(path)?)))
+    }
+
+    pub(crate) fn record(&mut self, line: &str) -> std::io::Result<()> {
+        writeln!(self.out, "{}", line)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gameboy;
+
+    #[test]
+    fn test_line_after_boot() {
+        let mut gameboy = Gameboy::default();
+        gameboy::initialize(&mut gameboy);
+        gameboy::skip_boot_rom(&mut gameboy);
+        let cartridge = vec![0; 0x8000].leak();
+        cartridge[0x100..0x104].copy_from_slice(&[0x00, 0xC3, 0x13, 0x02]);
+        gameboy.bus.cartridge_rom = cartridge;
+
+        assert_eq!(
+            line(&gameboy),
+            "A:01 F:B0 B:00 C:13 D:00 E:D8 H:01 L:4D SP:FFFE PC:0100 PCMEM:00,C3,13,02"
+        );
+    }
+
+    #[test]
+    fn test_force_ly() {
+        let mut gameboy = Gameboy::default();
+        gameboy.bus.memory[0xFF44] = 0x12;
+        assert_eq!(gameboy.read_byte(0xFF44), 0x12);
+        gameboy.force_ly = Some(DOCTOR_LY);
+        assert_eq!(gameboy.read_byte(0xFF44), 0x90);
+    }
+}
@@ -0,0 +1,135 @@
+//! Debug views shown in their own windows, toggled with function keys while
+//! the game runs.
+
+use sdl2::event::Event;
+use sdl2::keyboard::Keycode;
+use sdl2::Sdl;
+
+use crate::gameboy::Gameboy;
+use crate::io_viewer;
+use crate::memory_viewer::{self, MemoryViewer};
+use crate::oam_viewer;
+use crate::screen::DebugWindow;
+use crate::vram_viewer;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum View {
+    Vram,
+    Oam,
+    Memory,
+    Io,
+}
+
+impl View {
+    pub const ALL: [View; 4] = [View::Vram, View::Oam, View::Memory, View::Io];
+
+    pub fn key(self) -> Keycode {
+        match self {
+            View::Vram => Keycode::F1,
+            View::Oam => Keycode::F2,
+            View::Memory => Keycode::F3,
+            View::Io => Keycode::F4,
+        }
+    }
+
+    pub fn for_key(key: Keycode) -> Option<View> {
+        View::ALL.into_iter().find(|view| view.key() == key)
+    }
+
+    fn title(self) -> &'static str {
+        match self {
+            View::Vram => "VRAM",
+            View::Oam => "OAM",
+            View::Memory => "Memory",
+            View::Io => "I/O registers",
+        }
+    }
+
+    fn size(self) -> (usize, usize) {
+        match self {
+            View::Vram => (vram_viewer::WIDTH, vram_viewer::HEIGHT),
+            View::Oam => (oam_viewer::WIDTH, oam_viewer::HEIGHT),
+            View::Memory => (memory_viewer::WIDTH, memory_viewer::HEIGHT),
+            View::Io => (io_viewer::WIDTH, io_viewer::HEIGHT),
+        }
+    }
+
+    fn scale(self) -> u32 {
+        match self {
+            View::Vram | View::Io => 2,
+            View::Oam | View::Memory => 3,
+        }
+    }
+}
+
+/// The views currently open, and the state of those that keep some.
+#[derive(Default)]
+pub struct Views {
+    open: Vec<(View, DebugWindow)>,
+    memory: MemoryViewer,
+}
+
+impl Views {
+    pub fn toggle(&mut self, sdl_context: &Sdl, view: View) {
+        let count = self.open.len();
+        self.open.retain(|(other, _)| *other != view);
+        if self.open.len() == count {
+            let (width, height) = view.size();
+            let window = DebugWindow::new(sdl_context, view.title(), width, height, view.scale());
+            self.open.push((view, window));
+        }
+    }
+
+    /// Closes the view shown in `window_id`, returning whether there was one.
+    pub fn close(&mut self, window_id: u32) -> bool {
+        let count = self.open.len();
+        self.open
+            .retain(|(_, window)| window.window_id() != window_id);
+        self.open.len() != count
+    }
+
+    /// Passes input aimed at the memory view's window to it, returning
+    /// whether the event was used.
+    pub fn handle(&mut self, event: &Event, gameboy: &mut Gameboy) -> bool {
+        let Some((_, window)) = self.open.iter().find(|(view, _)| *view == View::Memory) else {
+            return false;
+        };
+        let id = window.window_id();
+        match *event {
+            Event::KeyDown {
+                window_id,
+                keycode: Some(key),
+                ..
+            } if window_id == id => self.memory.key(gameboy, key),
+            Event::MouseButtonDown {
+                window_id, x, y, ..
+            } if window_id == id => {
+                let (width, height) = View::Memory.size();
+                self.memory
+                    .click(window.image_position((x, y), width, height));
+                true
+            }
+            Event::MouseWheel { window_id, y, .. } if window_id == id => {
+                self.memory.scroll(-3 * y);
+                true
+            }
+            _ => false,
+        }
+    }
+
+    pub fn draw(&mut self, gameboy: &Gameboy) {
+        for (view, window) in &mut self.open {
+            let pixels = match view {
+                View::Vram => vram_viewer::render(&gameboy.bus),
+                View::Oam => oam_viewer::render(&gameboy.bus),
+                View::Io => io_viewer::render(gameboy),
+                View::Memory => {
+                    self.memory.update(gameboy);
+                    self.memory.render(gameboy)
+                }
+            };
+            let (width, height) = view.size();
+            window.draw(&pixels, width, height);
+        }
+    }
+}
@@ -0,0 +1,150 @@
+//! A view of what's in VRAM: the 384 tiles at 0x8000-0x97FF next to both
+//! tilemaps, with the area the screen and the window show outlined.
+
+use sdl2::pixels::Color;
+
+use crate::memory::special_addresses::{BGP, LCDC, SCX, SCY, WX, WY};
+use crate::memory::MemoryBus;
+use crate::ppu::{pack, shade, tile_address, tile_row_colors, SCREEN_HEIGHT, SCREEN_WIDTH};
+
+const TILES_WIDE: usize = 16;
+const TILE_COUNT: usize = 384;
+const MAP_SIZE: usize = 256;
+const GAP: usize = 8;
+
+pub const WIDTH: usize = TILES_WIDE * 8 + GAP + MAP_SIZE + GAP + MAP_SIZE;
+pub const HEIGHT: usize = MAP_SIZE;
+
+const BACKGROUND: Color = Color::RGB(0x40, 0x40, 0x40);
+const VIEWPORT: Color = Color::RGB(0xFF, 0x00, 0x00);
+const WINDOW: Color = Color::RGB(0x00, 0x80, 0xFF);
+
+/// Where a tilemap's left edge is in the rendered image.
+fn map_left(map: usize) -> usize {
+    TILES_WIDE * 8 + GAP + map * (MAP_SIZE + GAP)
+}
+
+fn draw_tile(pixels: &mut [u32], bus: &MemoryBus, tile_location: u16, left: usize, top: usize) {
+    let palette = bus.read(BGP as u16);
+    for row in 0..8 {
+        for (x, color_num) in tile_row_colors(bus, tile_location, row)
+            .into_iter()
+            .enumerate()
+        {
+            pixels[(top + row as usize) * WIDTH + left + x] = pack(shade(color_num, palette));
+        }
+    }
+}
+
+/// Outlines a `width`×`height` area of a tilemap starting at `x`, `y`,
+/// wrapping around its edges like the background does.
+fn outline(
+    pixels: &mut [u32],
+    map: usize,
+    (x, y): (usize, usize),
+    (width, height): (usize, usize),
+    color: Color,
+) {
+    let mut plot = |dx: usize, dy: usize| {
+        let (px, py) = ((x + dx) % MAP_SIZE, (y + dy) % MAP_SIZE);
+        pixels[py * WIDTH + map_left(map) + px] = pack(color);
+    };
+    for dx in 0..width {
+        plot(dx, 0);
+        plot(dx, height - 1);
+    }
+    for dy in 0..height {
+        plot(0, dy);
+        plot(width - 1, dy);
+    }
+}
+
+/// Renders the tiles and both tilemaps with the current BGP into a
+/// `WIDTH`×`HEIGHT` image packed like the PPU's framebuffer.
+pub fn render(bus: &MemoryBus) -> Vec<u32> {
+    let mut pixels = vec![pack(BACKGROUND); WIDTH * HEIGHT];
+
+    for tile in 0..TILE_COUNT {
+        let (left, top) = (tile % TILES_WIDE * 8, tile / TILES_WIDE * 8);
+        draw_tile(&mut pixels, bus, 0x8000 + tile as u16 * 16, left, top);
+    }
+
+    let control = bus.read(LCDC as u16);
+    let (tiledata, unsigned) = if control & 0x10 != 0 {
+        (0x8000, true)
+    } else {
+        (0x8800, false)
+    };
+    for (map, base) in [0x9800u16, 0x9C00].into_iter().enumerate() {
+        for index in 0..32 * 32 {
+            let tile_num = bus.read(base + index as u16);
+            let (left, top) = (map_left(map) + index % 32 * 8, index / 32 * 8);
+            draw_tile(
+                &mut pixels,
+                bus,
+                tile_address(tiledata, unsigned, tile_num),
+                left,
+                top,
+            );
+        }
+    }
+
+    let background_map = (control >> 3 & 1) as usize;
+    let scroll = (bus.read(SCX as u16) as usize, bus.read(SCY as u16) as usize);
+    outline(
+        &mut pixels,
+        background_map,
+        scroll,
+        (SCREEN_WIDTH, SCREEN_HEIGHT),
+        VIEWPORT,
+    );
+
+    let (window_x, window_y) = (bus.read(WX as u16) as usize, bus.read(WY as u16) as usize);
+    if control & 0x20 != 0 && window_x < SCREEN_WIDTH + 7 && window_y < SCREEN_HEIGHT {
+        let window_map = (control >> 6 & 1) as usize;
+        // the window always shows its map from the top left corner
+        let size = (SCREEN_WIDTH + 7 - window_x.max(7), SCREEN_HEIGHT - window_y);
+        outline(&mut pixels, window_map, (0, 0), size, WINDOW);
+    }
+    pixels
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pixel(pixels: &[u32], x: usize, y: usize) -> u32 {
+        pixels[y * WIDTH + x]
+    }
+
+    #[test]
+    fn test_tiles_and_maps() {
+        let mut bus = MemoryBus::default();
+        bus.memory[BGP] = 0xE4;
+        bus.memory[LCDC] = 0x91;
+        // tile 1: first row all color 3
+        bus.memory[0x8010] = 0xFF;
+        bus.memory[0x8011] = 0xFF;
+        bus.memory[0x9C00 + 33] = 1;
+        let pixels = render(&bus);
+
+        assert_eq!(pixel(&pixels, 8, 0), pack(Color::BLACK));
+        assert_eq!(pixel(&pixels, 8, 1), pack(Color::WHITE));
+        assert_eq!(pixel(&pixels, map_left(1) + 8, 8), pack(Color::BLACK));
+        assert_eq!(pixel(&pixels, map_left(0) + 8, 8), pack(Color::WHITE));
+    }
+
+    #[test]
+    fn test_viewport_wraps() {
+        let mut bus = MemoryBus::default();
+        bus.memory[LCDC] = 0x91;
+        bus.memory[SCX] = 200;
+        bus.memory[SCY] = 10;
+        let pixels = render(&bus);
+
+        assert_eq!(pixel(&pixels, map_left(0) + 200, 10), pack(VIEWPORT));
+        // the right edge wraps around to x = (200 + 159) % 256
+        assert_eq!(pixel(&pixels, map_left(0) + 103, 50), pack(VIEWPORT));
+        assert_ne!(pixel(&pixels, map_left(0) + 104, 50), pack(VIEWPORT));
+    }
+}
@@ -0,0 +1,52 @@
+//! Blargg's test ROMs, which report "Passed" or "Failed" over the serial port.
+
+mod common;
+
+use std::path::Path;
+
+/// The slowest of cpu_instrs needs around 30 emulated seconds.
+const TIMEOUT: u64 = 60 * common::SECOND;
+
+fn run_blargg(path: &Path) -> Result<(), String> {
+    let mut gameboy = common::headless(path);
+    gameboy.serial_output = Some(Vec::new());
+
+    let mut checked = 0;
+    let result = common::run_until(&mut gameboy, TIMEOUT, |gameboy| {
+        let output = gameboy.serial_output.as_deref().unwrap_or_default();
+        // only look again once something new has arrived
+        if output.len() == checked {
+            return false;
+        }
+        checked = output.len();
+        let output = String::from_utf8_lossy(output);
+        output.contains("Passed") || output.contains("Failed")
+    });
+
+    let output = String::from_utf8_lossy(gameboy.serial_output.as_deref().unwrap_or_default());
+    match result {
+        Ok(()) if output.contains("Passed") => Ok(()),
+        Ok(()) => Err(output.trim().to_string()),
+        Err(error) => Err(format!("{}, output: {:?}", error, output)),
+    }
+}
+
+#[test]
+fn cpu_instrs() {
+    common::run_suite("blargg/cpu_instrs/individual", run_blargg);
+}
+
+#[test]
+fn instr_timing() {
+    common::run_suite("blargg/instr_timing", run_blargg);
+}
+
+#[test]
+fn mem_timing() {
+    common::run_suite("blargg/mem_timing/individual", run_blargg);
+}
+
+#[test]
+fn halt_bug() {
+    common::run_suite("blargg/halt_bug.gb", run_blargg);
+}
@@ -0,0 +1,101 @@
+//! Shared setup for the test ROM suites. The ROMs aren't checked in: they're
+//! read from `$TEST_ROMS_DIR`, or `test-roms/` in the crate root, laid out like
+//! the gameboy-test-roms release. Suites whose ROMs are missing are skipped,
+//! unless `$TEST_ROMS_DIR` is set, in which case they fail.
+
+#![allow(dead_code)]
+
+use std::path::{Path, PathBuf};
+
+use rust_game_boy_emulator::gameboy::{self, Gameboy, Timing};
+
+/// One second of emulated time in t-states.
+pub const SECOND: u64 = 4_194_304;
+
+pub fn suite_dir(suite: &str) -> PathBuf {
+    std::env::var_os("TEST_ROMS_DIR")
+        .map(PathBuf::from)
+        .unwrap_or_else(|| Path::new(env!("CARGO_MANIFEST_DIR")).join("test-roms"))
+        .join(suite)
+}
+
+/// The ROMs of a suite, given as a directory (searched recursively) or a
+/// single file relative to the test ROM directory. `None` when they aren't
+/// there.
+pub fn roms(suite: &str) -> Option<Vec<PathBuf>> {
+    let path = suite_dir(suite);
+    if path.is_file() {
+        return Some(vec![path]);
+    }
+    let mut roms = Vec::new();
+    collect_roms(&path, &mut roms);
+    roms.sort();
+    (!roms.is_empty()).then_some(roms)
+}
+
+fn collect_roms(dir: &Path, roms: &mut Vec<PathBuf>) {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return;
+    };
+    for path in entries.filter_map(|entry| entry.ok().map(|entry| entry.path())) {
+        if path.is_dir() {
+            collect_roms(&path, roms);
+        } else if path.extension().is_some_and(|extension| extension == "gb") {
+            roms.push(path);
+        }
+    }
+}
+
+/// Loads a ROM with the boot ROM skipped, ready to `step` without a frontend.
+/// Memory accesses happen on the M-cycle they do on hardware, as the timing
+/// suites expect.
+pub fn headless(path: &Path) -> Gameboy<'static> {
+    let rom = std::fs::read(path)
+        .unwrap_or_else(|error| panic!("Error reading {}: {}", path.display(), error));
+    let mut gameboy = Gameboy::default();
+    gameboy::initialize(&mut gameboy);
+    gameboy::skip_boot_rom(&mut gameboy);
+    gameboy.timing = Timing::MCycle;
+    gameboy.bus.cartridge_rom = rom.leak();
+    gameboy
+}
+
+/// Steps until `done` holds, failing if emulation stops or `timeout` t-states
+/// pass first.
+pub fn run_until(
+    gameboy: &mut Gameboy,
+    timeout: u64,
+    mut done: impl FnMut(&Gameboy) -> bool,
+) -> Result<(), String> {
+    let start = gameboy.scheduler.now();
+    while !done(gameboy) {
+        if gameboy.scheduler.now() - start > timeout {
+            return Err(format!("timed out after {} t-states", timeout));
+        }
+        gameboy.step().map_err(|error| error.to_string())?;
+    }
+    Ok(())
+}
+
+/// Runs every ROM of a suite through `run`, reporting all failures at once.
+pub fn run_suite(suite: &str, run: impl Fn(&Path) -> Result<(), String>) {
+    let Some(roms) = roms(suite) else {
+        if std::env::var_os("TEST_ROMS_DIR").is_some() {
+            panic!("No ROMs for {} in {}", suite, suite_dir(suite).display());
+        }
+        eprintln!(
+            "Skipping {}: ROMs not found, set TEST_ROMS_DIR to run it",
+            suite
+        );
+        return;
+    };
+    let failures: Vec<String> = roms
+        .iter()
+        .filter_map(|rom| {
+            run(rom)
+                .err()
+                .map(|error| format!("{}: {}", rom.display(), error))
+        })
+        .collect();
+    assert!(failures.is_empty(), "\n{}", failures.join("\n"));
+}
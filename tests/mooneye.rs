@@ -0,0 +1,95 @@
+//! Mooneye's acceptance tests, which finish with `LD B,B` after loading the
+//! Fibonacci numbers 3/5/8/13/21/34 into B/C/D/E/H/L on success, or 0x42 into
+//! all of them on failure.
+
+mod common;
+
+use std::path::Path;
+
+use rust_game_boy_emulator::cpu::Registers;
+
+const TIMEOUT: u64 = 20 * common::SECOND;
+
+const SUITE: &str = "mooneye-test-suite/acceptance";
+
+/// ROMs known to fail, relative to the suite directory. Entries that start
+/// passing are reported so they can be dropped from the list.
+const EXPECTED_FAILURES: &[&str] = &[
+    "boot_div-dmgABCmgb.gb",
+    "boot_hwio-dmgABCmgb.gb",
+    "oam_dma_restart.gb",
+    "oam_dma_start.gb",
+    "oam_dma_timing.gb",
+    "ppu/hblank_ly_scx_timing-GS.gb",
+    "ppu/intr_2_0_timing.gb",
+    "ppu/intr_2_mode0_timing.gb",
+    "ppu/intr_2_mode0_timing_sprites.gb",
+    "ppu/intr_2_mode3_timing.gb",
+    "ppu/intr_2_oam_ok_timing.gb",
+    "ppu/lcdon_timing-GS.gb",
+    "ppu/lcdon_write_timing-GS.gb",
+    "ppu/stat_irq_blocking.gb",
+    "ppu/stat_lyc_onoff.gb",
+    "ppu/vblank_stat_intr-GS.gb",
+];
+
+/// Whether a ROM is meant to pass on a DMG, judging by the model suffix in its
+/// name: `-GS` and `-dmgABC...` do, other suffixes like `-mgb`, `-sgb`,
+/// `-cgb` or `-S` target other models.
+fn runs_on_dmg(path: &Path) -> bool {
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+    match stem.rsplit_once('-') {
+        Some((_, models)) => models.contains("dmgABC") || models.contains('G'),
+        None => true,
+    }
+}
+
+fn passed(registers: &Registers) -> bool {
+    [
+        registers.b,
+        registers.c,
+        registers.d,
+        registers.e,
+        registers.h,
+        registers.l,
+    ] == [3, 5, 8, 13, 21, 34]
+}
+
+fn run_mooneye(path: &Path) -> Result<(), String> {
+    let mut gameboy = common::headless(path);
+    gameboy.break_on_ld_b_b = true;
+    common::run_until(&mut gameboy, TIMEOUT, |gameboy| gameboy.paused)?;
+    let registers = &gameboy.cpu.registers;
+    match passed(registers) {
+        true => Ok(()),
+        false => Err(format!(
+            "B={:02X} C={:02X} D={:02X} E={:02X} H={:02X} L={:02X}",
+            registers.b, registers.c, registers.d, registers.e, registers.h, registers.l
+        )),
+    }
+}
+
+#[test]
+fn acceptance() {
+    let suite_dir = common::suite_dir(SUITE);
+    common::run_suite(SUITE, |rom| {
+        if !runs_on_dmg(rom) {
+            return Ok(());
+        }
+        let name = rom.strip_prefix(&suite_dir).unwrap_or(rom);
+        let expected_failure = EXPECTED_FAILURES
+            .iter()
+            .any(|failure| Path::new(failure) == name);
+        match (run_mooneye(rom), expected_failure) {
+            (Ok(()), true) => {
+                eprintln!(
+                    "{} passes now, remove it from EXPECTED_FAILURES",
+                    name.display()
+                );
+                Ok(())
+            }
+            (Err(_), true) | (Ok(()), false) => Ok(()),
+            (Err(error), false) => Err(error),
+        }
+    });
+}
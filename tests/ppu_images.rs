@@ -0,0 +1,165 @@
+//! Screen-based test ROMs compared against reference screenshots. A ROM's
+//! reference is the PNG next to it, named like `dmg-acid2-dmg.png` or
+//! `m3_bgp_change_dmg_blob.png`; ROMs without one are skipped. On a mismatch
+//! the actual frame and a diff are written under the target directory.
+
+mod common;
+
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::{Path, PathBuf};
+
+use rust_game_boy_emulator::gameboy::Gameboy;
+use rust_game_boy_emulator::ppu::{SCREEN_HEIGHT, SCREEN_WIDTH};
+
+/// How long a ROM gets to finish drawing when it doesn't signal with `LD B,B`.
+const FRAMES: usize = 60;
+
+const REFERENCE_SUFFIXES: [&str; 4] = ["-dmg.png", "_dmg_blob.png", "-dmg-blob.png", ".png"];
+
+/// ROMs known to render wrong, as path prefixes relative to the test ROM
+/// directory. Mealybug's mode 3 tests change registers mid-scanline, which a
+/// scanline renderer can't show.
+const EXPECTED_FAILURES: &[&str] = &["mealybug-tearoom-tests/ppu/m3_"];
+
+/// Shades from lightest (0) to darkest (3), one per pixel.
+type Shades = Vec<u8>;
+
+fn shade(gray: u8) -> u8 {
+    ((255 - gray as u16 + 42) / 85) as u8
+}
+
+fn reference_for(rom: &Path) -> Option<PathBuf> {
+    let stem = rom.file_stem()?.to_string_lossy();
+    REFERENCE_SUFFIXES
+        .iter()
+        .map(|suffix| rom.with_file_name(format!("{}{}", stem, suffix)))
+        .find(|path| path.is_file())
+}
+
+fn load_png(path: &Path) -> Result<(usize, usize, Shades), String> {
+    let mut decoder = png::Decoder::new(File::open(path).map_err(|error| error.to_string())?);
+    decoder.set_transformations(png::Transformations::normalize_to_color8());
+    let mut reader = decoder.read_info().map_err(|error| error.to_string())?;
+    let mut buffer = vec![0; reader.output_buffer_size()];
+    let info = reader
+        .next_frame(&mut buffer)
+        .map_err(|error| error.to_string())?;
+    let channels = info.color_type.samples();
+    let shades = buffer[..info.buffer_size()]
+        .chunks(channels)
+        .map(|pixel| match channels {
+            1 | 2 => shade(pixel[0]),
+            _ => shade(((pixel[0] as u16 + pixel[1] as u16 + pixel[2] as u16) / 3) as u8),
+        })
+        .collect();
+    Ok((info.width as usize, info.height as usize, shades))
+}
+
+fn write_png(path: &Path, rgb: &[u8]) {
+    let file = File::create(path).unwrap();
+    let mut encoder = png::Encoder::new(
+        BufWriter::new(file),
+        SCREEN_WIDTH as u32,
+        SCREEN_HEIGHT as u32,
+    );
+    encoder.set_color(png::ColorType::Rgb);
+    encoder.set_depth(png::BitDepth::Eight);
+    let mut writer = encoder.write_header().unwrap();
+    writer.write_image_data(rgb).unwrap();
+}
+
+fn screen(gameboy: &Gameboy) -> Shades {
+    (0..SCREEN_HEIGHT)
+        .flat_map(|y| (0..SCREEN_WIDTH).map(move |x| (x, y)))
+        .map(|(x, y)| shade(gameboy.ppu.pixel(x, y).r))
+        .collect()
+}
+
+/// Writes the actual frame, and a diff with mismatches in red over a faded
+/// copy of the reference.
+fn write_diff(name: &str, actual: &Shades, expected: &Shades) -> PathBuf {
+    let dir = Path::new(env!("CARGO_TARGET_TMPDIR")).join("ppu-diffs");
+    std::fs::create_dir_all(&dir).unwrap();
+    let gray = |shade: u8| 255 - shade * 85;
+
+    let frame: Vec<u8> = actual.iter().flat_map(|&shade| [gray(shade); 3]).collect();
+    write_png(&dir.join(format!("{}-actual.png", name)), &frame);
+
+    let diff: Vec<u8> = actual
+        .iter()
+        .zip(expected)
+        .flat_map(|(&actual, &expected)| match actual == expected {
+            true => [gray(expected) / 4 + 192; 3],
+            false => [0xFF, 0x00, 0x00],
+        })
+        .collect();
+    let path = dir.join(format!("{}-diff.png", name));
+    write_png(&path, &diff);
+    path
+}
+
+fn run_screenshot(rom: &Path, reference: &Path) -> Result<(), String> {
+    let (width, height, expected) = load_png(reference)?;
+    if (width, height) != (SCREEN_WIDTH, SCREEN_HEIGHT) {
+        return Err(format!("reference is {}x{}", width, height));
+    }
+
+    let mut gameboy = common::headless(rom);
+    gameboy.break_on_ld_b_b = true;
+    for _ in 0..FRAMES {
+        gameboy.run_frame().map_err(|error| error.to_string())?;
+        if gameboy.paused {
+            break;
+        }
+    }
+    // let the frame in progress finish drawing
+    gameboy.paused = false;
+    gameboy.break_on_ld_b_b = false;
+    gameboy.run_frame().map_err(|error| error.to_string())?;
+
+    let actual = screen(&gameboy);
+    let wrong = actual.iter().zip(&expected).filter(|(a, e)| a != e).count();
+    if wrong == 0 {
+        return Ok(());
+    }
+    let name = rom.file_stem().unwrap_or_default().to_string_lossy();
+    let diff = write_diff(&name, &actual, &expected);
+    Err(format!("{} pixels differ, see {}", wrong, diff.display()))
+}
+
+fn run_images(suite: &str) {
+    let root = common::suite_dir("");
+    common::run_suite(suite, |rom| {
+        let Some(reference) = reference_for(rom) else {
+            return Ok(());
+        };
+        let name = rom.strip_prefix(&root).unwrap_or(rom).to_string_lossy();
+        let expected_failure = EXPECTED_FAILURES
+            .iter()
+            .any(|prefix| name.starts_with(prefix));
+        match (run_screenshot(rom, &reference), expected_failure) {
+            (Ok(()), true) => {
+                eprintln!("{} passes now, remove it from EXPECTED_FAILURES", name);
+                Ok(())
+            }
+            (Err(_), true) | (Ok(()), false) => Ok(()),
+            (Err(error), false) => Err(error),
+        }
+    });
+}
+
+#[test]
+fn dmg_acid2() {
+    run_images("dmg-acid2");
+}
+
+#[test]
+fn mealybug_tearoom() {
+    run_images("mealybug-tearoom-tests");
+}
+
+#[test]
+fn scribbltests() {
+    run_images("scribbltests");
+}
@@ -0,0 +1,346 @@
+//! The SM83 SingleStepTests: for every opcode, a JSON file of randomised
+//! initial states and the state after executing one instruction. They're read
+//! from `sm83/v1` under the test ROM directory and skipped when missing.
+//! Besides the final state, every read and write has to happen on the same
+//! M-cycle as in the test's `cycles`.
+//!
+//! The tests assume the opcode was already fetched from PC-1 and end after
+//! fetching the next one, so their PC runs one ahead of ours, and so do their
+//! cycles.
+//!
+//! Instructions are run with `Gameboy::run_next_instruction` rather than by
+//! calling `instructions::from_byte` and `from_prefixed_byte` directly: the
+//! opcode fetch, and the second one after a 0xCB prefix, have to go on the bus
+//! like they do on hardware for the cycles to line up. Each case is run once
+//! with `Dispatch::Table`, which calls those tables, and once with
+//! `Dispatch::Match`.
+
+mod common;
+
+use std::cell::RefCell;
+use std::path::Path;
+use std::rc::Rc;
+
+use rust_game_boy_emulator::gameboy::{Dispatch, Gameboy, Timing};
+use rust_game_boy_emulator::hooks::{AccessKind, MemoryAccess};
+use rust_game_boy_emulator::scheduler::Scheduler;
+use serde_json::{json, Value};
+
+const SUITE: &str = "sm83/v1";
+
+const REGISTERS: [&str; 8] = ["a", "b", "c", "d", "e", "f", "h", "l"];
+
+fn number(state: &Value, name: &str) -> u16 {
+    state[name]
+        .as_u64()
+        .unwrap_or_else(|| panic!("missing {}", name)) as u16
+}
+
+/// `[[address, value], ...]`
+fn ram(state: &Value) -> impl Iterator<Item = (u16, u8)> + '_ {
+    state["ram"].as_array().into_iter().flatten().map(|entry| {
+        (
+            entry[0].as_u64().unwrap() as u16,
+            entry[1].as_u64().unwrap() as u8,
+        )
+    })
+}
+
+fn register(gameboy: &Gameboy, name: &str) -> u8 {
+    let registers = &gameboy.cpu.registers;
+    match name {
+        "a" => registers.a,
+        "b" => registers.b,
+        "c" => registers.c,
+        "d" => registers.d,
+        "e" => registers.e,
+        "f" => u8::from(registers.f),
+        "h" => registers.h,
+        "l" => registers.l,
+        _ => unreachable!(),
+    }
+}
+
+/// A bus access: the M-cycle it happened on, counted from the first one after
+/// the opcode fetch, the address, the value and whether it was a write.
+type Cycle = (usize, u16, u8, bool);
+
+/// The accesses in `[[address, value, "r-m"], ...]`, leaving out idle cycles
+/// and the fetch of the next opcode at the end.
+fn expected_cycles(case: &Value) -> Vec<Cycle> {
+    let cycles = case["cycles"].as_array().map_or(&[][..], Vec::as_slice);
+    cycles[..cycles.len().saturating_sub(1)]
+        .iter()
+        .enumerate()
+        .filter_map(|(index, cycle)| {
+            let kind = cycle[2].as_str().unwrap_or("---").as_bytes();
+            let write = kind.get(1) == Some(&b'w');
+            (kind.first() == Some(&b'r') || write).then(|| {
+                (
+                    index,
+                    cycle[0].as_u64().unwrap() as u16,
+                    cycle[1].as_u64().unwrap() as u8,
+                    write,
+                )
+            })
+        })
+        .collect()
+}
+
+fn set_up(initial: &Value, dispatch: Dispatch) -> Gameboy<'static> {
+    let mut gameboy = Gameboy::default();
+    gameboy.bus.flat = true;
+    gameboy.dispatch = dispatch;
+    gameboy.timing = Timing::MCycle;
+    // nothing but the CPU touches memory
+    gameboy.scheduler = Scheduler::default();
+    let registers = &mut gameboy.cpu.registers;
+    registers.a = number(initial, "a") as u8;
+    registers.b = number(initial, "b") as u8;
+    registers.c = number(initial, "c") as u8;
+    registers.d = number(initial, "d") as u8;
+    registers.e = number(initial, "e") as u8;
+    registers.f = (number(initial, "f") as u8).into();
+    registers.h = number(initial, "h") as u8;
+    registers.l = number(initial, "l") as u8;
+    registers.sp = number(initial, "sp");
+    registers.pc = number(initial, "pc").wrapping_sub(1);
+    gameboy.interrupts_enabled = number(initial, "ime") != 0;
+    for (address, value) in ram(initial) {
+        gameboy.write_byte(address, value);
+    }
+    gameboy
+}
+
+/// Runs one test case with both kinds of dispatch, describing the
+/// differences found.
+fn run_case(case: &Value) -> Result<(), String> {
+    for dispatch in [Dispatch::Table, Dispatch::Match] {
+        run_case_with(case, dispatch).map_err(|error| format!("{:?}: {}", dispatch, error))?;
+    }
+    Ok(())
+}
+
+fn run_case_with(case: &Value, dispatch: Dispatch) -> Result<(), String> {
+    let mut gameboy = set_up(&case["initial"], dispatch);
+    let accesses = Rc::new(RefCell::new(Vec::new()));
+    let sink = accesses.clone();
+    gameboy.observer = Some(Box::new(move |access: MemoryAccess| {
+        sink.borrow_mut().push(access)
+    }));
+    let ticks = gameboy.run_next_instruction();
+    gameboy.observer = None;
+
+    let expected = &case["final"];
+    let mut differences = Vec::new();
+    for name in REGISTERS {
+        let (actual, wanted) = (register(&gameboy, name), number(expected, name) as u8);
+        if actual != wanted {
+            differences.push(format!("{}={:02X}, expected {:02X}", name, actual, wanted));
+        }
+    }
+    let registers = &gameboy.cpu.registers;
+    for (name, actual) in [("sp", registers.sp), ("pc", registers.pc.wrapping_add(1))] {
+        let wanted = number(expected, name);
+        if actual != wanted {
+            differences.push(format!("{}={:04X}, expected {:04X}", name, actual, wanted));
+        }
+    }
+    if gameboy.interrupts_enabled != (number(expected, "ime") != 0) {
+        differences.push(format!("ime={}", gameboy.interrupts_enabled));
+    }
+    for (address, wanted) in ram(expected) {
+        let actual = gameboy.read_byte(address);
+        if actual != wanted {
+            differences.push(format!(
+                "[{:04X}]={:02X}, expected {:02X}",
+                address, actual, wanted
+            ));
+        }
+    }
+    let cycles = case["cycles"].as_array().map_or(0, Vec::len);
+    if ticks as usize != cycles * 4 {
+        differences.push(format!("took {} t-states, expected {}", ticks, cycles * 4));
+    }
+    // the first access is the opcode fetch the test treats as already done,
+    // and each access is stamped at the end of its M-cycle
+    let actual: Vec<Cycle> = accesses.borrow()[1..]
+        .iter()
+        .map(|access| {
+            (
+                access.cycle as usize / 4 - 2,
+                access.address,
+                access.value,
+                access.kind == AccessKind::Write,
+            )
+        })
+        .collect();
+    let expected = expected_cycles(case);
+    if actual != expected {
+        differences.push(format!(
+            "bus activity (cycle, address, value, write) {:X?}, expected {:X?}",
+            actual, expected
+        ));
+    }
+
+    match differences.is_empty() {
+        true => Ok(()),
+        false => Err(differences.join(", ")),
+    }
+}
+
+fn run_file(path: &Path) -> Result<(), String> {
+    let text = std::fs::read_to_string(path).map_err(|error| error.to_string())?;
+    let cases: Value = serde_json::from_str(&text).map_err(|error| error.to_string())?;
+    let cases = cases.as_array().ok_or("expected a list of tests")?;
+    let failures: Vec<String> = cases
+        .iter()
+        .filter_map(|case| {
+            run_case(case)
+                .err()
+                .map(|error| format!("{}: {}", case["name"].as_str().unwrap_or("?"), error))
+        })
+        .collect();
+    match failures.first() {
+        None => Ok(()),
+        Some(first) => Err(format!(
+            "{} of {} failed, first {}",
+            failures.len(),
+            cases.len(),
+            first
+        )),
+    }
+}
+
+#[test]
+fn single_step_tests() {
+    let suite_dir = common::suite_dir(SUITE);
+    if !suite_dir.is_dir() {
+        if std::env::var_os("TEST_ROMS_DIR").is_some() {
+            panic!("No tests in {}", suite_dir.display());
+        }
+        eprintln!(
+            "Skipping {}: tests not found, set TEST_ROMS_DIR to run them",
+            SUITE
+        );
+        return;
+    }
+    let mut files: Vec<_> = std::fs::read_dir(&suite_dir)
+        .unwrap()
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| {
+            path.extension()
+                .is_some_and(|extension| extension == "json")
+        })
+        .collect();
+    files.sort();
+    let failures: Vec<String> = files
+        .iter()
+        .filter_map(|file| {
+            run_file(file).err().map(|error| {
+                let name = file.file_stem().unwrap_or_default().to_string_lossy();
+                format!("{}: {}", name, error)
+            })
+        })
+        .collect();
+    assert!(
+        failures.is_empty(),
+        "{} of {} opcodes failed:\n{}",
+        failures.len(),
+        files.len(),
+        failures.join("\n")
+    );
+}
+
+/// A state in the suite's format with every other register zero.
+fn state(pc: u16, sp: u16, f: u8, ram: &[(u16, u8)]) -> Value {
+    let ram: Vec<Value> = ram
+        .iter()
+        .map(|&(address, value)| json!([address, value]))
+        .collect();
+    json!({
+        "pc": pc, "sp": sp, "f": f, "ime": 0, "ram": ram,
+        "a": 0, "b": 0, "c": 0, "d": 0, "e": 0, "h": 0, "l": 0,
+    })
+}
+
+/// Cases in the suite's format for the conditional jumps and calls, whose
+/// operands are read whether or not the branch is taken. They don't need the
+/// suite, so they always run.
+#[test]
+fn conditional_branches() {
+    let read = |address: u16, value: u8| json!([address, value, "r-m"]);
+    let write = |address: u16, value: u8| json!([address, value, "-wm"]);
+    let jp = [
+        (0xC000, 0xC2),
+        (0xC001, 0x34),
+        (0xC002, 0x12),
+        (0xC003, 0x00),
+    ];
+    let jr = [(0xC000, 0x38), (0xC001, 0x10), (0xC002, 0x00)];
+    let call = [
+        (0xC000, 0xCC),
+        (0xC001, 0x34),
+        (0xC002, 0x12),
+        (0xC003, 0x00),
+    ];
+    let call_nc = [
+        (0xC000, 0xD4),
+        (0xC001, 0x34),
+        (0xC002, 0x12),
+        (0x1234, 0x00),
+        (0xCFFE, 0x00),
+        (0xCFFF, 0x00),
+    ];
+    let mut called = call_nc;
+    called[4].1 = 0x03;
+    called[5].1 = 0xC0;
+    let cases = [
+        // JP NZ,$1234 with Z set, then clear
+        json!({
+            "name": "c2 untaken",
+            "initial": state(0xC001, 0xD000, 0x80, &jp),
+            "final": state(0xC004, 0xD000, 0x80, &jp),
+            "cycles": [read(0xC001, 0x34), read(0xC002, 0x12), read(0xC003, 0x00)],
+        }),
+        json!({
+            "name": "c2 taken",
+            "initial": state(0xC001, 0xD000, 0x00, &[jp[0], jp[1], jp[2], (0x1234, 0x00)]),
+            "final": state(0x1235, 0xD000, 0x00, &[jp[0], jp[1], jp[2], (0x1234, 0x00)]),
+            "cycles": [read(0xC001, 0x34), read(0xC002, 0x12), null, read(0x1234, 0x00)],
+        }),
+        // JR C,$10 with C clear
+        json!({
+            "name": "38 untaken",
+            "initial": state(0xC001, 0xD000, 0x00, &jr),
+            "final": state(0xC003, 0xD000, 0x00, &jr),
+            "cycles": [read(0xC001, 0x10), read(0xC002, 0x00)],
+        }),
+        // CALL Z,$1234 with Z clear
+        json!({
+            "name": "cc untaken",
+            "initial": state(0xC001, 0xD000, 0x00, &call),
+            "final": state(0xC004, 0xD000, 0x00, &call),
+            "cycles": [read(0xC001, 0x34), read(0xC002, 0x12), read(0xC003, 0x00)],
+        }),
+        // CALL NC,$1234 with C clear
+        json!({
+            "name": "d4 taken",
+            "initial": state(0xC001, 0xD000, 0x00, &call_nc),
+            "final": state(0x1235, 0xCFFE, 0x00, &called),
+            "cycles": [
+                read(0xC001, 0x34),
+                read(0xC002, 0x12),
+                null,
+                write(0xCFFF, 0xC0),
+                write(0xCFFE, 0x03),
+                read(0x1234, 0x00),
+            ],
+        }),
+    ];
+    for case in cases {
+        if let Err(error) = run_case(&case) {
+            panic!("{}: {}", case["name"], error);
+        }
+    }
+}